@@ -0,0 +1,708 @@
+use crate::lexer::Location;
+use std::fmt::Write;
+use std::ops::Range;
+use turn_utils::position::Position;
+use turn_utils::source_map::{FileId, SourceFile, SourceMap, Span};
+use unicode_width::UnicodeWidthChar;
+
+const TAB: &str = "    ";
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// The severity of a diagnostic.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => GREEN,
+            Severity::Help => BLUE,
+        }
+    }
+}
+
+/// A region of the source code.
+///
+/// The end of a region is exclusive.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum SourceRegion {
+    /// Rows and columns starting at 1, counted as configured for the file.
+    Rows {
+        /// The file of the region.
+        file: FileId,
+        /// The row and column of the first character of the region.
+        start: (usize, usize),
+        /// The row and column after the last character of the region.
+        end: (usize, usize),
+    },
+    /// Byte offsets into the source, resolved to rows and columns when rendered.
+    Span(Span),
+}
+
+impl SourceRegion {
+    /// The file of the region.
+    pub fn file(self) -> FileId {
+        match self {
+            SourceRegion::Rows { file, .. } => file,
+            SourceRegion::Span(span) => span.file,
+        }
+    }
+
+    /// Compute the rows and columns of the start and the end of the region in its file.
+    ///
+    /// Byte offsets past the end of the file are resolved to the end of the file.
+    ///
+    /// # Example
+    /// ```
+    /// use turn::diagnostics::SourceRegion;
+    /// use turn_utils::source_map::{SourceMap, Span};
+    ///
+    /// let mut map = SourceMap::new();
+    /// let file = map.add_file("main.x", "let €x = 1;");
+    /// let region = SourceRegion::Span(Span::new(file, 4, 8));
+    /// assert_eq!(region.resolve(map.file(file)), ((1, 5), (1, 7)));
+    /// ```
+    pub fn resolve(self, file: &SourceFile) -> ((usize, usize), (usize, usize)) {
+        match self {
+            SourceRegion::Rows { start, end, .. } => (start, end),
+            SourceRegion::Span(span) => {
+                let position = |offset: usize| {
+                    let position = file
+                        .position(offset.min(file.source().len()))
+                        .expect("the end of the file has a position");
+                    (position.row, position.col)
+                };
+                (position(span.start), position(span.end))
+            }
+        }
+    }
+}
+
+/// Positions are located in the first file of a source map.
+impl From<Range<Position>> for SourceRegion {
+    fn from(range: Range<Position>) -> SourceRegion {
+        SourceRegion::Rows {
+            file: FileId::default(),
+            start: (range.start.row, range.start.col),
+            end: (range.end.row, range.end.col),
+        }
+    }
+}
+
+/// Positions are located in the first file of a source map.
+impl From<Position> for SourceRegion {
+    fn from(position: Position) -> SourceRegion {
+        SourceRegion::Rows {
+            file: FileId::default(),
+            start: (position.row, position.col),
+            end: (position.row, position.col),
+        }
+    }
+}
+
+impl From<Span> for SourceRegion {
+    fn from(span: Span) -> SourceRegion {
+        SourceRegion::Span(span)
+    }
+}
+
+/// The region of a location is its span.
+impl From<Location> for SourceRegion {
+    fn from(location: Location) -> SourceRegion {
+        SourceRegion::Span(location.into())
+    }
+}
+
+/// A message attached to a region of the source code.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Label {
+    /// The labelled region.
+    pub region: SourceRegion,
+    /// The message displayed under the region. May be empty.
+    pub message: String,
+}
+
+impl Label {
+    /// Create a new label.
+    pub fn new(region: impl Into<SourceRegion>, message: impl Into<String>) -> Label {
+        Label {
+            region: region.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Options for rendering diagnostics.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash)]
+pub struct RenderConfig {
+    /// Use ANSI escape sequences to colour the report.
+    pub color: bool,
+}
+
+/// A compiler-style report of an error or a warning.
+///
+/// # Example
+/// ```
+/// use turn::diagnostics::{Diagnostic, RenderConfig};
+/// use turn_utils::position::Position;
+/// use turn_utils::source_map::SourceMap;
+///
+/// let mut map = SourceMap::new();
+/// map.add_file("main.x", "let x = 1 +;\n");
+/// let diagnostic = Diagnostic::error(
+///     "expected an expression",
+///     Position { row: 1, col: 12, index: 11 }..Position { row: 1, col: 13, index: 12 },
+///     "expected an expression here",
+/// )
+/// .with_label(
+///     Position { row: 1, col: 11, index: 10 }..Position { row: 1, col: 12, index: 11 },
+///     "operator without a right operand",
+/// )
+/// .with_note("operands of binary operators must not be empty");
+///
+/// assert_eq!(
+///     diagnostic.render(&map, RenderConfig::default()),
+///     "\
+/// error: expected an expression
+///  --> main.x:1:12
+///   |
+/// 1 | let x = 1 +;
+///   |            ^ expected an expression here
+///   |           - operator without a right operand
+///   = note: operands of binary operators must not be empty
+/// "
+/// );
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Diagnostic {
+    /// The severity of the diagnostic.
+    pub severity: Severity,
+    /// The main message of the diagnostic.
+    pub message: String,
+    /// The region the diagnostic is about.
+    pub primary: Label,
+    /// Other regions relevant to the diagnostic.
+    pub secondary: Vec<Label>,
+    /// Notes displayed after the source snippet.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Create a new diagnostic with a primary region.
+    pub fn new(
+        severity: Severity,
+        message: impl Into<String>,
+        region: impl Into<SourceRegion>,
+        label: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            primary: Label::new(region, label),
+            secondary: vec![],
+            notes: vec![],
+        }
+    }
+
+    /// Create a new error diagnostic.
+    pub fn error(
+        message: impl Into<String>,
+        region: impl Into<SourceRegion>,
+        label: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message, region, label)
+    }
+
+    /// Create a new warning diagnostic.
+    pub fn warning(
+        message: impl Into<String>,
+        region: impl Into<SourceRegion>,
+        label: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message, region, label)
+    }
+
+    /// Add a secondary labelled region.
+    pub fn with_label(
+        mut self,
+        region: impl Into<SourceRegion>,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        self.secondary.push(Label::new(region, message));
+        self
+    }
+
+    /// Add a note.
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Render the diagnostic with the sources it refers to.
+    ///
+    /// Every file with labelled regions gets its own snippet.
+    /// Regions outside of their file are reported without a snippet.
+    pub fn render(&self, map: &SourceMap, config: RenderConfig) -> String {
+        let mut output = String::new();
+        self.render_to(&mut output, map, config)
+            .expect("writing to a String cannot fail");
+        output
+    }
+
+    /// Render the diagnostic into a writer.
+    pub fn render_to<W: Write>(
+        &self,
+        output: &mut W,
+        map: &SourceMap,
+        config: RenderConfig,
+    ) -> std::fmt::Result {
+        let paint = Painter(config.color);
+        // all displayed labels with their resolved regions, the primary label first
+        let labels: Vec<_> = std::iter::once((&self.primary, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .collect();
+        // the files of the labels in the order of their first labels
+        let mut snippets: Vec<Snippet> = vec![];
+        for &(label, primary) in &labels {
+            let id = label.region.file();
+            let file = map.file(id);
+            let index = match snippets.iter().position(|snippet| snippet.id == id) {
+                Some(index) => index,
+                None => {
+                    snippets.push(Snippet {
+                        id,
+                        file,
+                        labels: vec![],
+                        rows: vec![],
+                    });
+                    snippets.len() - 1
+                }
+            };
+            let (start, end) = label.region.resolve(file);
+            if file.line(start.0).is_some() {
+                let snippet = &mut snippets[index];
+                snippet.labels.push((label, start, end, primary));
+                snippet
+                    .rows
+                    .extend((start.0..=end.0.max(start.0)).filter(|&row| file.line(row).is_some()));
+            }
+        }
+        for snippet in &mut snippets {
+            snippet.rows.sort_unstable();
+            snippet.rows.dedup();
+        }
+        let gutter = snippets
+            .iter()
+            .filter_map(|snippet| snippet.rows.last())
+            .max()
+            .map_or(1, |row| row.to_string().len());
+        let severity = self.severity;
+
+        // header
+        writeln!(
+            output,
+            "{}{}: {}{}{}",
+            paint.start(severity.color()),
+            severity.name(),
+            paint.start(BOLD),
+            self.message,
+            paint.reset()
+        )?;
+
+        for (i, snippet) in snippets.iter().enumerate() {
+            // other files are only shown with labels inside of them
+            let (row, col) = match snippet.labels.first() {
+                Some(&(_, start, _, _)) => start,
+                None if i == 0 => self.primary.region.resolve(snippet.file).0,
+                None => continue,
+            };
+            writeln!(
+                output,
+                "{:gutter$}{}-->{} {}:{}:{}",
+                "",
+                paint.start(BLUE),
+                paint.reset(),
+                snippet.file.name(),
+                row,
+                col,
+                gutter = gutter
+            )?;
+            snippet.render_to(output, paint, severity, gutter)?;
+        }
+
+        // notes
+        for note in &self.notes {
+            writeln!(
+                output,
+                "{:gutter$} {}={} {}note{}: {}",
+                "",
+                paint.start(BLUE),
+                paint.reset(),
+                paint.start(BOLD),
+                paint.reset(),
+                note,
+                gutter = gutter
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A label with the rows and columns of the start and the end of its region,
+/// and whether it is the primary label.
+type ResolvedLabel<'d> = (&'d Label, (usize, usize), (usize, usize), bool);
+
+/// The labels of a diagnostic inside a single file with their resolved regions.
+struct Snippet<'d> {
+    id: FileId,
+    file: &'d SourceFile,
+    labels: Vec<ResolvedLabel<'d>>,
+    /// The displayed rows.
+    rows: Vec<usize>,
+}
+
+impl Snippet<'_> {
+    /// Render the source lines of the snippet with the labels under them.
+    fn render_to<W: Write>(
+        &self,
+        output: &mut W,
+        paint: Painter,
+        severity: Severity,
+        gutter: usize,
+    ) -> std::fmt::Result {
+        if !self.rows.is_empty() {
+            writeln!(
+                output,
+                "{:gutter$} {}|{}",
+                "",
+                paint.start(BLUE),
+                paint.reset(),
+                gutter = gutter
+            )?;
+        }
+        let mut previous = None;
+        for &row in &self.rows {
+            if let Some(previous) = previous {
+                if row > previous + 1 {
+                    writeln!(output, "{}...{}", paint.start(BLUE), paint.reset())?;
+                }
+            }
+            previous = Some(row);
+            let text = self.file.line(row).unwrap_or("");
+            writeln!(
+                output,
+                "{}{:>gutter$} |{} {}",
+                paint.start(BLUE),
+                row,
+                paint.reset(),
+                text.replace('\t', TAB),
+                gutter = gutter
+            )?;
+            for &(label, region_start, region_end, primary) in &self.labels {
+                let end_row = region_end.0.max(region_start.0);
+                if row < region_start.0 || row > end_row {
+                    continue;
+                }
+                let start = if row == region_start.0 {
+                    self.display_column(row, region_start.1)
+                } else {
+                    0
+                };
+                let end = if row == end_row {
+                    self.display_column(row, region_end.1)
+                } else {
+                    display_width(text)
+                }
+                .max(start + 1);
+                let (marker, color) = if primary {
+                    ("^", severity.color())
+                } else {
+                    ("-", BLUE)
+                };
+                let underline = marker.repeat(end - start);
+                let message = if row == end_row && !label.message.is_empty() {
+                    format!(" {}", label.message)
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    output,
+                    "{:gutter$} {}|{} {:start$}{}{}{}{}",
+                    "",
+                    paint.start(BLUE),
+                    paint.reset(),
+                    "",
+                    paint.start(color),
+                    underline,
+                    message,
+                    paint.reset(),
+                    gutter = gutter,
+                    start = start
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute the offset of a column of a row from the start of the rendered line.
+    fn display_column(&self, row: usize, col: usize) -> usize {
+        let text = self.file.line(row).unwrap_or("");
+        let start = self.file.offset(row, 1).unwrap_or(0);
+        match self.file.offset(row, col) {
+            Some(index) => display_width(&text[..(index - start).min(text.len())]),
+            // columns past the end of the line
+            None => {
+                let last = self
+                    .file
+                    .position(start + text.len())
+                    .map_or(1, |end| end.col);
+                display_width(text) + col.saturating_sub(last)
+            }
+        }
+    }
+}
+
+/// Compute the width of a rendered text.
+///
+/// Tabs are rendered as multiple spaces.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| {
+            if c == '\t' {
+                TAB.len()
+            } else {
+                c.width().unwrap_or(0)
+            }
+        })
+        .sum()
+}
+
+/// Optionally emits ANSI escape sequences.
+#[derive(Clone, Copy)]
+struct Painter(bool);
+
+impl Painter {
+    fn start(self, color: &'static str) -> &'static str {
+        if self.0 {
+            color
+        } else {
+            ""
+        }
+    }
+
+    fn reset(self) -> &'static str {
+        self.start(RESET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: (usize, usize), end: (usize, usize)) -> SourceRegion {
+        SourceRegion::Rows {
+            file: FileId::default(),
+            start,
+            end,
+        }
+    }
+
+    fn source(name: &str, source: &str) -> SourceMap {
+        let mut map = SourceMap::new();
+        map.add_file(name, source);
+        map
+    }
+
+    #[test]
+    fn render_multiline_region() {
+        let text = "first\n  { a\n    b }\nlast";
+        let diagnostic =
+            Diagnostic::error("unterminated block", region((2, 3), (3, 8)), "this block")
+                .with_label(region((4, 1), (4, 5)), "follows");
+        assert_eq!(
+            diagnostic.render(&source("<input>", text), RenderConfig::default()),
+            "\
+error: unterminated block
+ --> <input>:2:3
+  |
+2 |   { a
+  |   ^^^
+3 |     b }
+  | ^^^^^^^ this block
+4 | last
+  | ---- follows
+"
+        );
+    }
+
+    #[test]
+    fn render_distant_lines() {
+        let text = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+        let diagnostic = Diagnostic::warning("shadowed", region((11, 1), (11, 2)), "")
+            .with_label(region((2, 1), (2, 2)), "first defined here");
+        assert_eq!(
+            diagnostic.render(&source("f", text), RenderConfig::default()),
+            "\
+warning: shadowed
+  --> f:11:1
+   |
+ 2 | b
+   | - first defined here
+...
+11 | k
+   | ^
+"
+        );
+    }
+
+    #[test]
+    fn render_tabs_and_end_of_input() {
+        let text = "\tx";
+        let diagnostic = Diagnostic::error("unexpected end of input", region((1, 3), (1, 3)), "");
+        assert_eq!(
+            diagnostic.render(&source("<input>", text), RenderConfig::default()),
+            "\
+error: unexpected end of input
+ --> <input>:1:3
+  |
+1 |     x
+  |      ^
+"
+        );
+    }
+
+    #[test]
+    fn render_outside_of_source() {
+        let diagnostic = Diagnostic::error("missing", region((5, 1), (5, 2)), "here")
+            .with_note("the input is empty");
+        assert_eq!(
+            diagnostic.render(&source("<input>", ""), RenderConfig::default()),
+            "\
+error: missing
+ --> <input>:5:1
+  = note: the input is empty
+"
+        );
+    }
+
+    #[test]
+    fn render_color() {
+        let diagnostic = Diagnostic::error("bad", region((1, 1), (1, 2)), "");
+        let rendered = diagnostic.render(&source("<input>", "x"), RenderConfig { color: true });
+        assert!(rendered.starts_with("\x1b[1;31merror: \x1b[1mbad\x1b[0m\n"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn region_from_location() {
+        let file = SourceFile::new("test", "a€c\nd");
+        let mut location = Location::new();
+        location.advance('a');
+        let mut location = location.end();
+        location.advance('€');
+        location.advance('c');
+        let region = SourceRegion::from(location);
+        assert_eq!(region.resolve(&file), ((1, 2), (1, 4)));
+        location.advance('\n');
+        assert_eq!(
+            SourceRegion::from(location.end()).resolve(&file),
+            ((2, 1), (2, 1))
+        );
+        location.span = (9, 9);
+        assert_eq!(
+            SourceRegion::from(location).resolve(&file),
+            ((2, 2), (2, 2))
+        );
+    }
+
+    #[test]
+    fn render_location() {
+        let text = "x = ☃ + 1\n";
+        let mut location = Location::new();
+        "x = ".chars().for_each(|c| location.advance(c));
+        let mut location = location.end();
+        location.advance('☃');
+        let diagnostic = Diagnostic::error("unknown operand", location, "here");
+        assert_eq!(
+            diagnostic.render(&source("<input>", text), RenderConfig::default()),
+            "\
+error: unknown operand
+ --> <input>:1:5
+  |
+1 | x = ☃ + 1
+  |     ^ here
+"
+        );
+    }
+
+    #[test]
+    fn render_multiple_files() {
+        let mut map = SourceMap::new();
+        let main = map.add_file("main.x", "use lib.f;\nf(1, 2)\n");
+        let lib = map.add_file("lib.x", "\n\nfn f(x) {}\n");
+        let diagnostic = Diagnostic::error(
+            "wrong number of arguments",
+            Span::new(main, 11, 18),
+            "two arguments",
+        )
+        .with_label(Span::new(lib, 2, 12), "one parameter")
+        .with_label(Span::new(main, 4, 9), "imported here");
+        assert_eq!(
+            diagnostic.render(&map, RenderConfig::default()),
+            "\
+error: wrong number of arguments
+ --> main.x:2:1
+  |
+1 | use lib.f;
+  |     ----- imported here
+2 | f(1, 2)
+  | ^^^^^^^ two arguments
+ --> lib.x:3:1
+  |
+3 | fn f(x) {}
+  | ---------- one parameter
+"
+        );
+    }
+
+    #[test]
+    fn render_wide_characters() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("<input>", "\"界\u{301}\" + x");
+        let diagnostic = Diagnostic::error("mismatched types", Span::new(file, 10, 11), "")
+            .with_label(Span::new(file, 0, 7), "a string");
+        assert_eq!(
+            diagnostic.render(&map, RenderConfig::default()),
+            "\
+error: mismatched types
+ --> <input>:1:8
+  |
+1 | \"界\u{301}\" + x
+  |        ^
+  | ---- a string
+"
+        );
+    }
+}
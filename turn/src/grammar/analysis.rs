@@ -0,0 +1,524 @@
+use super::indexed::{IndexedGrammar, IndexedSymbol};
+use super::{Grammar, Lookahead, Symbol};
+use fixedbitset::FixedBitSet;
+use std::collections::BTreeSet;
+
+/// The FIRST set of a sequence of symbols.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FirstSet<Terminal: Ord> {
+    /// The terminals that can start a string derived from the sequence.
+    pub terminals: BTreeSet<Terminal>,
+    /// True if the sequence can derive the empty string.
+    pub nullable: bool,
+}
+
+/// Nullable, FIRST and FOLLOW sets of a grammar.
+///
+/// The sets are computed once as a fixed point over all rules
+/// and stored as bitsets over the terminals of the grammar.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Lookahead, Rule, Symbol};
+/// use std::collections::BTreeSet;
+///
+/// // S -> A 'b'; A -> 'a' A | ε
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("S")
+///     .rules(vec![
+///         Rule::new("S", vec![Symbol::Nonterminal("A"), Symbol::Terminal('b')]),
+///         Rule::new("A", vec![Symbol::Terminal('a'), Symbol::Nonterminal("A")]),
+///         Rule::new("A", vec![]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let analysis = grammar.analyze();
+///
+/// assert!(analysis.is_nullable(&"A"));
+/// assert_eq!(analysis.first(&"S"), vec!['a', 'b'].into_iter().collect());
+/// assert_eq!(
+///     analysis.follow(&"A"),
+///     vec![Lookahead::Terminal('b')].into_iter().collect()
+/// );
+/// assert_eq!(
+///     analysis.follow(&"S"),
+///     vec![Lookahead::End].into_iter().collect()
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct GrammarAnalysis<Terminal, Nonterminal> {
+    grammar: IndexedGrammar<Terminal, Nonterminal>,
+    nullable: FixedBitSet,
+    first: Vec<FixedBitSet>,
+    follow: Vec<FixedBitSet>,
+}
+
+impl<Terminal, Nonterminal> Grammar<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Compute the nullable, FIRST and FOLLOW sets of the grammar.
+    pub fn analyze(&self) -> GrammarAnalysis<Terminal, Nonterminal> {
+        GrammarAnalysis::new(self)
+    }
+}
+
+impl<Terminal, Nonterminal> GrammarAnalysis<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Compute the nullable, FIRST and FOLLOW sets of a grammar.
+    pub fn new(grammar: &Grammar<Terminal, Nonterminal>) -> Self {
+        GrammarAnalysis::from_indexed(IndexedGrammar::new(grammar))
+    }
+
+    pub(crate) fn from_indexed(grammar: IndexedGrammar<Terminal, Nonterminal>) -> Self {
+        let nonterminals = grammar.nonterminals.len();
+        let mut analysis = GrammarAnalysis {
+            nullable: grammar.nullable(),
+            first: vec![FixedBitSet::with_capacity(grammar.end() + 1); nonterminals],
+            follow: vec![FixedBitSet::with_capacity(grammar.end() + 1); nonterminals],
+            grammar,
+        };
+        analysis.compute_first();
+        analysis.compute_follow();
+        analysis
+    }
+
+    fn compute_first(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.grammar.rules {
+                let mut first = FixedBitSet::with_capacity(self.grammar.end() + 1);
+                self.indexed_first_of_sequence(&rule.right_hand, &mut first);
+                let set = &mut self.first[rule.left_hand];
+                let count = set.count_ones(..);
+                set.union_with(&first);
+                changed |= set.count_ones(..) != count;
+            }
+        }
+    }
+
+    fn compute_follow(&mut self) {
+        let start = self.grammar.start;
+        let end = self.grammar.end();
+        self.follow[start].insert(end);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.grammar.rules {
+                for (i, symbol) in rule.right_hand.iter().enumerate() {
+                    let nonterminal = match *symbol {
+                        IndexedSymbol::Nonterminal(n) => n,
+                        IndexedSymbol::Terminal(_) => continue,
+                    };
+                    let mut follow = FixedBitSet::with_capacity(end + 1);
+                    if self.indexed_first_of_sequence(&rule.right_hand[i + 1..], &mut follow) {
+                        follow.union_with(&self.follow[rule.left_hand]);
+                    }
+                    let set = &mut self.follow[nonterminal];
+                    let count = set.count_ones(..);
+                    set.union_with(&follow);
+                    changed |= set.count_ones(..) != count;
+                }
+            }
+        }
+    }
+
+    /// Returns true if the nonterminal can derive the empty string.
+    ///
+    /// Nonterminals that do not appear in the grammar are not nullable.
+    pub fn is_nullable(&self, nonterminal: &Nonterminal) -> bool {
+        self.grammar
+            .nonterminal(nonterminal)
+            .is_some_and(|n| self.nullable.contains(n))
+    }
+
+    /// The terminals that can start a string derived from the nonterminal.
+    pub fn first(&self, nonterminal: &Nonterminal) -> BTreeSet<Terminal> {
+        self.grammar
+            .nonterminal(nonterminal)
+            .map(|n| {
+                self.first[n]
+                    .ones()
+                    .map(|t| self.grammar.terminals[t].clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The terminals and the end of input that can follow the nonterminal
+    /// in a sentential form derived from the starting nonterminal.
+    pub fn follow(&self, nonterminal: &Nonterminal) -> BTreeSet<Lookahead<Terminal>> {
+        self.grammar
+            .nonterminal(nonterminal)
+            .map(|n| {
+                self.follow[n]
+                    .ones()
+                    .map(|t| self.grammar.lookahead(t))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Compute the FIRST set of a sequence of symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+    ///
+    /// // S -> A B; A -> 'a' | ε; B -> 'b' | ε
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("S")
+    ///     .rules(vec![
+    ///         Rule::new("S", vec![Symbol::Nonterminal("A"), Symbol::Nonterminal("B")]),
+    ///         Rule::new("A", vec![Symbol::Terminal('a')]),
+    ///         Rule::new("A", vec![]),
+    ///         Rule::new("B", vec![Symbol::Terminal('b')]),
+    ///         Rule::new("B", vec![]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .build()
+    ///     .unwrap();
+    /// let analysis = grammar.analyze();
+    ///
+    /// let first = analysis.first_of_sequence(&[Symbol::Nonterminal("A"), Symbol::Nonterminal("B")]);
+    /// assert_eq!(first.terminals, vec!['a', 'b'].into_iter().collect());
+    /// assert!(first.nullable);
+    ///
+    /// let first = analysis.first_of_sequence(&[Symbol::Nonterminal("B"), Symbol::Terminal('c')]);
+    /// assert_eq!(first.terminals, vec!['b', 'c'].into_iter().collect());
+    /// assert!(!first.nullable);
+    /// ```
+    pub fn first_of_sequence(
+        &self,
+        symbols: &[Symbol<Terminal, Nonterminal>],
+    ) -> FirstSet<Terminal> {
+        let mut terminals = BTreeSet::new();
+        for symbol in symbols {
+            match symbol {
+                Symbol::Terminal(t) => {
+                    terminals.insert(t.clone());
+                    return FirstSet {
+                        terminals,
+                        nullable: false,
+                    };
+                }
+                Symbol::Nonterminal(n) => {
+                    terminals.extend(self.first(n));
+                    if !self.is_nullable(n) {
+                        return FirstSet {
+                            terminals,
+                            nullable: false,
+                        };
+                    }
+                }
+            }
+        }
+        FirstSet {
+            terminals,
+            nullable: true,
+        }
+    }
+
+    /// Compute the FIRST_k set of a nonterminal.
+    ///
+    /// See `GrammarAnalysis::first_k_of_sequence`.
+    pub fn first_k(&self, nonterminal: &Nonterminal, k: usize) -> BTreeSet<Vec<Terminal>> {
+        self.first_k_of_sequence(&[Symbol::Nonterminal(nonterminal.clone())], k)
+    }
+
+    /// Compute the FIRST_k set of a sequence of symbols.
+    ///
+    /// The result contains the prefixes of length `k` of all terminal strings derivable from the
+    /// sequence, and all derivable terminal strings shorter than `k`.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+    ///
+    /// // S -> 'a' S 'b' | ε
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("S")
+    ///     .rules(vec![
+    ///         Rule::new(
+    ///             "S",
+    ///             vec![Symbol::Terminal('a'), Symbol::Nonterminal("S"), Symbol::Terminal('b')],
+    ///         ),
+    ///         Rule::new("S", vec![]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .build()
+    ///     .unwrap();
+    /// let analysis = grammar.analyze();
+    ///
+    /// assert_eq!(
+    ///     analysis.first_k(&"S", 2),
+    ///     vec![vec![], vec!['a', 'a'], vec!['a', 'b']].into_iter().collect()
+    /// );
+    /// ```
+    pub fn first_k_of_sequence(
+        &self,
+        symbols: &[Symbol<Terminal, Nonterminal>],
+        k: usize,
+    ) -> BTreeSet<Vec<Terminal>> {
+        let table = self.first_k_table(k);
+        let mut result: BTreeSet<Vec<Terminal>> = std::iter::once(vec![]).collect();
+        for symbol in symbols {
+            let strings = match symbol {
+                Symbol::Terminal(t) => std::iter::once(vec![t.clone()]).collect(),
+                Symbol::Nonterminal(n) => match self.grammar.nonterminal(n) {
+                    Some(n) => table[n]
+                        .iter()
+                        .map(|string| {
+                            string
+                                .iter()
+                                .map(|&t| self.grammar.terminals[t].clone())
+                                .collect()
+                        })
+                        .collect(),
+                    None => BTreeSet::new(),
+                },
+            };
+            result = concatenate_k(&result, &strings, k);
+        }
+        result
+    }
+
+    /// Compute the FIRST_k sets of all nonterminals as a fixed point.
+    fn first_k_table(&self, k: usize) -> Vec<BTreeSet<Vec<usize>>> {
+        let mut table: Vec<BTreeSet<Vec<usize>>> =
+            vec![BTreeSet::new(); self.grammar.nonterminals.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.grammar.rules {
+                let mut strings: BTreeSet<Vec<usize>> = std::iter::once(vec![]).collect();
+                for symbol in &rule.right_hand {
+                    let symbol_strings = match *symbol {
+                        IndexedSymbol::Terminal(t) => std::iter::once(vec![t]).collect(),
+                        IndexedSymbol::Nonterminal(n) => table[n].clone(),
+                    };
+                    strings = concatenate_k(&strings, &symbol_strings, k);
+                    if strings.is_empty() {
+                        break;
+                    }
+                }
+                let set = &mut table[rule.left_hand];
+                let count = set.len();
+                set.extend(strings);
+                changed |= set.len() != count;
+            }
+        }
+        table
+    }
+
+    /// The indexed grammar the sets were computed for.
+    pub(crate) fn indexed_grammar(&self) -> &IndexedGrammar<Terminal, Nonterminal> {
+        &self.grammar
+    }
+
+    /// The FOLLOW set of an indexed nonterminal.
+    pub(crate) fn indexed_follow(&self, nonterminal: usize) -> &FixedBitSet {
+        &self.follow[nonterminal]
+    }
+
+    /// Add the FIRST set of an indexed sequence to a bitset.
+    ///
+    /// Returns true if the sequence is nullable.
+    pub(crate) fn indexed_first_of_sequence(
+        &self,
+        symbols: &[IndexedSymbol],
+        first: &mut FixedBitSet,
+    ) -> bool {
+        for symbol in symbols {
+            match *symbol {
+                IndexedSymbol::Terminal(t) => {
+                    first.insert(t);
+                    return false;
+                }
+                IndexedSymbol::Nonterminal(n) => {
+                    first.union_with(&self.first[n]);
+                    if !self.nullable.contains(n) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Concatenate two sets of strings, truncating the results to `k` symbols.
+fn concatenate_k<T: Ord + Clone>(
+    prefixes: &BTreeSet<Vec<T>>,
+    suffixes: &BTreeSet<Vec<T>>,
+    k: usize,
+) -> BTreeSet<Vec<T>> {
+    let mut result = BTreeSet::new();
+    for prefix in prefixes {
+        if prefix.len() >= k {
+            if !suffixes.is_empty() {
+                result.insert(prefix.clone());
+            }
+            continue;
+        }
+        for suffix in suffixes {
+            let mut string = prefix.clone();
+            string.extend(suffix.iter().take(k - prefix.len()).cloned());
+            result.insert(string);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule};
+
+    type TestGrammar = Grammar<&'static str, &'static str>;
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn set<T: Ord + Clone>(items: &[T]) -> BTreeSet<T> {
+        items.iter().cloned().collect()
+    }
+
+    fn lookaheads(items: &[&'static str]) -> BTreeSet<Lookahead<&'static str>> {
+        items
+            .iter()
+            .map(|&t| {
+                if t == "$" {
+                    Lookahead::End
+                } else {
+                    Lookahead::Terminal(t)
+                }
+            })
+            .collect()
+    }
+
+    /// The LL(1) expression grammar from the dragon book (4.28).
+    fn expression_grammar() -> TestGrammar {
+        GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![n("T"), n("E'")]),
+                Rule::new("E'", vec![t("+"), n("T"), n("E'")]),
+                Rule::new("E'", vec![]),
+                Rule::new("T", vec![n("F"), n("T'")]),
+                Rule::new("T'", vec![t("*"), n("F"), n("T'")]),
+                Rule::new("T'", vec![]),
+                Rule::new("F", vec![t("("), n("E"), t(")")]),
+                Rule::new("F", vec![t("id")]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn expression_grammar_sets() {
+        let analysis = expression_grammar().analyze();
+
+        assert!(!analysis.is_nullable(&"E"));
+        assert!(analysis.is_nullable(&"E'"));
+        assert!(!analysis.is_nullable(&"T"));
+        assert!(analysis.is_nullable(&"T'"));
+        assert!(!analysis.is_nullable(&"F"));
+
+        assert_eq!(analysis.first(&"E"), set(&["(", "id"]));
+        assert_eq!(analysis.first(&"T"), set(&["(", "id"]));
+        assert_eq!(analysis.first(&"F"), set(&["(", "id"]));
+        assert_eq!(analysis.first(&"E'"), set(&["+"]));
+        assert_eq!(analysis.first(&"T'"), set(&["*"]));
+
+        assert_eq!(analysis.follow(&"E"), lookaheads(&[")", "$"]));
+        assert_eq!(analysis.follow(&"E'"), lookaheads(&[")", "$"]));
+        assert_eq!(analysis.follow(&"T"), lookaheads(&["+", ")", "$"]));
+        assert_eq!(analysis.follow(&"T'"), lookaheads(&["+", ")", "$"]));
+        assert_eq!(analysis.follow(&"F"), lookaheads(&["+", "*", ")", "$"]));
+    }
+
+    #[test]
+    fn sequence_first() {
+        let analysis = expression_grammar().analyze();
+
+        let first = analysis.first_of_sequence(&[n("T'"), n("E'")]);
+        assert_eq!(first.terminals, set(&["*", "+"]));
+        assert!(first.nullable);
+
+        let first = analysis.first_of_sequence(&[n("E'"), t(")"), n("T")]);
+        assert_eq!(first.terminals, set(&["+", ")"]));
+        assert!(!first.nullable);
+
+        let first = analysis.first_of_sequence(&[]);
+        assert!(first.terminals.is_empty());
+        assert!(first.nullable);
+    }
+
+    #[test]
+    fn first_k_sets() {
+        let analysis = expression_grammar().analyze();
+        let strings = |items: &[&[&'static str]]| {
+            items
+                .iter()
+                .map(|string| string.to_vec())
+                .collect::<BTreeSet<_>>()
+        };
+
+        assert_eq!(
+            analysis.first_k(&"E'", 2),
+            strings(&[&[], &["+", "("], &["+", "id"]])
+        );
+        assert_eq!(
+            analysis.first_k(&"E", 2),
+            strings(&[
+                &["id"],
+                &["id", "+"],
+                &["id", "*"],
+                &["(", "("],
+                &["(", "id"],
+            ])
+        );
+        assert_eq!(analysis.first_k(&"F", 1), strings(&[&["("], &["id"]]));
+        assert_eq!(
+            analysis.first_k_of_sequence(&[n("T'"), t(")")], 2),
+            strings(&[&[")"], &["*", "("], &["*", "id"]])
+        );
+    }
+
+    #[test]
+    fn unproductive_nonterminals() {
+        // A -> 'a' A has no finite derivations
+        let grammar: TestGrammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![t("s")]),
+                Rule::new("S", vec![n("A")]),
+                Rule::new("A", vec![t("a"), n("A")]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let analysis = grammar.analyze();
+
+        assert_eq!(analysis.first(&"A"), set(&["a"]));
+        assert_eq!(analysis.first_k(&"A", 2), BTreeSet::new());
+        assert_eq!(
+            analysis.first_k(&"S", 2),
+            [vec!["s"]].iter().cloned().collect()
+        );
+        assert_eq!(analysis.follow(&"A"), lookaheads(&["$"]));
+    }
+}
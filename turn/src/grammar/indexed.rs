@@ -0,0 +1,206 @@
+use super::{Associativity, Grammar, Lookahead, Symbol};
+use fixedbitset::FixedBitSet;
+use std::collections::BTreeSet;
+
+/// A grammar symbol represented by the index of its terminal or nonterminal.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub(crate) enum IndexedSymbol {
+    Terminal(usize),
+    Nonterminal(usize),
+}
+
+/// A grammar rule with indexed symbols.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct IndexedRule {
+    pub left_hand: usize,
+    pub right_hand: Vec<IndexedSymbol>,
+    pub precedence: Option<usize>,
+}
+
+/// A grammar with terminals and nonterminals replaced by their indices.
+///
+/// Terminals and nonterminals are numbered in their sorted order.
+/// The end of input is represented by the terminal index `terminals.len()`,
+/// so terminal sets are bitsets of `terminals.len() + 1` bits.
+/// Rules keep the indices they have in the original grammar;
+/// the augmented start rule `S' -> S` has the index `rules.len()`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct IndexedGrammar<Terminal, Nonterminal> {
+    pub terminals: Vec<Terminal>,
+    pub nonterminals: Vec<Nonterminal>,
+    pub rules: Vec<IndexedRule>,
+    pub start: usize,
+    /// The right-hand side of the augmented start rule.
+    pub augmented_right_hand: Vec<IndexedSymbol>,
+    /// The rules of each nonterminal.
+    pub rules_of: Vec<Vec<usize>>,
+    /// The precedence level and associativity of each terminal;
+    /// lower levels have higher precedence.
+    pub precedence: Vec<Option<(usize, Associativity)>>,
+    /// The error terminal, if the rules use it.
+    pub error: Option<usize>,
+    /// The display name of each terminal.
+    pub names: Vec<Option<String>>,
+}
+
+impl<Terminal, Nonterminal> IndexedGrammar<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    pub fn new(grammar: &Grammar<Terminal, Nonterminal>) -> Self {
+        let mut terminals = BTreeSet::new();
+        let mut nonterminals = BTreeSet::new();
+        nonterminals.insert(grammar.starting_nonterminal.clone());
+        for rule in &grammar.rules {
+            nonterminals.insert(rule.left_hand.clone());
+            for symbol in &rule.right_hand {
+                match symbol {
+                    Symbol::Terminal(t) => terminals.insert(t.clone()),
+                    Symbol::Nonterminal(n) => nonterminals.insert(n.clone()),
+                };
+            }
+            terminals.extend(rule.precedence.iter().cloned());
+        }
+        for (_, level) in &grammar.symbol_precedence {
+            terminals.extend(level.iter().cloned());
+        }
+        let mut indexed = IndexedGrammar {
+            terminals: terminals.into_iter().collect(),
+            nonterminals: nonterminals.into_iter().collect(),
+            rules: vec![],
+            start: 0,
+            augmented_right_hand: vec![],
+            rules_of: vec![],
+            precedence: vec![],
+            error: None,
+            names: vec![],
+        };
+        indexed.start = indexed.nonterminal(&grammar.starting_nonterminal).unwrap();
+        indexed.augmented_right_hand = vec![IndexedSymbol::Nonterminal(indexed.start)];
+        indexed.rules_of = vec![vec![]; indexed.nonterminals.len()];
+        indexed.precedence = vec![None; indexed.terminals.len()];
+        indexed.error = grammar
+            .error_terminal
+            .as_ref()
+            .and_then(|t| indexed.terminal(t));
+        indexed.names = vec![None; indexed.terminals.len()];
+        for (terminal, name) in &grammar.terminal_names {
+            if let Some(terminal) = indexed.terminal(terminal) {
+                indexed.names[terminal].get_or_insert_with(|| name.clone());
+            }
+        }
+        for (level, (associativity, terminals)) in grammar.symbol_precedence.iter().enumerate() {
+            for terminal in terminals {
+                let terminal = indexed.terminal(terminal).unwrap();
+                let precedence = &mut indexed.precedence[terminal];
+                if precedence.is_none() {
+                    *precedence = Some((level, *associativity));
+                }
+            }
+        }
+        indexed.rules = grammar
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let left_hand = indexed.nonterminal(&rule.left_hand).unwrap();
+                indexed.rules_of[left_hand].push(i);
+                IndexedRule {
+                    left_hand,
+                    right_hand: rule
+                        .right_hand
+                        .iter()
+                        .map(|symbol| indexed.symbol(symbol).unwrap())
+                        .collect(),
+                    precedence: rule
+                        .precedence
+                        .as_ref()
+                        .map(|t| indexed.terminal(t).unwrap()),
+                }
+            })
+            .collect();
+        indexed
+    }
+
+    pub fn terminal(&self, terminal: &Terminal) -> Option<usize> {
+        self.terminals.binary_search(terminal).ok()
+    }
+
+    pub fn nonterminal(&self, nonterminal: &Nonterminal) -> Option<usize> {
+        self.nonterminals.binary_search(nonterminal).ok()
+    }
+
+    pub fn lookahead_index(&self, lookahead: &Lookahead<Terminal>) -> Option<usize> {
+        match lookahead {
+            Lookahead::Terminal(t) => self.terminal(t),
+            Lookahead::End => Some(self.end()),
+        }
+    }
+
+    pub fn symbol(&self, symbol: &Symbol<Terminal, Nonterminal>) -> Option<IndexedSymbol> {
+        match symbol {
+            Symbol::Terminal(t) => self.terminal(t).map(IndexedSymbol::Terminal),
+            Symbol::Nonterminal(n) => self.nonterminal(n).map(IndexedSymbol::Nonterminal),
+        }
+    }
+}
+
+impl<Terminal, Nonterminal> IndexedGrammar<Terminal, Nonterminal> {
+    /// The index of the end of input marker.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.terminals.len()
+    }
+
+    /// The index of the augmented start rule.
+    #[inline]
+    pub fn augmented_rule(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// The set of nonterminals that can derive the empty string.
+    pub fn nullable(&self) -> FixedBitSet {
+        let mut nullable = FixedBitSet::with_capacity(self.nonterminals.len());
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.rules {
+                if !nullable.contains(rule.left_hand)
+                    && rule.right_hand.iter().all(|symbol| match *symbol {
+                        IndexedSymbol::Terminal(_) => false,
+                        IndexedSymbol::Nonterminal(n) => nullable.contains(n),
+                    })
+                {
+                    nullable.insert(rule.left_hand);
+                    changed = true;
+                }
+            }
+        }
+        nullable
+    }
+
+    /// The right-hand side of a rule, including the augmented start rule.
+    pub fn right_hand(&self, rule: usize) -> &[IndexedSymbol] {
+        match self.rules.get(rule) {
+            Some(rule) => &rule.right_hand,
+            None => &self.augmented_right_hand,
+        }
+    }
+}
+
+impl<Terminal: Clone, Nonterminal: Clone> IndexedGrammar<Terminal, Nonterminal> {
+    pub fn lookahead(&self, index: usize) -> Lookahead<Terminal> {
+        match self.terminals.get(index) {
+            Some(t) => Lookahead::Terminal(t.clone()),
+            None => Lookahead::End,
+        }
+    }
+
+    pub fn original_symbol(&self, symbol: IndexedSymbol) -> Symbol<Terminal, Nonterminal> {
+        match symbol {
+            IndexedSymbol::Terminal(t) => Symbol::Terminal(self.terminals[t].clone()),
+            IndexedSymbol::Nonterminal(n) => Symbol::Nonterminal(self.nonterminals[n].clone()),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+mod analysis;
+mod indexed;
+mod operators;
+mod validation;
+
+pub use analysis::{FirstSet, GrammarAnalysis};
+pub use operators::{Operator, OperatorError, OperatorPart, Operators};
+pub use validation::GrammarError;
+
+pub(crate) use indexed::{IndexedGrammar, IndexedSymbol};
+
+use derive_builder::Builder;
+
+/// A single grammar symbol.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Symbol<Terminal, Nonterminal> {
+    Nonterminal(Nonterminal),
+    Terminal(Terminal),
+}
+
+/// A terminal or the end of input, used in FOLLOW and lookahead sets.
+///
+/// The end of input is ordered after all terminals.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Lookahead<Terminal> {
+    Terminal(Terminal),
+    End,
+}
+
+/// Associativity of a terminal or a group of terminals.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Associativity {
+    None,
+    Left,
+    Right,
+}
+
+/// The number of conflicts a grammar is expected to have, like yacc's `%expect` and `%expect-rr`.
+///
+/// `None` leaves a kind of conflicts unchecked. Once a grammar declares any expected conflicts,
+/// the undeclared kinds are expected to have no conflicts.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash)]
+pub struct ExpectedConflicts {
+    pub shift_reduce: Option<usize>,
+    pub reduce_reduce: Option<usize>,
+}
+
+impl ExpectedConflicts {
+    /// Returns true if no expected conflicts are declared.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.shift_reduce.is_none() && self.reduce_reduce.is_none()
+    }
+}
+
+/// How the disambiguation filters of parse forests treat a rule (see `parser::Filters`).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Default)]
+pub enum Preference {
+    #[default]
+    Normal,
+    /// Of the alternatives of an ambiguous node, only those by preferred rules are kept.
+    Prefer,
+    /// Of the alternatives of an ambiguous node, those by avoided rules are removed
+    /// unless all of them are.
+    Avoid,
+    /// The nonterminal of the rule cannot derive anything the rule derives, like
+    /// SDF's `{reject}`: `Identifier -> "if"` rejects the keyword as an identifier.
+    Reject,
+}
+
+/// A single context-free grammar rule.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Rule<Terminal, Nonterminal> {
+    /// The left-hand nonterminal of the rule.
+    left_hand: Nonterminal,
+    // The right-hand side of the rule.
+    right_hand: Vec<Symbol<Terminal, Nonterminal>>,
+    /// The precedence of the rule.
+    ///
+    /// Usually, this will be set to the last terminal of the right-hand side.
+    precedence: Option<Terminal>,
+    /// The number of conflicts involving the rule.
+    expected_conflicts: ExpectedConflicts,
+    /// How disambiguation filters treat the rule.
+    preference: Preference,
+}
+
+impl<Terminal: Clone, Nonterminal> Rule<Terminal, Nonterminal> {
+    pub fn new(
+        left_hand: Nonterminal,
+        right_hand: Vec<Symbol<Terminal, Nonterminal>>,
+    ) -> Rule<Terminal, Nonterminal> {
+        let precedence = Rule::find_precedence(&right_hand);
+        Rule {
+            left_hand,
+            right_hand,
+            precedence,
+            expected_conflicts: ExpectedConflicts::default(),
+            preference: Preference::Normal,
+        }
+    }
+
+    pub fn with_precedence(
+        left_hand: Nonterminal,
+        right_hand: Vec<Symbol<Terminal, Nonterminal>>,
+        precedence: Terminal,
+    ) -> Rule<Terminal, Nonterminal> {
+        Rule {
+            left_hand,
+            right_hand,
+            precedence: Some(precedence),
+            expected_conflicts: ExpectedConflicts::default(),
+            preference: Preference::Normal,
+        }
+    }
+
+    fn find_precedence(right_hand: &[Symbol<Terminal, Nonterminal>]) -> Option<Terminal> {
+        right_hand.iter().rev().find_map(|e| {
+            if let Symbol::Terminal(t) = e {
+                Some(t.clone())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<Terminal, Nonterminal> Rule<Terminal, Nonterminal> {
+    /// Declare the number of conflicts the rule is involved in.
+    ///
+    /// A rule is involved in a conflict if it is reduced, or if the lookahead is shifted
+    /// in one of its items. Conflicts involving a rule with a declaration for their kind
+    /// are not counted for the expected conflicts of the grammar.
+    pub fn expect_conflicts(mut self, expected: ExpectedConflicts) -> Self {
+        self.expected_conflicts = expected;
+        self
+    }
+
+    /// Set how disambiguation filters treat the rule, see `Preference`.
+    pub fn with_preference(mut self, preference: Preference) -> Self {
+        self.preference = preference;
+        self
+    }
+
+    /// The left-hand nonterminal of the rule.
+    #[inline]
+    pub fn left_hand(&self) -> &Nonterminal {
+        &self.left_hand
+    }
+
+    /// The right-hand side of the rule.
+    #[inline]
+    pub fn right_hand(&self) -> &[Symbol<Terminal, Nonterminal>] {
+        &self.right_hand
+    }
+
+    /// The precedence terminal of the rule.
+    #[inline]
+    pub fn precedence(&self) -> Option<&Terminal> {
+        self.precedence.as_ref()
+    }
+
+    /// The number of conflicts the rule is expected to be involved in.
+    #[inline]
+    pub fn expected_conflicts(&self) -> ExpectedConflicts {
+        self.expected_conflicts
+    }
+
+    /// How disambiguation filters treat the rule.
+    #[inline]
+    pub fn preference(&self) -> Preference {
+        self.preference
+    }
+}
+
+/// Context-free grammar
+#[derive(Clone, Builder, PartialEq, Eq, Debug)]
+pub struct Grammar<Terminal, Nonterminal> {
+    // The starting nonterminal of the grammar.
+    starting_nonterminal: Nonterminal,
+    // The rules of this grammar.
+    rules: Vec<Rule<Terminal, Nonterminal>>,
+    // A list of symbol precedence, starting with the highest precedence.
+    symbol_precedence: Vec<(Associativity, Vec<Terminal>)>,
+    // The number of conflicts not involving rules with their own expected conflicts.
+    #[builder(default)]
+    expected_conflicts: ExpectedConflicts,
+    // The terminal used like yacc's `error` token in error recovery rules.
+    #[builder(default, setter(strip_option))]
+    error_terminal: Option<Terminal>,
+    // The names of terminals displayed in syntax errors.
+    #[builder(default)]
+    terminal_names: Vec<(Terminal, String)>,
+    // Pairs of rules `(higher, lower)`, like SDF's priorities `higher > lower`.
+    #[builder(default)]
+    rule_priorities: Vec<(usize, usize)>,
+}
+
+impl<Terminal, Nonterminal> Grammar<Terminal, Nonterminal> {
+    /// The starting nonterminal of the grammar.
+    #[inline]
+    pub fn starting_nonterminal(&self) -> &Nonterminal {
+        &self.starting_nonterminal
+    }
+
+    /// The rules of the grammar.
+    #[inline]
+    pub fn rules(&self) -> &[Rule<Terminal, Nonterminal>] {
+        &self.rules
+    }
+
+    /// The precedence levels of terminals, starting with the highest precedence.
+    #[inline]
+    pub fn symbol_precedence(&self) -> &[(Associativity, Vec<Terminal>)] {
+        &self.symbol_precedence
+    }
+
+    /// The number of conflicts the grammar is expected to have,
+    /// besides those expected by its rules.
+    #[inline]
+    pub fn expected_conflicts(&self) -> ExpectedConflicts {
+        self.expected_conflicts
+    }
+
+    /// The terminal standing for a syntax error, like yacc's `error` token.
+    ///
+    /// Rules with the error terminal in their right-hand side recover from syntax errors
+    /// (see `LrParser::parse_with_recovery`). The lexer never produces the error terminal.
+    #[inline]
+    pub fn error_terminal(&self) -> Option<&Terminal> {
+        self.error_terminal.as_ref()
+    }
+
+    /// The names of terminals displayed in syntax errors, like "`)`" or "identifier".
+    ///
+    /// Terminals without a name are displayed by their `Display` implementation;
+    /// the first name of a terminal is used.
+    #[inline]
+    pub fn terminal_names(&self) -> &[(Terminal, String)] {
+        &self.terminal_names
+    }
+
+    /// The priorities between rules as pairs of indices `(higher, lower)`, like `A > B`:
+    /// the disambiguation filters of parse forests (`parser::Filters`) remove
+    /// the trees where a node of the lower rule is a child of a node of the higher rule.
+    #[inline]
+    pub fn rule_priorities(&self) -> &[(usize, usize)] {
+        &self.rule_priorities
+    }
+}
@@ -0,0 +1,521 @@
+//! Operator expressions declared by binding powers.
+
+use super::{Associativity, Rule, Symbol};
+use std::fmt;
+
+/// A part of the right-hand side of an operator.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum OperatorPart<Terminal, Nonterminal> {
+    /// An operand: the expression nonterminal, bound by the operator.
+    Operand,
+    /// Any other symbol, such as the terminals of the operator or
+    /// an enclosed expression (the condition of a ternary, the index of an index).
+    Symbol(Symbol<Terminal, Nonterminal>),
+}
+
+/// An operator of an expression nonterminal.
+///
+/// Operators with a higher binding power bind tighter. The operands at the start
+/// and at the end of an operator are bound by precedence and associativity;
+/// the parts between its terminals are enclosed and unaffected.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Operator<Terminal, Nonterminal> {
+    parts: Vec<OperatorPart<Terminal, Nonterminal>>,
+    binding_power: u32,
+    associativity: Associativity,
+    precedence: Terminal,
+}
+
+impl<Terminal: Clone, Nonterminal> Operator<Terminal, Nonterminal> {
+    /// A prefix operator: `op E`.
+    pub fn prefix(terminal: Terminal, binding_power: u32) -> Self {
+        Operator::mixfix(
+            vec![
+                OperatorPart::Symbol(Symbol::Terminal(terminal)),
+                OperatorPart::Operand,
+            ],
+            binding_power,
+            Associativity::Right,
+        )
+    }
+
+    /// An infix operator: `E op E`.
+    pub fn infix(terminal: Terminal, binding_power: u32, associativity: Associativity) -> Self {
+        Operator::mixfix(
+            vec![
+                OperatorPart::Operand,
+                OperatorPart::Symbol(Symbol::Terminal(terminal)),
+                OperatorPart::Operand,
+            ],
+            binding_power,
+            associativity,
+        )
+    }
+
+    /// A postfix operator: `E op`.
+    pub fn postfix(terminal: Terminal, binding_power: u32) -> Self {
+        Operator::mixfix(
+            vec![
+                OperatorPart::Operand,
+                OperatorPart::Symbol(Symbol::Terminal(terminal)),
+            ],
+            binding_power,
+            Associativity::Left,
+        )
+    }
+
+    /// An operator of any shape, such as a ternary `E '?' E ':' E`,
+    /// a call `E '(' Arguments ')'` or an index `E '[' E ']'`.
+    ///
+    /// The precedence of the operator is given to its first terminal;
+    /// see `Operator::with_precedence` to use another one.
+    ///
+    /// # Panics
+    /// Panics if the parts contain no terminal.
+    pub fn mixfix(
+        parts: Vec<OperatorPart<Terminal, Nonterminal>>,
+        binding_power: u32,
+        associativity: Associativity,
+    ) -> Self {
+        let precedence = parts
+            .iter()
+            .find_map(|part| match part {
+                OperatorPart::Symbol(Symbol::Terminal(t)) => Some(t.clone()),
+                _ => None,
+            })
+            .expect("an operator has a terminal");
+        Operator {
+            parts,
+            binding_power,
+            associativity,
+            precedence,
+        }
+    }
+
+    /// Declare the precedence of the operator on another terminal, like yacc's `%prec`.
+    ///
+    /// A terminal has a single precedence, so an operator sharing its terminal with another
+    /// operator of a different binding power (a prefix `-` binding tighter than the infix `-`)
+    /// needs a terminal of its own, which does not have to occur in the input.
+    pub fn with_precedence(mut self, terminal: Terminal) -> Self {
+        self.precedence = terminal;
+        self
+    }
+
+    /// The right-hand side of the operator.
+    #[inline]
+    pub fn parts(&self) -> &[OperatorPart<Terminal, Nonterminal>] {
+        &self.parts
+    }
+
+    #[inline]
+    pub fn binding_power(&self) -> u32 {
+        self.binding_power
+    }
+
+    #[inline]
+    pub fn associativity(&self) -> Associativity {
+        self.associativity
+    }
+
+    /// The terminal carrying the precedence of the operator.
+    #[inline]
+    pub fn precedence(&self) -> &Terminal {
+        &self.precedence
+    }
+}
+
+/// An error returned by `Operators::symbol_precedence`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OperatorError<Terminal> {
+    /// Operators of the same binding power have different associativities.
+    MixedAssociativities {
+        binding_power: u32,
+        associativities: (Associativity, Associativity),
+    },
+    /// Operators of different binding powers have the same precedence terminal
+    /// (see `Operator::with_precedence`).
+    SharedPrecedence {
+        terminal: Terminal,
+        binding_powers: (u32, u32),
+    },
+}
+
+impl<Terminal: fmt::Debug> fmt::Display for OperatorError<Terminal> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatorError::MixedAssociativities {
+                binding_power,
+                associativities: (first, second),
+            } => write!(
+                f,
+                "operators of binding power {} have different associativities, {:?} and {:?}",
+                binding_power, first, second
+            ),
+            OperatorError::SharedPrecedence {
+                terminal,
+                binding_powers: (first, second),
+            } => write!(
+                f,
+                "the precedence terminal {:?} is used at binding powers {} and {}",
+                terminal, first, second
+            ),
+        }
+    }
+}
+
+impl<Terminal: fmt::Debug> std::error::Error for OperatorError<Terminal> {}
+
+/// The operators of an expression nonterminal, turned into rules and precedences.
+///
+/// Instead of a nonterminal per precedence level, every operator becomes a single rule
+/// of the expression nonterminal, and the binding powers and associativities become
+/// precedence levels (`Grammar::symbol_precedence`) that resolve the conflicts of the
+/// rules like in yacc. The rules are ambiguous on their own: LR tables resolve them by
+/// the precedences, `parser::Filters` disambiguate the forests of the generalized parsers.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{Associativity, GrammarBuilder, Operator, Operators, Rule, Symbol};
+/// use turn::lr::{Algorithm, ParseTable};
+/// use turn::parser::{Callbacks, Locations};
+/// use turn::{Location, Token};
+///
+/// // `-` is also a prefix operator, binding tighter than `*` but not than `^`
+/// let operators = Operators::new("E")
+///     .with(Operator::infix('+', 10, Associativity::Left))
+///     .with(Operator::infix('-', 10, Associativity::Left))
+///     .with(Operator::infix('*', 20, Associativity::Left))
+///     .with(Operator::prefix('-', 25).with_precedence('~'))
+///     .with(Operator::infix('^', 30, Associativity::Right));
+///
+/// let mut rules = vec![Rule::new("E", vec![Symbol::Terminal('n')])];
+/// rules.extend(operators.rules());
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("E")
+///     .rules(rules)
+///     .symbol_precedence(operators.symbol_precedence().unwrap())
+///     .build()
+///     .unwrap();
+/// let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+///
+/// let source = "-2^2-3*-2";
+/// let tokens = source.char_indices().map(|(i, c)| {
+///     let symbol = if c.is_ascii_digit() { 'n' } else { c };
+///     let mut location = Location::new();
+///     location.span = (i, i + 1);
+///     Ok(Token { symbol, attribute: &source[i..i + 1], location })
+/// });
+/// let mut actions = Callbacks {
+///     shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+///     reduce: |rule, values: std::vec::Drain<i64>, _: Locations| {
+///         let values: Vec<i64> = values.collect();
+///         match rule {
+///             1 => values[0] + values[2],
+///             2 => values[0] - values[2],
+///             3 => values[0] * values[2],
+///             4 => -values[1],
+///             5 => values[0].pow(values[2] as u32),
+///             _ => values[0],
+///         }
+///     },
+/// };
+/// assert_eq!(turn::parse(&table, tokens, &mut actions), Ok(2));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Operators<Terminal, Nonterminal> {
+    expression: Nonterminal,
+    operators: Vec<Operator<Terminal, Nonterminal>>,
+}
+
+impl<Terminal, Nonterminal> Operators<Terminal, Nonterminal>
+where
+    Terminal: Clone,
+    Nonterminal: Clone,
+{
+    /// No operators of an expression nonterminal.
+    pub fn new(expression: Nonterminal) -> Self {
+        Operators {
+            expression,
+            operators: vec![],
+        }
+    }
+
+    /// Add an operator.
+    pub fn with(mut self, operator: Operator<Terminal, Nonterminal>) -> Self {
+        self.operators.push(operator);
+        self
+    }
+
+    #[inline]
+    pub fn expression(&self) -> &Nonterminal {
+        &self.expression
+    }
+
+    #[inline]
+    pub fn operators(&self) -> &[Operator<Terminal, Nonterminal>] {
+        &self.operators
+    }
+
+    /// The rules of the operators, in the order of their declarations.
+    ///
+    /// Every rule has the precedence of its operator (`Operator::precedence`).
+    pub fn rules(&self) -> Vec<Rule<Terminal, Nonterminal>> {
+        self.operators
+            .iter()
+            .map(|operator| {
+                let right_hand = operator
+                    .parts
+                    .iter()
+                    .map(|part| match part {
+                        OperatorPart::Operand => Symbol::Nonterminal(self.expression.clone()),
+                        OperatorPart::Symbol(symbol) => symbol.clone(),
+                    })
+                    .collect();
+                Rule::with_precedence(
+                    self.expression.clone(),
+                    right_hand,
+                    operator.precedence.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// The precedence levels of the operators, from the highest binding power.
+    ///
+    /// Operators of the same binding power share a level with their associativity.
+    /// The terminals of a level are the precedence terminals of its operators.
+    ///
+    /// Fails if operators of the same binding power have different associativities,
+    /// or if operators of different binding powers have the same precedence terminal.
+    pub fn symbol_precedence(
+        &self,
+    ) -> Result<Vec<(Associativity, Vec<Terminal>)>, OperatorError<Terminal>>
+    where
+        Terminal: PartialEq,
+    {
+        let mut powers: Vec<u32> = self.operators.iter().map(|o| o.binding_power).collect();
+        powers.sort_unstable_by(|a, b| b.cmp(a));
+        powers.dedup();
+        for operator in &self.operators {
+            let power = operator.binding_power;
+            for other in &self.operators {
+                if other.binding_power == power && other.associativity != operator.associativity {
+                    return Err(OperatorError::MixedAssociativities {
+                        binding_power: power,
+                        associativities: (operator.associativity, other.associativity),
+                    });
+                }
+                if other.precedence == operator.precedence && other.binding_power != power {
+                    return Err(OperatorError::SharedPrecedence {
+                        terminal: operator.precedence.clone(),
+                        binding_powers: (power, other.binding_power),
+                    });
+                }
+            }
+        }
+        Ok(powers
+            .into_iter()
+            .map(|power| {
+                let operators = self.operators.iter().filter(|o| o.binding_power == power);
+                let mut terminals: Vec<Terminal> = vec![];
+                for operator in operators.clone() {
+                    if !terminals.contains(&operator.precedence) {
+                        terminals.push(operator.precedence.clone());
+                    }
+                }
+                let associativity = operators.map(|o| o.associativity).next().unwrap();
+                (associativity, terminals)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Grammar, GrammarBuilder};
+    use crate::lexer::Token;
+    use crate::lr::{Algorithm, ParseTable, PrecedenceWarning};
+    use crate::parser::{tokens, Callbacks, EarleyParser, Filters, Locations};
+    use std::vec::Drain;
+
+    fn part(terminal: char) -> OperatorPart<char, &'static str> {
+        OperatorPart::Symbol(Symbol::Terminal(terminal))
+    }
+
+    fn operators() -> Operators<char, &'static str> {
+        let operand = || OperatorPart::Operand;
+        let enclosed = || OperatorPart::Symbol(Symbol::Nonterminal("E"));
+        Operators::new("E")
+            .with(Operator::mixfix(
+                vec![operand(), part('?'), enclosed(), part(':'), operand()],
+                5,
+                Associativity::Right,
+            ))
+            .with(Operator::infix('+', 10, Associativity::Left))
+            .with(Operator::infix('*', 20, Associativity::Left))
+            .with(Operator::prefix('-', 25))
+            .with(Operator::infix('^', 30, Associativity::Right))
+            .with(Operator::postfix('!', 40))
+            .with(Operator::mixfix(
+                vec![operand(), part('['), enclosed(), part(']')],
+                50,
+                Associativity::Left,
+            ))
+    }
+
+    /// E -> '(' E ')' | n, with the operators.
+    fn grammar() -> Grammar<char, &'static str> {
+        let operators = operators();
+        let mut rules = vec![
+            Rule::new("E", vec![Symbol::Terminal('n')]),
+            Rule::new(
+                "E",
+                vec![
+                    Symbol::Terminal('('),
+                    Symbol::Nonterminal("E"),
+                    Symbol::Terminal(')'),
+                ],
+            ),
+        ];
+        rules.extend(operators.rules());
+        GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(rules)
+            .symbol_precedence(operators.symbol_precedence().unwrap())
+            .build()
+            .unwrap()
+    }
+
+    fn value(rule: usize, values: Drain<i64>, _: Locations) -> i64 {
+        let values: Vec<i64> = values.collect();
+        match rule {
+            1 => values[1],
+            2 => match values[0] {
+                0 => values[4],
+                _ => values[2],
+            },
+            3 => values[0] + values[2],
+            4 => values[0] * values[2],
+            5 => -values[1],
+            6 => values[0].pow(values[2] as u32),
+            7 => (1..=values[0]).product(),
+            8 => values[0] * 10 + values[2],
+            _ => values[0],
+        }
+    }
+
+    #[test]
+    fn rules_and_precedence() {
+        let operators = operators();
+        let rules = operators.rules();
+        assert_eq!(rules.len(), 7);
+        // the ternary has the precedence of its first terminal, not of the last one
+        assert_eq!(rules[0].precedence(), Some(&'?'));
+        assert_eq!(rules[6].precedence(), Some(&'['));
+        assert_eq!(
+            operators.symbol_precedence(),
+            Ok(vec![
+                (Associativity::Left, vec!['[']),
+                (Associativity::Left, vec!['!']),
+                (Associativity::Right, vec!['^']),
+                (Associativity::Right, vec!['-']),
+                (Associativity::Left, vec!['*']),
+                (Associativity::Left, vec!['+']),
+                (Associativity::Right, vec!['?']),
+            ])
+        );
+        let minus = Operator::<char, &str>::prefix('-', 25).with_precedence('~');
+        assert_eq!(minus.precedence(), &'~');
+        assert_eq!(minus.parts()[0], part('-'));
+    }
+
+    #[test]
+    fn lr_parsing() {
+        let table = ParseTable::new(&grammar(), Algorithm::Lalr1).unwrap();
+        // only the associativities of the operators with a single open side are unused
+        assert_eq!(
+            table.precedence_warnings(),
+            &[
+                PrecedenceWarning::UselessAssociativity('['),
+                PrecedenceWarning::UselessAssociativity('!'),
+                PrecedenceWarning::UselessAssociativity('-'),
+            ]
+        );
+        let mut actions = Callbacks {
+            shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+            reduce: value,
+        };
+        let mut evaluate = |input| crate::parse(&table, tokens(input), &mut actions).unwrap();
+        assert_eq!(evaluate("2+3*4"), 14);
+        assert_eq!(evaluate("2^3^2"), 512);
+        assert_eq!(evaluate("-2^2"), -4);
+        assert_eq!(evaluate("-2*3+1"), -5);
+        assert_eq!(evaluate("3!+1"), 7);
+        assert_eq!(evaluate("2*3!"), 12);
+        assert_eq!(evaluate("1+2[3]"), 24);
+        assert_eq!(evaluate("(1+2)[3]"), 33);
+        assert_eq!(evaluate("1?2:0?3:4"), 2);
+        assert_eq!(evaluate("0?2:0?3:4"), 4);
+        assert_eq!(evaluate("1+0?5:6"), 5);
+        assert_eq!(evaluate("0?1+1:2*3"), 6);
+    }
+
+    #[test]
+    fn filtered_forests() {
+        let grammar = grammar();
+        let evaluate = |input| {
+            let mut forest = EarleyParser::new(&grammar).parse(tokens(input)).unwrap();
+            assert!(Filters::new(&grammar).apply(&mut forest));
+            let tree = forest.tree(|_, _, _| unreachable!()).unwrap();
+            let mut actions = Callbacks {
+                shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+                reduce: value,
+            };
+            forest.evaluate(&tree, &mut actions)
+        };
+        assert_eq!(evaluate("-2^2+3!*2[1]"), 122);
+        assert_eq!(evaluate("0?1:1?2+2:3"), 4);
+    }
+
+    #[test]
+    fn mixed_associativities() {
+        let error = Operators::<char, &str>::new("E")
+            .with(Operator::infix('+', 10, Associativity::Left))
+            .with(Operator::infix('-', 10, Associativity::Right))
+            .symbol_precedence()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            OperatorError::MixedAssociativities {
+                binding_power: 10,
+                associativities: (Associativity::Left, Associativity::Right),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "operators of binding power 10 have different associativities, Left and Right"
+        );
+    }
+
+    #[test]
+    fn shared_precedence_terminal() {
+        let error = Operators::<char, &str>::new("E")
+            .with(Operator::infix('-', 10, Associativity::Left))
+            .with(Operator::prefix('-', 25))
+            .symbol_precedence()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            OperatorError::SharedPrecedence {
+                terminal: '-',
+                binding_powers: (10, 25),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "the precedence terminal '-' is used at binding powers 10 and 25"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use super::{Grammar, Symbol};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A problem found in a grammar by `Grammar::validate`.
+///
+/// Rules are referenced by their indices in `Grammar::rules`,
+/// precedence levels by their indices in `Grammar::symbol_precedence`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum GrammarError<Terminal, Nonterminal> {
+    /// A nonterminal is used, but no rule defines it.
+    UndefinedNonterminal {
+        nonterminal: Nonterminal,
+        /// The rules using the nonterminal.
+        rules: Vec<usize>,
+    },
+    /// A nonterminal cannot be reached from the starting nonterminal.
+    UnreachableNonterminal {
+        nonterminal: Nonterminal,
+        /// The rules of the nonterminal.
+        rules: Vec<usize>,
+    },
+    /// A nonterminal cannot derive any string of terminals.
+    UnproductiveNonterminal {
+        nonterminal: Nonterminal,
+        /// The rules of the nonterminal.
+        rules: Vec<usize>,
+    },
+    /// Multiple rules have the same left-hand nonterminal and right-hand side.
+    DuplicateRule {
+        /// The identical rules.
+        rules: Vec<usize>,
+    },
+    /// A terminal is listed multiple times in the symbol precedence.
+    DuplicatePrecedence {
+        terminal: Terminal,
+        /// The precedence levels listing the terminal.
+        levels: Vec<usize>,
+    },
+    /// A rule priority refers to a rule that does not exist.
+    UndefinedRule { rule: usize },
+}
+
+impl<Terminal, Nonterminal> Grammar<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Check the grammar for undefined, unreachable and unproductive nonterminals,
+    /// duplicate rules, terminals with multiple precedences and priorities of undefined rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, GrammarError, Rule, Symbol};
+    ///
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("S")
+    ///     .rules(vec![
+    ///         Rule::new("S", vec![Symbol::Terminal('a'), Symbol::Nonterminal("A")]),
+    ///         Rule::new("B", vec![Symbol::Terminal('b')]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     grammar.validate(),
+    ///     Err(vec![
+    ///         GrammarError::UndefinedNonterminal { nonterminal: "A", rules: vec![0] },
+    ///         GrammarError::UnreachableNonterminal { nonterminal: "B", rules: vec![1] },
+    ///         GrammarError::UnproductiveNonterminal { nonterminal: "S", rules: vec![0] },
+    ///     ])
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<GrammarError<Terminal, Nonterminal>>> {
+        let mut errors = vec![];
+        // rules of each nonterminal
+        let mut definitions: BTreeMap<&Nonterminal, Vec<usize>> = BTreeMap::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            definitions.entry(&rule.left_hand).or_default().push(i);
+        }
+
+        // undefined nonterminals
+        let mut undefined: BTreeMap<&Nonterminal, Vec<usize>> = BTreeMap::new();
+        if !definitions.contains_key(&self.starting_nonterminal) {
+            undefined.insert(&self.starting_nonterminal, vec![]);
+        }
+        for (i, rule) in self.rules.iter().enumerate() {
+            for nonterminal in nonterminals(&rule.right_hand) {
+                if !definitions.contains_key(nonterminal) {
+                    let rules = undefined.entry(nonterminal).or_default();
+                    if rules.last() != Some(&i) {
+                        rules.push(i);
+                    }
+                }
+            }
+        }
+        errors.extend(undefined.into_iter().map(|(nonterminal, rules)| {
+            GrammarError::UndefinedNonterminal {
+                nonterminal: nonterminal.clone(),
+                rules,
+            }
+        }));
+
+        // unreachable nonterminals
+        let mut reachable = BTreeSet::new();
+        let mut stack = vec![&self.starting_nonterminal];
+        while let Some(nonterminal) = stack.pop() {
+            if !reachable.insert(nonterminal) {
+                continue;
+            }
+            for &rule in definitions.get(nonterminal).into_iter().flatten() {
+                stack.extend(nonterminals(&self.rules[rule].right_hand));
+            }
+        }
+        errors.extend(
+            definitions
+                .iter()
+                .filter(|(nonterminal, _)| !reachable.contains(*nonterminal))
+                .map(
+                    |(nonterminal, rules)| GrammarError::UnreachableNonterminal {
+                        nonterminal: (*nonterminal).clone(),
+                        rules: rules.clone(),
+                    },
+                ),
+        );
+
+        // unproductive nonterminals
+        let mut productive = BTreeSet::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.rules {
+                if !productive.contains(&rule.left_hand)
+                    && nonterminals(&rule.right_hand).all(|x| productive.contains(x))
+                {
+                    productive.insert(&rule.left_hand);
+                    changed = true;
+                }
+            }
+        }
+        errors.extend(
+            definitions
+                .iter()
+                .filter(|(nonterminal, _)| !productive.contains(*nonterminal))
+                .map(
+                    |(nonterminal, rules)| GrammarError::UnproductiveNonterminal {
+                        nonterminal: (*nonterminal).clone(),
+                        rules: rules.clone(),
+                    },
+                ),
+        );
+
+        // duplicate rules
+        let mut rules: BTreeMap<_, Vec<usize>> = BTreeMap::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            rules
+                .entry((&rule.left_hand, &rule.right_hand))
+                .or_default()
+                .push(i);
+        }
+        let mut duplicates: Vec<_> = rules
+            .into_values()
+            .filter(|rules| rules.len() > 1)
+            .collect();
+        duplicates.sort();
+        errors.extend(
+            duplicates
+                .into_iter()
+                .map(|rules| GrammarError::DuplicateRule { rules }),
+        );
+
+        // terminals with multiple precedences
+        let mut levels: BTreeMap<&Terminal, Vec<usize>> = BTreeMap::new();
+        for (level, (_, terminals)) in self.symbol_precedence.iter().enumerate() {
+            for terminal in terminals {
+                levels.entry(terminal).or_default().push(level);
+            }
+        }
+        errors.extend(
+            levels
+                .into_iter()
+                .filter(|(_, levels)| levels.len() > 1)
+                .map(|(terminal, levels)| GrammarError::DuplicatePrecedence {
+                    terminal: terminal.clone(),
+                    levels,
+                }),
+        );
+
+        // priorities of undefined rules
+        let mut undefined_rules: Vec<usize> = self
+            .rule_priorities
+            .iter()
+            .flat_map(|&(higher, lower)| vec![higher, lower])
+            .filter(|&rule| rule >= self.rules.len())
+            .collect();
+        undefined_rules.sort_unstable();
+        undefined_rules.dedup();
+        errors.extend(
+            undefined_rules
+                .into_iter()
+                .map(|rule| GrammarError::UndefinedRule { rule }),
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Iterate over the nonterminals of a right-hand side.
+fn nonterminals<Terminal, Nonterminal>(
+    right_hand: &[Symbol<Terminal, Nonterminal>],
+) -> impl Iterator<Item = &Nonterminal> {
+    right_hand.iter().filter_map(|symbol| match symbol {
+        Symbol::Nonterminal(nonterminal) => Some(nonterminal),
+        Symbol::Terminal(_) => None,
+    })
+}
+
+impl<Terminal, Nonterminal> fmt::Display for GrammarError<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrammarError::UndefinedNonterminal { nonterminal, rules } => write!(
+                f,
+                "nonterminal {:?} has no rules, but is used in rules {:?}",
+                nonterminal, rules
+            ),
+            GrammarError::UnreachableNonterminal { nonterminal, rules } => write!(
+                f,
+                "nonterminal {:?} is unreachable from the starting nonterminal (rules {:?})",
+                nonterminal, rules
+            ),
+            GrammarError::UnproductiveNonterminal { nonterminal, rules } => write!(
+                f,
+                "nonterminal {:?} cannot derive any string of terminals (rules {:?})",
+                nonterminal, rules
+            ),
+            GrammarError::DuplicateRule { rules } => write!(f, "rules {:?} are identical", rules),
+            GrammarError::DuplicatePrecedence { terminal, levels } => write!(
+                f,
+                "terminal {:?} has multiple precedences (levels {:?})",
+                terminal, levels
+            ),
+            GrammarError::UndefinedRule { rule } => {
+                write!(f, "rule {} of a rule priority does not exist", rule)
+            }
+        }
+    }
+}
+
+impl<Terminal, Nonterminal> std::error::Error for GrammarError<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Associativity, GrammarBuilder, Rule};
+
+    fn t(terminal: char) -> Symbol<char, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<char, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn build_grammar(
+        rules: Vec<Rule<char, &'static str>>,
+        symbol_precedence: Vec<(Associativity, Vec<char>)>,
+    ) -> Grammar<char, &'static str> {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(symbol_precedence)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn valid_grammar() {
+        let grammar = build_grammar(
+            vec![
+                Rule::new("S", vec![n("S"), t('+'), n("S")]),
+                Rule::new("S", vec![t('('), n("S"), t(')')]),
+                Rule::new("S", vec![t('x')]),
+            ],
+            vec![(Associativity::Left, vec!['+'])],
+        );
+        assert_eq!(grammar.validate(), Ok(()));
+    }
+
+    #[test]
+    fn undefined_nonterminals() {
+        let grammar = build_grammar(
+            vec![
+                Rule::new("S", vec![n("A"), n("A")]),
+                Rule::new("S", vec![t('x'), n("A")]),
+                Rule::new("S", vec![]),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            grammar.validate(),
+            Err(vec![GrammarError::UndefinedNonterminal {
+                nonterminal: "A",
+                rules: vec![0, 1]
+            }])
+        );
+
+        let empty = build_grammar(vec![], vec![]);
+        assert_eq!(
+            empty.validate(),
+            Err(vec![GrammarError::UndefinedNonterminal {
+                nonterminal: "S",
+                rules: vec![]
+            }])
+        );
+    }
+
+    #[test]
+    fn unreachable_and_unproductive_nonterminals() {
+        let grammar = build_grammar(
+            vec![
+                Rule::new("S", vec![t('x')]),
+                Rule::new("S", vec![n("L")]),
+                Rule::new("L", vec![t('('), n("L")]),
+                Rule::new("U", vec![n("L")]),
+                Rule::new("U", vec![t('u')]),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            grammar.validate(),
+            Err(vec![
+                GrammarError::UnreachableNonterminal {
+                    nonterminal: "U",
+                    rules: vec![3, 4]
+                },
+                GrammarError::UnproductiveNonterminal {
+                    nonterminal: "L",
+                    rules: vec![2]
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        let grammar = build_grammar(
+            vec![
+                Rule::new("S", vec![t('x')]),
+                Rule::new("S", vec![t('y')]),
+                Rule::with_precedence("S", vec![t('x')], 'y'),
+                Rule::new("S", vec![t('y')]),
+                Rule::new("S", vec![t('x')]),
+            ],
+            vec![
+                (Associativity::Left, vec!['x', 'y']),
+                (Associativity::Right, vec!['z']),
+                (Associativity::None, vec!['x']),
+            ],
+        );
+        assert_eq!(
+            grammar.validate(),
+            Err(vec![
+                GrammarError::DuplicateRule {
+                    rules: vec![0, 2, 4]
+                },
+                GrammarError::DuplicateRule { rules: vec![1, 3] },
+                GrammarError::DuplicatePrecedence {
+                    terminal: 'x',
+                    levels: vec![0, 2]
+                },
+            ])
+        );
+    }
+}
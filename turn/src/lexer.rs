@@ -1,72 +1,87 @@
+use crate::diagnostics::Diagnostic;
+use std::fmt;
 use std::ops::Range;
+use turn_utils::matchers::Matcher;
+pub use turn_utils::position::{ColumnMode, LineBreaks, PositionConfig};
+use turn_utils::source_map::{FileId, Span};
 
 /// A struct containing source location information for a token.
 ///
+/// The span holds byte offsets into the file identified by `file`;
+/// a `SourceMap` with the file resolves them to rows and columns.
+///
+/// A lexer extends a location past every read character. The location of the next token
+/// starts at the end of the previous one.
+///
 /// # Examples
 ///
 /// ```
-/// let loc = turn::Location::new();
-/// assert_eq!(loc.row, 1);
-/// assert_eq!(loc.col, 1);
-/// assert_eq!(loc.filename, Option::None);
+/// use turn_utils::position::Position;
+/// use turn_utils::source_map::SourceMap;
+///
+/// let mut map = SourceMap::new();
+/// let file = map.add_file("stdin", "ab\n€");
 ///
-/// let mut locf = turn::Location::from_file("stdin");
-/// assert_eq!(locf.filename, Option::Some("stdin"));
-/// locf.advance('a');
-/// assert_eq!(locf.row, 1);
-/// assert_eq!(locf.col, 2);
-/// locf.advance('\n');
-/// assert_eq!(locf.row, 2);
-/// assert_eq!(locf.col, 1);
+/// let mut loc = turn::Location::in_file(file);
+/// loc.advance('a');
+/// assert_eq!(loc.span, (0, 1));
+///
+/// let mut token = loc.end();
+/// token.advance('b');
+/// token.advance('\n');
+/// token.advance('€');
+/// assert_eq!(token.span, (1, 6));
+/// assert_eq!(map.name(token.file), "stdin");
+/// assert_eq!(
+///     map.span_positions(token.into()),
+///     Some(Position { row: 1, col: 2, index: 1 }..Position { row: 2, col: 2, index: 6 })
+/// );
 /// ```
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
-pub struct Location<'a> {
-    /// The row number.
-    pub row: u64,
-    /// The column number.
-    pub col: u64,
+pub struct Location {
+    /// The file of the location.
+    pub file: FileId,
+    /// The byte offsets of the start and the end of the location in its file.
     pub span: (usize, usize),
-    /// The name of the originator file (optional).
-    pub filename: Option<&'a str>,
 }
 
-impl<'a> Location<'a> {
-    /// Creates a new location without a filename.
+impl Location {
+    /// Creates a new location at the start of the first file of a source map.
     ///
     /// # Examples
     /// ```
+    /// use turn_utils::source_map::FileId;
+    ///
     /// let loc = turn::Location::new();
-    /// assert_eq!(loc.row, 1);
-    /// assert_eq!(loc.col, 1);
     /// assert_eq!(loc.span, (0, 0));
-    /// assert_eq!(loc.filename, Option::None);
+    /// assert_eq!(loc.file, FileId::default());
     /// ```
     pub fn new() -> Self {
         Location {
-            row: 1,
-            col: 1,
+            file: FileId::default(),
             span: (0, 0),
-            filename: None,
         }
     }
-    /// Creates a new location with a filename.
+    /// Creates a new location at the start of a file.
     ///
     /// # Examples
     /// ```
-    /// let loc = turn::Location::from_file("filename");
-    /// assert_eq!(loc.row, 1);
-    /// assert_eq!(loc.col, 1);
-    /// assert_eq!(loc.filename, Option::Some("filename"));
+    /// use turn_utils::source_map::SourceMap;
+    ///
+    /// let mut map = SourceMap::new();
+    /// map.add_file("first", "");
+    /// let second = map.add_file("second", "");
+    /// let loc = turn::Location::in_file(second);
+    /// assert_eq!(loc.span, (0, 0));
+    /// assert_eq!(loc.file, second);
     /// ```
-    pub fn from_file(filename: &'a str) -> Self {
+    pub fn in_file(file: FileId) -> Self {
         Location {
-            row: 1,
-            col: 1,
-            span: (0, 0),
-            filename: Some(filename),
+            file,
+            ..Location::new()
         }
     }
-    /// Advances the location according to the read character.
+    /// Extends the location past the read character at the end of its span.
     ///
     /// # Examples
     /// ```
@@ -74,22 +89,33 @@ impl<'a> Location<'a> {
     /// for _ in 0..10 {
     ///     loc.advance('👍');
     /// }
-    /// assert_eq!(loc.row, 1);
-    /// assert_eq!(loc.col, 11);
-    /// assert_eq!(loc.span, (0, 10));
+    /// assert_eq!(loc.span, (0, 40));
     /// loc.advance('\n');
-    /// assert_eq!(loc.row, 2);
-    /// assert_eq!(loc.col, 1);
-    /// assert_eq!(loc.span, (0, 11));
+    /// assert_eq!(loc.span, (0, 41));
     /// ```
     pub fn advance(&mut self, c: char) {
-        if c == '\n' {
-            self.row += 1;
-            self.col = 1;
-        } else {
-            self.col += 1;
+        self.span.1 += c.len_utf8();
+    }
+    /// The empty location at the end of this location.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut loc = turn::Location::new();
+    /// loc.advance('a');
+    /// loc.advance('b');
+    /// assert_eq!(loc.end().span, (2, 2));
+    /// ```
+    pub fn end(self) -> Location {
+        Location {
+            span: (self.span.1, self.span.1),
+            ..self
         }
-        self.span.1 += 1;
+    }
+}
+
+impl From<Location> for Span {
+    fn from(location: Location) -> Span {
+        Span::new(location.file, location.span.0, location.span.1)
     }
 }
 
@@ -104,11 +130,176 @@ impl<'a> Location<'a> {
 /// assert_eq!(token.location, Location::new());
 /// ```
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
-pub struct Token<'a, 'b, Symbol> {
+pub struct Token<'a, Symbol> {
     /// The symbol represented by this token.
     pub symbol: Symbol,
     /// The attribute of this token, represented by a slice of its input.
     pub attribute: &'a str,
     /// The location of this token.
-    pub location: Location<'b>,
+    pub location: Location,
+}
+
+/// The kind of a lexical error.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum LexErrorKind {
+    /// The character cannot start or continue any token.
+    UnexpectedCharacter(char),
+    /// The input ended before the current token was completed.
+    UnterminatedToken,
+    /// The token was read, but its attribute could not be converted.
+    InvalidConversion(String),
+    /// A token callback rejected the read token.
+    CallbackRejection(String),
+}
+
+/// An item a lexer would have accepted in place of the erroneous input.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Expected<Symbol> {
+    /// A character matching the matcher.
+    Character(Matcher),
+    /// A complete token.
+    Token(Symbol),
+}
+
+/// A lexical error returned from a lexer.
+///
+/// # Examples
+/// ```
+/// use turn::{Expected, LexError, LexErrorKind, Location};
+/// use turn_utils::matchers::{CharacterCategory, Matcher, SingleMatcher};
+///
+/// let mut location = Location::new();
+/// location.advance('1');
+/// location.span = (1, 2);
+/// let error: LexError<&str> = LexError {
+///     kind: LexErrorKind::UnexpectedCharacter('x'),
+///     location,
+///     expected: vec![
+///         Expected::Character(Matcher::SingleMatcher(SingleMatcher::Category(
+///             CharacterCategory::ASCIIDigit,
+///         ))),
+///         Expected::Token("Number"),
+///     ],
+/// };
+/// assert_eq!(
+///     error.to_string(),
+///     "unexpected character 'x' at byte 1; expected one of <0-9>, Number"
+/// );
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct LexError<Symbol> {
+    /// The kind of the error.
+    pub kind: LexErrorKind,
+    /// The location of the offending input.
+    pub location: Location,
+    /// The characters and tokens that would have been accepted instead.
+    pub expected: Vec<Expected<Symbol>>,
+}
+
+impl<Symbol: fmt::Display> LexError<Symbol> {
+    /// Create a diagnostic report of this error.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let label = match self.kind {
+            LexErrorKind::UnexpectedCharacter(_) => "unexpected character",
+            LexErrorKind::UnterminatedToken => "unterminated token",
+            LexErrorKind::InvalidConversion(_) => "invalid token",
+            LexErrorKind::CallbackRejection(_) => "rejected token",
+        };
+        let diagnostic = Diagnostic::error(self.kind.to_string(), self.location, label);
+        if self.expected.is_empty() {
+            diagnostic
+        } else {
+            diagnostic.with_note(format!("expected {}", ExpectedList(&self.expected)))
+        }
+    }
 }
+
+/// Read all tokens from a lexer and collect all lexical errors.
+///
+/// Lexers that continue after errors will have all of their errors reported.
+///
+/// # Examples
+/// ```
+/// use turn::{tokenize, LexError, LexErrorKind, Location, Token};
+///
+/// let token = Token { symbol: 'a', attribute: "a", location: Location::new() };
+/// let error = LexError { kind: LexErrorKind::UnterminatedToken, location: Location::new(), expected: vec![] };
+///
+/// let input = vec![Ok(token), Err(error.clone()), Ok(token), Err(error.clone())];
+/// assert_eq!(tokenize(input), Err(vec![error.clone(), error]));
+/// assert_eq!(tokenize(vec![Ok(token), Ok(token)]), Ok(vec![token, token]));
+/// ```
+pub fn tokenize<'a, Symbol, Lexer>(
+    lexer: Lexer,
+) -> Result<Vec<Token<'a, Symbol>>, Vec<LexError<Symbol>>>
+where
+    Lexer: IntoIterator<Item = Result<Token<'a, Symbol>, LexError<Symbol>>>,
+{
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    for item in lexer {
+        match item {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            LexErrorKind::UnterminatedToken => write!(f, "unexpected end of input inside a token"),
+            LexErrorKind::InvalidConversion(message) => {
+                write!(f, "invalid token: {}", message)
+            }
+            LexErrorKind::CallbackRejection(message) => {
+                write!(f, "token rejected: {}", message)
+            }
+        }
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for Expected<Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Character(matcher) => matcher.fmt(f),
+            Expected::Token(symbol) => symbol.fmt(f),
+        }
+    }
+}
+
+/// Formats a list of expected items.
+struct ExpectedList<'a, Symbol>(&'a [Expected<Symbol>]);
+
+impl<Symbol: fmt::Display> fmt::Display for ExpectedList<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.len() > 1 {
+            write!(f, "one of ")?;
+        }
+        for (i, expected) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            expected.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for LexError<Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.location.span.0)?;
+        if !self.expected.is_empty() {
+            write!(f, "; expected {}", ExpectedList(&self.expected))?;
+        }
+        Ok(())
+    }
+}
+
+impl<Symbol: fmt::Display + fmt::Debug> std::error::Error for LexError<Symbol> {}
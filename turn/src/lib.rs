@@ -1,15 +1,79 @@
 //mod finite_automata;
+pub mod diagnostics;
 pub mod grammar;
 pub mod lexer;
-//pub mod parser;
+pub mod ll;
+pub mod lr;
+pub mod parser;
 
 pub use lexer::*;
 
-pub fn parse<'a, 'b, Symbol, Lexer>(_lexer: Lexer)
+/// Parse the tokens of a lexer with an LR parse table.
+///
+/// The semantic actions compute a value for every shifted token and every reduced rule;
+/// the value of the starting nonterminal is returned. Lexical errors are propagated,
+/// syntax errors report the offending token and the expected terminals.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+/// use turn::lr::{Algorithm, ParseTable};
+/// use turn::parser::{Callbacks, Locations, ParseError};
+/// use turn::{Location, Token};
+///
+/// // S -> S '+' 'n' | 'n'
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("S")
+///     .rules(vec![
+///         Rule::new(
+///             "S",
+///             vec![Symbol::Nonterminal("S"), Symbol::Terminal('+'), Symbol::Terminal('n')],
+///         ),
+///         Rule::new("S", vec![Symbol::Terminal('n')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+///
+/// let source = "1+22+3";
+/// let tokens = ["1", "+", "22", "+", "3"].iter().scan(0, |start, &text| {
+///     let mut location = Location::new();
+///     location.span = (*start, *start + text.len());
+///     *start += text.len();
+///     let symbol = if text == "+" { '+' } else { 'n' };
+///     Some(Ok(Token { symbol, attribute: text, location }))
+/// });
+/// let mut actions = Callbacks {
+///     shift: |token: Token<char>| token.attribute.parse::<u32>().unwrap_or(0),
+///     reduce: |rule, values: std::vec::Drain<u32>, _: Locations| match rule {
+///         0 => values.step_by(2).sum(),
+///         _ => values.sum(),
+///     },
+/// };
+/// assert_eq!(turn::parse(&table, tokens.clone(), &mut actions), Ok(26));
+///
+/// match turn::parse(&table, tokens.take(4), &mut actions) {
+///     Err(ParseError::Syntax(error)) => {
+///         assert_eq!(error.token, None);
+///         assert_eq!(error.location.span, (source.len() - 1, source.len() - 1));
+///         assert_eq!(error.to_string(), "unexpected end of input at byte 5; expected n");
+///     }
+///     result => panic!("unexpected result {:?}", result),
+/// }
+/// ```
+pub fn parse<'a, Symbol, Nonterminal, Lexer, A>(
+    table: &lr::ParseTable<Symbol, Nonterminal>,
+    lexer: Lexer,
+    actions: &mut A,
+) -> Result<A::Value, parser::ParseError<'a, Symbol>>
 where
-    Lexer: Iterator<Item = Result<lexer::Token<'a, 'b, Symbol>, ()>>,
+    Symbol: Ord + Clone,
+    Nonterminal: Ord + Clone,
+    Lexer: IntoIterator<Item = Result<lexer::Token<'a, Symbol>, lexer::LexError<Symbol>>>,
+    A: parser::Actions<'a, Symbol>,
 {
-    unimplemented!();
+    parser::LrParser::new(table).parse(lexer, actions)
 }
 
 #[cfg(test)]
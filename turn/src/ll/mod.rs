@@ -0,0 +1,5 @@
+//! LL(1) parse tables.
+
+mod table;
+
+pub use table::{LlConflict, LlConflictKind, LlTable, LlTableError};
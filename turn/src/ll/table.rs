@@ -0,0 +1,344 @@
+use crate::grammar::{
+    Grammar, GrammarAnalysis, GrammarError, IndexedGrammar, IndexedSymbol, Lookahead,
+};
+use fixedbitset::FixedBitSet;
+use std::fmt;
+
+/// The kind of an LL(1) conflict.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum LlConflictKind {
+    /// The lookahead can start the right-hand sides of multiple rules.
+    FirstFirst,
+    /// The lookahead can start the right-hand side of a rule
+    /// and follow the nonterminal derived to the empty string by another rule,
+    /// or follow it for multiple nullable rules.
+    FirstFollow,
+}
+
+/// Multiple rules predicted for the same nonterminal and lookahead.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LlConflict<Terminal, Nonterminal> {
+    pub kind: LlConflictKind,
+    pub nonterminal: Nonterminal,
+    pub lookahead: Lookahead<Terminal>,
+    /// The competing rules, by their indices in `Grammar::rules`.
+    pub rules: Vec<usize>,
+}
+
+/// An error returned by `LlTable::new`.
+#[derive(Clone, Debug)]
+pub enum LlTableError<Terminal, Nonterminal> {
+    /// The grammar did not pass `Grammar::validate`.
+    InvalidGrammar(Vec<GrammarError<Terminal, Nonterminal>>),
+    /// The grammar is not LL(1).
+    ///
+    /// The table keeps all competing rules; `LlTable::rule` prefers earlier rules.
+    Conflicts {
+        conflicts: Vec<LlConflict<Terminal, Nonterminal>>,
+        table: Box<LlTable<Terminal, Nonterminal>>,
+    },
+}
+
+/// The prediction table of an LL(1) parser.
+///
+/// For every nonterminal and lookahead, the table holds the rules whose right-hand side
+/// can start with the lookahead (its FIRST set), or can derive the empty string
+/// while the lookahead follows the nonterminal (its FOLLOW set).
+/// Rules are referenced by their indices in `Grammar::rules`.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Lookahead, Rule, Symbol};
+/// use turn::ll::{LlConflictKind, LlTable, LlTableError};
+///
+/// // L -> 'x' T; T -> ',' 'x' T | ε
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("L")
+///     .rules(vec![
+///         Rule::new("L", vec![Symbol::Terminal('x'), Symbol::Nonterminal("T")]),
+///         Rule::new(
+///             "T",
+///             vec![Symbol::Terminal(','), Symbol::Terminal('x'), Symbol::Nonterminal("T")],
+///         ),
+///         Rule::new("T", vec![]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let table = LlTable::new(&grammar).unwrap();
+/// assert_eq!(table.rule(&"L", &Lookahead::Terminal('x')), Some(0));
+/// assert_eq!(table.rule(&"T", &Lookahead::Terminal(',')), Some(1));
+/// assert_eq!(table.rule(&"T", &Lookahead::End), Some(2));
+/// assert_eq!(table.rule(&"T", &Lookahead::Terminal('x')), None);
+///
+/// // L -> L ',' 'x' | 'x' is left recursive
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("L")
+///     .rules(vec![
+///         Rule::new(
+///             "L",
+///             vec![Symbol::Nonterminal("L"), Symbol::Terminal(','), Symbol::Terminal('x')],
+///         ),
+///         Rule::new("L", vec![Symbol::Terminal('x')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// match LlTable::new(&grammar) {
+///     Err(LlTableError::Conflicts { conflicts, .. }) => {
+///         assert_eq!(conflicts.len(), 1);
+///         assert_eq!(conflicts[0].kind, LlConflictKind::FirstFirst);
+///         assert_eq!(conflicts[0].rules, vec![0, 1]);
+///         assert_eq!(
+///             conflicts[0].to_string(),
+///             "FIRST/FIRST conflict for \"L\" on 'x': rules 0, 1"
+///         );
+///     }
+///     _ => panic!("the grammar is not LL(1)"),
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LlTable<Terminal, Nonterminal> {
+    analysis: GrammarAnalysis<Terminal, Nonterminal>,
+    /// The predicted rules of each nonterminal, indexed by lookahead.
+    rules: Vec<Vec<Vec<usize>>>,
+    conflicts: Vec<LlConflict<Terminal, Nonterminal>>,
+}
+
+impl<Terminal, Nonterminal> LlTable<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Validate the grammar and construct its LL(1) table.
+    ///
+    /// The conflicts are returned together with the table containing all competing rules.
+    pub fn new(
+        grammar: &Grammar<Terminal, Nonterminal>,
+    ) -> Result<Self, LlTableError<Terminal, Nonterminal>> {
+        grammar.validate().map_err(LlTableError::InvalidGrammar)?;
+        let analysis = GrammarAnalysis::from_indexed(IndexedGrammar::new(grammar));
+        let indexed = analysis.indexed_grammar();
+        let end = indexed.end();
+        let mut rules = vec![vec![vec![]; end + 1]; indexed.nonterminals.len()];
+        // the lookaheads of each rule predicted by its FIRST set
+        let mut firsts = vec![];
+        for (index, rule) in indexed.rules.iter().enumerate() {
+            let mut first = FixedBitSet::with_capacity(end + 1);
+            let nullable = analysis.indexed_first_of_sequence(&rule.right_hand, &mut first);
+            let mut lookaheads = first.clone();
+            if nullable {
+                lookaheads.union_with(analysis.indexed_follow(rule.left_hand));
+            }
+            for t in lookaheads.ones() {
+                rules[rule.left_hand][t].push(index);
+            }
+            firsts.push(first);
+        }
+
+        let mut conflicts = vec![];
+        for (nonterminal, row) in rules.iter().enumerate() {
+            for (t, cell) in row.iter().enumerate() {
+                if cell.len() > 1 {
+                    let kind = match cell
+                        .iter()
+                        .filter(|&&rule| firsts[rule].contains(t))
+                        .count()
+                    {
+                        0 | 1 => LlConflictKind::FirstFollow,
+                        _ => LlConflictKind::FirstFirst,
+                    };
+                    conflicts.push(LlConflict {
+                        kind,
+                        nonterminal: indexed.nonterminals[nonterminal].clone(),
+                        lookahead: indexed.lookahead(t),
+                        rules: cell.clone(),
+                    });
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            return Ok(LlTable {
+                analysis,
+                rules,
+                conflicts,
+            });
+        }
+        let table = LlTable {
+            analysis,
+            rules,
+            conflicts: conflicts.clone(),
+        };
+        Err(LlTableError::Conflicts {
+            conflicts,
+            table: Box::new(table),
+        })
+    }
+
+    /// The preferred rule for a nonterminal and a lookahead, `None` for a syntax error.
+    pub fn rule(
+        &self,
+        nonterminal: &Nonterminal,
+        lookahead: &Lookahead<Terminal>,
+    ) -> Option<usize> {
+        self.rules(nonterminal, lookahead).first().copied()
+    }
+
+    /// All rules for a nonterminal and a lookahead, in the order of the grammar.
+    ///
+    /// There is more than one rule only if the grammar has conflicts.
+    pub fn rules(&self, nonterminal: &Nonterminal, lookahead: &Lookahead<Terminal>) -> &[usize] {
+        let grammar = self.grammar();
+        match (
+            grammar.nonterminal(nonterminal),
+            grammar.lookahead_index(lookahead),
+        ) {
+            (Some(n), Some(t)) => &self.rules[n][t],
+            _ => &[],
+        }
+    }
+
+    #[inline]
+    pub(crate) fn grammar(&self) -> &IndexedGrammar<Terminal, Nonterminal> {
+        self.analysis.indexed_grammar()
+    }
+
+    /// The rules for an indexed nonterminal and the index of a lookahead.
+    #[inline]
+    pub(crate) fn indexed_rules(&self, nonterminal: usize, lookahead: usize) -> &[usize] {
+        &self.rules[nonterminal][lookahead]
+    }
+
+    /// Add the FIRST set of an indexed sequence to a bitset, returns true if it is nullable.
+    #[inline]
+    pub(crate) fn first_of_sequence(
+        &self,
+        symbols: &[IndexedSymbol],
+        first: &mut FixedBitSet,
+    ) -> bool {
+        self.analysis.indexed_first_of_sequence(symbols, first)
+    }
+}
+
+impl<Terminal, Nonterminal> LlTable<Terminal, Nonterminal> {
+    /// The conflicts of the table, ordered by nonterminal and lookahead;
+    /// empty unless the table was returned by `LlTableError::Conflicts`.
+    #[inline]
+    pub fn conflicts(&self) -> &[LlConflict<Terminal, Nonterminal>] {
+        &self.conflicts
+    }
+}
+
+impl<Terminal: fmt::Debug, Nonterminal: fmt::Debug> fmt::Display
+    for LlConflict<Terminal, Nonterminal>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} conflict for {:?} on ", self.kind, self.nonterminal)?;
+        match &self.lookahead {
+            Lookahead::Terminal(t) => write!(f, "{:?}", t)?,
+            Lookahead::End => write!(f, "end of input")?,
+        }
+        for (i, rule) in self.rules.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ": rules " } else { ", " }, rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LlConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LlConflictKind::FirstFirst => write!(f, "FIRST/FIRST"),
+            LlConflictKind::FirstFollow => write!(f, "FIRST/FOLLOW"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule, Symbol};
+
+    fn grammar(rules: Vec<Rule<char, &'static str>>) -> Grammar<char, &'static str> {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn predictions() {
+        // S -> A B 'c'; A -> 'a' | ε; B -> 'b' | ε
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let table = LlTable::new(&grammar(vec![
+            Rule::new("S", vec![n("A"), n("B"), t('c')]),
+            Rule::new("A", vec![t('a')]),
+            Rule::new("A", vec![]),
+            Rule::new("B", vec![t('b')]),
+            Rule::new("B", vec![]),
+        ]))
+        .unwrap();
+        let rules = |nonterminal| -> Vec<Option<usize>> {
+            ['a', 'b', 'c']
+                .iter()
+                .map(|&c| Lookahead::Terminal(c))
+                .chain(Some(Lookahead::End))
+                .map(|lookahead| table.rule(&nonterminal, &lookahead))
+                .collect()
+        };
+        assert_eq!(rules("S"), vec![Some(0), Some(0), Some(0), None]);
+        assert_eq!(rules("A"), vec![Some(1), Some(2), Some(2), None]);
+        assert_eq!(rules("B"), vec![None, Some(3), Some(4), None]);
+        assert_eq!(rules("C"), vec![None; 4]);
+        assert!(table.conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts() {
+        // S -> 'i' S E | 'x'; E -> 'e' S | ε: the dangling else
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let (conflicts, table) = match LlTable::new(&grammar(vec![
+            Rule::new("S", vec![t('i'), n("S"), n("E")]),
+            Rule::new("S", vec![t('x')]),
+            Rule::new("E", vec![t('e'), n("S")]),
+            Rule::new("E", vec![]),
+        ])) {
+            Err(LlTableError::Conflicts { conflicts, table }) => (conflicts, table),
+            _ => panic!("the grammar is not LL(1)"),
+        };
+        assert_eq!(
+            conflicts,
+            vec![LlConflict {
+                kind: LlConflictKind::FirstFollow,
+                nonterminal: "E",
+                lookahead: Lookahead::Terminal('e'),
+                rules: vec![2, 3],
+            }]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "FIRST/FOLLOW conflict for \"E\" on 'e': rules 2, 3"
+        );
+        assert_eq!(table.conflicts(), &conflicts[..]);
+        assert_eq!(table.rules(&"E", &Lookahead::Terminal('e')), &[2, 3]);
+        assert_eq!(table.rule(&"E", &Lookahead::End), Some(3));
+
+        let invalid = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![Rule::new("S", vec![t('x')])])
+            .symbol_precedence(vec![])
+            .rule_priorities(vec![(0, 1)])
+            .build()
+            .unwrap();
+        match LlTable::new(&invalid) {
+            Err(LlTableError::InvalidGrammar(errors)) => {
+                assert_eq!(errors, vec![GrammarError::UndefinedRule { rule: 1 }])
+            }
+            _ => panic!("the grammar is invalid"),
+        }
+    }
+}
@@ -0,0 +1,1034 @@
+//! Counterexamples for conflicts of a parse table.
+//!
+//! For each conflicting action, a lookahead-sensitive path through the items of the automaton
+//! gives a derivation of a sentential form in which the parser reaches the conflict state
+//! with the conflict's lookahead next and takes the action. If one of these sentential forms
+//! can be derived with either of two conflicting actions, it is reported as a unifying example,
+//! which shows that the grammar is ambiguous.
+
+use super::{Action, Conflict, Item, Lr0Automaton};
+use crate::grammar::{GrammarAnalysis, IndexedSymbol, Symbol};
+use fixedbitset::FixedBitSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// The maximum number of derivations kept for a symbol and a span of a sentential form.
+const DERIVATION_LIMIT: usize = 16;
+/// The maximum length of a sentential form searched for a unifying example.
+const UNIFYING_LENGTH_LIMIT: usize = 32;
+
+/// A derivation tree of a sentential form, with the point of a conflict marked.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Derivation<Terminal, Nonterminal> {
+    /// A symbol of the sentential form.
+    Symbol(Symbol<Terminal, Nonterminal>),
+    /// The position of the parser when the conflict occurs.
+    Dot,
+    /// A nonterminal derived by a rule.
+    Rule {
+        /// The index of the rule.
+        rule: usize,
+        nonterminal: Nonterminal,
+        children: Vec<Derivation<Terminal, Nonterminal>>,
+    },
+}
+
+/// A derivation for one of the actions of a conflict.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Example<Terminal, Nonterminal> {
+    pub action: Action,
+    /// The derivation of the right-hand side of the augmented start rule,
+    /// usually a single derivation of the starting nonterminal.
+    pub derivations: Vec<Derivation<Terminal, Nonterminal>>,
+}
+
+/// Examples of how the parser reaches a conflict, in the style of bison's counterexamples.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Counterexample<Terminal, Nonterminal> {
+    pub conflict: Conflict<Terminal>,
+    /// A shortest sequence of symbols leading from the starting state to the conflict state.
+    pub prefix: Vec<Symbol<Terminal, Nonterminal>>,
+    /// An example for each conflicting action, in the order of `conflict.actions`.
+    pub examples: Vec<Example<Terminal, Nonterminal>>,
+    /// Two derivations of the same sentential form for two of the conflicting actions.
+    ///
+    /// The search is bounded, so a missing unifying example does not prove
+    /// that the grammar is unambiguous.
+    pub unifying: Option<(
+        Example<Terminal, Nonterminal>,
+        Example<Terminal, Nonterminal>,
+    )>,
+}
+
+impl<Terminal: Clone, Nonterminal: Clone> Derivation<Terminal, Nonterminal> {
+    fn push_leaves(&self, leaves: &mut Vec<Symbol<Terminal, Nonterminal>>) {
+        match self {
+            Derivation::Symbol(symbol) => leaves.push(symbol.clone()),
+            Derivation::Dot => {}
+            Derivation::Rule { children, .. } => {
+                for child in children {
+                    child.push_leaves(leaves);
+                }
+            }
+        }
+    }
+}
+
+impl<Terminal: Clone, Nonterminal: Clone> Example<Terminal, Nonterminal> {
+    /// The derived sentential form.
+    pub fn sentential_form(&self) -> Vec<Symbol<Terminal, Nonterminal>> {
+        let mut leaves = vec![];
+        for derivation in &self.derivations {
+            derivation.push_leaves(&mut leaves);
+        }
+        leaves
+    }
+}
+
+impl<Terminal, Nonterminal> Example<Terminal, Nonterminal> {
+    /// The number of symbols of the sentential form before the conflict point.
+    pub fn position(&self) -> usize {
+        fn find<T, N>(derivation: &Derivation<T, N>, position: &mut usize) -> bool {
+            match derivation {
+                Derivation::Symbol(_) => {
+                    *position += 1;
+                    false
+                }
+                Derivation::Dot => true,
+                Derivation::Rule { children, .. } => {
+                    children.iter().any(|child| find(child, position))
+                }
+            }
+        }
+        let mut position = 0;
+        self.derivations
+            .iter()
+            .any(|derivation| find(derivation, &mut position));
+        position
+    }
+}
+
+/// A derivation tree in the indexed grammar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Tree {
+    Leaf(IndexedSymbol),
+    Dot,
+    Node(usize, Vec<Tree>),
+}
+
+impl Tree {
+    fn push_leaves(&self, leaves: &mut Vec<IndexedSymbol>) {
+        match self {
+            Tree::Leaf(symbol) => leaves.push(*symbol),
+            Tree::Dot => {}
+            Tree::Node(_, children) => {
+                for child in children {
+                    child.push_leaves(leaves);
+                }
+            }
+        }
+    }
+
+    /// The number of leaves of the tree.
+    fn width(&self) -> usize {
+        match self {
+            Tree::Leaf(_) => 1,
+            Tree::Dot => 0,
+            Tree::Node(_, children) => children.iter().map(Tree::width).sum(),
+        }
+    }
+
+    /// Returns true if a node of the rule spans the leaves up to `position`.
+    fn reduces_at(&self, offset: usize, rule: usize, position: usize) -> bool {
+        match self {
+            Tree::Node(node_rule, children) => {
+                if *node_rule == rule && offset + self.width() == position {
+                    return true;
+                }
+                let mut offset = offset;
+                for child in children {
+                    if child.reduces_at(offset, rule, position) {
+                        return true;
+                    }
+                    offset += child.width();
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Mark the end of the innermost node of the rule ending at `position`.
+    fn insert_reduction_dot(&mut self, offset: usize, rule: usize, position: usize) -> bool {
+        let width = self.width();
+        if let Tree::Node(node_rule, children) = self {
+            let mut child_offset = offset;
+            for child in children.iter_mut() {
+                let child_width = child.width();
+                if child.insert_reduction_dot(child_offset, rule, position) {
+                    return true;
+                }
+                child_offset += child_width;
+            }
+            if *node_rule == rule && offset + width == position {
+                children.push(Tree::Dot);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Mark the position before the leaf at `position`.
+    fn insert_shift_dot(&mut self, offset: usize, position: usize) -> bool {
+        if let Tree::Node(_, children) = self {
+            let mut child_offset = offset;
+            for i in 0..children.len() {
+                let width = children[i].width();
+                if child_offset <= position && position < child_offset + width {
+                    return match children[i] {
+                        Tree::Leaf(_) => {
+                            children.insert(i, Tree::Dot);
+                            true
+                        }
+                        _ => children[i].insert_shift_dot(child_offset, position),
+                    };
+                }
+                child_offset += width;
+            }
+        }
+        false
+    }
+}
+
+/// A step of a path through the items of an automaton.
+#[derive(Clone, Debug)]
+enum Step {
+    /// Shift a symbol within the current item.
+    Shift(IndexedSymbol),
+    /// Derive the nonterminal after the dot of the current item by a rule.
+    Expand {
+        rule: usize,
+        /// The symbols following the nonterminal in the current item.
+        rest: Vec<IndexedSymbol>,
+        /// True if the required lookahead is the first terminal derived from `rest`.
+        produces_lookahead: bool,
+    },
+}
+
+/// A node of the lookahead-sensitive item graph: a state, the index of one of its items
+/// and whether the lookahead still has to follow the item.
+type ItemNode = (usize, usize, bool);
+
+/// Searches counterexamples in the automaton of a parse table.
+pub(crate) struct CounterexampleSearch<'a, Terminal, Nonterminal> {
+    automaton: &'a Lr0Automaton<Terminal, Nonterminal>,
+    analysis: GrammarAnalysis<Terminal, Nonterminal>,
+    predecessors: Vec<Vec<usize>>,
+    /// A rule deriving the empty string for each nullable nonterminal.
+    empty_rules: Vec<Option<usize>>,
+}
+
+impl<'a, Terminal, Nonterminal> CounterexampleSearch<'a, Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    pub(crate) fn new(automaton: &'a Lr0Automaton<Terminal, Nonterminal>) -> Self {
+        let grammar = &automaton.grammar;
+        let mut predecessors = vec![vec![]; automaton.states.len()];
+        for (state, data) in automaton.states.iter().enumerate() {
+            for &target in data.transitions.values() {
+                if predecessors[target].last() != Some(&state) {
+                    predecessors[target].push(state);
+                }
+            }
+        }
+
+        let mut empty_rules = vec![None; grammar.nonterminals.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, rule) in grammar.rules.iter().enumerate() {
+                if empty_rules[rule.left_hand].is_none()
+                    && rule.right_hand.iter().all(|symbol| match *symbol {
+                        IndexedSymbol::Terminal(_) => false,
+                        IndexedSymbol::Nonterminal(n) => empty_rules[n].is_some(),
+                    })
+                {
+                    empty_rules[rule.left_hand] = Some(index);
+                    changed = true;
+                }
+            }
+        }
+
+        CounterexampleSearch {
+            automaton,
+            analysis: GrammarAnalysis::from_indexed(grammar.clone()),
+            predecessors,
+            empty_rules,
+        }
+    }
+
+    pub(crate) fn counterexample(
+        &self,
+        conflict: &Conflict<Terminal>,
+    ) -> Counterexample<Terminal, Nonterminal> {
+        let grammar = &self.automaton.grammar;
+        let lookahead = grammar
+            .lookahead_index(&conflict.lookahead)
+            .expect("the lookahead of a conflict is a terminal of the grammar");
+        let examples: Vec<(Action, Vec<Tree>)> = conflict
+            .actions
+            .iter()
+            .filter_map(|&action| {
+                self.example(conflict.state, lookahead, action)
+                    .map(|trees| (action, trees))
+            })
+            .collect();
+        let unifying = self.unifying_example(&examples);
+        Counterexample {
+            conflict: conflict.clone(),
+            prefix: self
+                .shortest_prefix(conflict.state)
+                .into_iter()
+                .map(|symbol| grammar.original_symbol(symbol))
+                .collect(),
+            examples: examples
+                .iter()
+                .map(|(action, trees)| self.to_example(*action, trees))
+                .collect(),
+            unifying: unifying.map(|(first, second)| {
+                (
+                    self.to_example(first.0, &first.1),
+                    self.to_example(second.0, &second.1),
+                )
+            }),
+        }
+    }
+
+    /// A shortest sequence of symbols leading from the starting state to a state.
+    fn shortest_prefix(&self, state: usize) -> Vec<IndexedSymbol> {
+        let states = &self.automaton.states;
+        let mut parents: Vec<Option<(usize, IndexedSymbol)>> = vec![None; states.len()];
+        let mut queue: VecDeque<usize> = std::iter::once(0).collect();
+        let mut visited = FixedBitSet::with_capacity(states.len());
+        visited.insert(0);
+        while let Some(current) = queue.pop_front() {
+            if current == state {
+                break;
+            }
+            for (&symbol, &target) in &states[current].transitions {
+                if !visited.put(target) {
+                    parents[target] = Some((current, symbol));
+                    queue.push_back(target);
+                }
+            }
+        }
+        let mut prefix = vec![];
+        let mut current = state;
+        while let Some((parent, symbol)) = parents[current] {
+            prefix.push(symbol);
+            current = parent;
+        }
+        prefix.reverse();
+        prefix
+    }
+
+    /// A shortest derivation reaching the conflict state with the lookahead next
+    /// in which the parser takes the action.
+    fn example(&self, state: usize, lookahead: usize, action: Action) -> Option<Vec<Tree>> {
+        let data = &self.automaton.states[state];
+        let targets: Vec<(Item, bool)> = match action {
+            Action::Shift(_) => data
+                .items
+                .iter()
+                .filter(|&&item| {
+                    self.automaton.indexed_next_symbol(item)
+                        == Some(IndexedSymbol::Terminal(lookahead))
+                })
+                .map(|&item| (item, false))
+                .collect(),
+            Action::Reduce(rule) => {
+                vec![(Item::new(rule, self.automaton.right_hand(rule).len()), true)]
+            }
+            Action::Accept => vec![(Item::new(self.automaton.augmented_rule(), 1), true)],
+            Action::Error => vec![],
+        };
+        targets
+            .into_iter()
+            .filter_map(|(item, needs_lookahead)| {
+                let index = data.item_index(item)?;
+                let steps = self.item_path((state, index, needs_lookahead), lookahead)?;
+                Some(self.build_trees(&steps, item, lookahead))
+            })
+            .min_by_key(|trees| trees.iter().map(Tree::width).sum::<usize>())
+    }
+
+    /// Find a shortest path from the augmented start item in the starting state to an item node.
+    ///
+    /// The search goes backwards from the target, tracking whether the lookahead
+    /// still has to be derived after the current item.
+    fn item_path(&self, target: ItemNode, lookahead: usize) -> Option<Vec<Step>> {
+        let grammar = &self.automaton.grammar;
+        let states = &self.automaton.states;
+        let augmented = self.automaton.augmented_rule();
+        let mut distances: HashMap<ItemNode, usize> = HashMap::new();
+        let mut links: HashMap<ItemNode, (ItemNode, Step)> = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(target, 0);
+        queue.push_back((target, 0));
+
+        let mut goal = None;
+        while let Some((node, distance)) = queue.pop_front() {
+            if distances[&node] < distance {
+                continue;
+            }
+            let (state, index, needs_lookahead) = node;
+            let item = states[state].items[index];
+            if state == 0
+                && item == Item::new(augmented, 0)
+                && (!needs_lookahead || lookahead == grammar.end())
+            {
+                goal = Some(node);
+                break;
+            }
+
+            let mut edges = vec![];
+            if item.dot > 0 {
+                let symbol = self.automaton.right_hand(item.rule)[item.dot - 1];
+                let previous = Item::new(item.rule, item.dot - 1);
+                for &p in &self.predecessors[state] {
+                    if states[p].transitions.get(&symbol) == Some(&state) {
+                        let index = states[p].item_index(previous).unwrap();
+                        edges.push(((p, index, needs_lookahead), Step::Shift(symbol), 1));
+                    }
+                }
+            } else if item.rule != augmented {
+                let nonterminal = IndexedSymbol::Nonterminal(grammar.rules[item.rule].left_hand);
+                for (index, &parent) in states[state].items.iter().enumerate() {
+                    if self.automaton.indexed_next_symbol(parent) != Some(nonterminal) {
+                        continue;
+                    }
+                    let rest = &self.automaton.right_hand(parent.rule)[parent.dot + 1..];
+                    let (parent_needs, produces_lookahead) = if needs_lookahead {
+                        let mut first = FixedBitSet::with_capacity(grammar.end() + 1);
+                        let nullable = self.analysis.indexed_first_of_sequence(rest, &mut first);
+                        if first.contains(lookahead) {
+                            (false, true)
+                        } else if nullable {
+                            (true, false)
+                        } else {
+                            continue;
+                        }
+                    } else {
+                        (false, false)
+                    };
+                    let step = Step::Expand {
+                        rule: item.rule,
+                        rest: rest.to_vec(),
+                        produces_lookahead,
+                    };
+                    edges.push(((state, index, parent_needs), step, 0));
+                }
+            }
+
+            for (previous, step, cost) in edges {
+                let distance = distance + cost;
+                if distances
+                    .get(&previous)
+                    .is_none_or(|&known| distance < known)
+                {
+                    distances.insert(previous, distance);
+                    links.insert(previous, (node, step));
+                    if cost == 0 {
+                        queue.push_front((previous, distance));
+                    } else {
+                        queue.push_back((previous, distance));
+                    }
+                }
+            }
+        }
+
+        let mut node = goal?;
+        let mut steps = vec![];
+        while node != target {
+            let (next, step) = links[&node].clone();
+            steps.push(step);
+            node = next;
+        }
+        Some(steps)
+    }
+
+    /// Build the derivation of a path ending at an item, marking the position after the path.
+    fn build_trees(&self, steps: &[Step], item: Item, lookahead: usize) -> Vec<Tree> {
+        // the open nodes: rule, children, the symbols following it in the parent
+        // and whether they produce the lookahead
+        let mut frames = vec![(self.automaton.augmented_rule(), vec![], vec![], false)];
+        for step in steps {
+            match step {
+                Step::Shift(symbol) => frames.last_mut().unwrap().1.push(Tree::Leaf(*symbol)),
+                Step::Expand {
+                    rule,
+                    rest,
+                    produces_lookahead,
+                } => frames.push((*rule, vec![], rest.clone(), *produces_lookahead)),
+            }
+        }
+
+        let top = &mut frames.last_mut().unwrap().1;
+        top.push(Tree::Dot);
+        top.extend(
+            self.automaton.right_hand(item.rule)[item.dot..]
+                .iter()
+                .map(|&symbol| Tree::Leaf(symbol)),
+        );
+        while frames.len() > 1 {
+            let (rule, children, rest, produces_lookahead) = frames.pop().unwrap();
+            let parent = &mut frames.last_mut().unwrap().1;
+            parent.push(Tree::Node(rule, children));
+            if produces_lookahead {
+                parent.extend(self.lookahead_first(&rest, lookahead));
+            } else {
+                parent.extend(rest.iter().map(|&symbol| Tree::Leaf(symbol)));
+            }
+        }
+        frames.pop().unwrap().1
+    }
+
+    /// Derive a sequence of symbols so that it starts with the lookahead.
+    fn lookahead_first(&self, symbols: &[IndexedSymbol], lookahead: usize) -> Vec<Tree> {
+        let mut trees = vec![];
+        let mut found = false;
+        for &symbol in symbols {
+            let tree = match symbol {
+                IndexedSymbol::Nonterminal(n) if !found => {
+                    match self.first_derivation(n, lookahead, &mut vec![]) {
+                        Some(tree) => {
+                            found = true;
+                            tree
+                        }
+                        None => self.empty_derivation(n),
+                    }
+                }
+                _ => {
+                    found = true;
+                    Tree::Leaf(symbol)
+                }
+            };
+            trees.push(tree);
+        }
+        trees
+    }
+
+    /// A derivation of a nonterminal whose first leaf is the terminal.
+    fn first_derivation(
+        &self,
+        nonterminal: usize,
+        terminal: usize,
+        visiting: &mut Vec<usize>,
+    ) -> Option<Tree> {
+        let mut first = FixedBitSet::with_capacity(self.automaton.grammar.end() + 1);
+        self.analysis
+            .indexed_first_of_sequence(&[IndexedSymbol::Nonterminal(nonterminal)], &mut first);
+        if visiting.contains(&nonterminal) || !first.contains(terminal) {
+            return None;
+        }
+        visiting.push(nonterminal);
+        let mut result = None;
+        'rules: for &rule in &self.automaton.grammar.rules_of[nonterminal] {
+            let mut children = vec![];
+            let mut found = false;
+            for &symbol in self.automaton.right_hand(rule) {
+                if found {
+                    children.push(Tree::Leaf(symbol));
+                    continue;
+                }
+                match symbol {
+                    IndexedSymbol::Terminal(t) if t == terminal => {
+                        children.push(Tree::Leaf(symbol));
+                        found = true;
+                    }
+                    IndexedSymbol::Terminal(_) => continue 'rules,
+                    IndexedSymbol::Nonterminal(n) => {
+                        if let Some(tree) = self.first_derivation(n, terminal, visiting) {
+                            children.push(tree);
+                            found = true;
+                        } else if self.empty_rules[n].is_some() {
+                            children.push(self.empty_derivation(n));
+                        } else {
+                            continue 'rules;
+                        }
+                    }
+                }
+            }
+            if found {
+                result = Some(Tree::Node(rule, children));
+                break;
+            }
+        }
+        visiting.pop();
+        result
+    }
+
+    /// A derivation of the empty string from a nullable nonterminal.
+    fn empty_derivation(&self, nonterminal: usize) -> Tree {
+        let rule = self.empty_rules[nonterminal].expect("the nonterminal is nullable");
+        let children = self
+            .automaton
+            .right_hand(rule)
+            .iter()
+            .map(|&symbol| match symbol {
+                IndexedSymbol::Nonterminal(n) => self.empty_derivation(n),
+                IndexedSymbol::Terminal(_) => unreachable!(),
+            })
+            .collect();
+        Tree::Node(rule, children)
+    }
+
+    /// Search two derivations of the sentential form of one of the examples
+    /// that take two different conflicting actions at the conflict point.
+    #[allow(clippy::type_complexity)]
+    fn unifying_example(
+        &self,
+        examples: &[(Action, Vec<Tree>)],
+    ) -> Option<((Action, Vec<Tree>), (Action, Vec<Tree>))> {
+        let start = IndexedSymbol::Nonterminal(self.automaton.grammar.start);
+        for (_, trees) in examples {
+            let mut sentence = vec![];
+            for tree in trees {
+                tree.push_leaves(&mut sentence);
+            }
+            if sentence.len() > UNIFYING_LENGTH_LIMIT {
+                continue;
+            }
+            let position = self.to_example(Action::Error, trees).position();
+
+            let mut parser = SententialParser {
+                automaton: self.automaton,
+                sentence: &sentence,
+                symbols: HashMap::new(),
+                sequences: HashMap::new(),
+                in_progress: HashSet::new(),
+            };
+            let derivations = parser.symbol(start, 0, sentence.len());
+            for (i, &(first, _)) in examples.iter().enumerate() {
+                for &(second, _) in &examples[i + 1..] {
+                    let takes = |tree: &Tree, action: Action| match action {
+                        Action::Reduce(rule) => tree.reduces_at(0, rule, position),
+                        Action::Shift(_) => {
+                            position < sentence.len()
+                                && examples.iter().all(|&(other, _)| match other {
+                                    Action::Reduce(rule) => !tree.reduces_at(0, rule, position),
+                                    _ => true,
+                                })
+                        }
+                        Action::Accept | Action::Error => false,
+                    };
+                    for (j, first_tree) in derivations.iter().enumerate() {
+                        if !takes(first_tree, first) {
+                            continue;
+                        }
+                        let second_tree = derivations
+                            .iter()
+                            .enumerate()
+                            .find(|&(k, tree)| k != j && takes(tree, second));
+                        if let Some((_, second_tree)) = second_tree {
+                            return Some((
+                                (first, vec![self.mark(first_tree.clone(), first, position)]),
+                                (
+                                    second,
+                                    vec![self.mark(second_tree.clone(), second, position)],
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Mark the conflict point in a derivation taking the action.
+    fn mark(&self, mut tree: Tree, action: Action, position: usize) -> Tree {
+        match action {
+            Action::Reduce(rule) => tree.insert_reduction_dot(0, rule, position),
+            _ => tree.insert_shift_dot(0, position),
+        };
+        tree
+    }
+
+    fn to_derivation(&self, tree: &Tree) -> Derivation<Terminal, Nonterminal> {
+        let grammar = &self.automaton.grammar;
+        match tree {
+            Tree::Leaf(symbol) => Derivation::Symbol(grammar.original_symbol(*symbol)),
+            Tree::Dot => Derivation::Dot,
+            Tree::Node(rule, children) => Derivation::Rule {
+                rule: *rule,
+                nonterminal: grammar.nonterminals[grammar.rules[*rule].left_hand].clone(),
+                children: children
+                    .iter()
+                    .map(|child| self.to_derivation(child))
+                    .collect(),
+            },
+        }
+    }
+
+    fn to_example(&self, action: Action, trees: &[Tree]) -> Example<Terminal, Nonterminal> {
+        Example {
+            action,
+            derivations: trees.iter().map(|tree| self.to_derivation(tree)).collect(),
+        }
+    }
+}
+
+/// Enumerates derivations of a sentential form whose leaves can be terminals and nonterminals.
+struct SententialParser<'a, Terminal, Nonterminal> {
+    automaton: &'a Lr0Automaton<Terminal, Nonterminal>,
+    sentence: &'a [IndexedSymbol],
+    symbols: HashMap<(IndexedSymbol, usize, usize), Vec<Tree>>,
+    sequences: HashMap<(usize, usize, usize, usize), Vec<Vec<Tree>>>,
+    /// Spans being derived, to cut cycles of unit and empty rules.
+    in_progress: HashSet<(IndexedSymbol, usize, usize)>,
+}
+
+impl<Terminal, Nonterminal> SententialParser<'_, Terminal, Nonterminal> {
+    /// Derivations of the symbols `start..end` of the sentential form from a symbol.
+    fn symbol(&mut self, symbol: IndexedSymbol, start: usize, end: usize) -> Vec<Tree> {
+        let key = (symbol, start, end);
+        if let Some(trees) = self.symbols.get(&key) {
+            return trees.clone();
+        }
+        if !self.in_progress.insert(key) {
+            return vec![];
+        }
+        let mut trees = vec![];
+        if end == start + 1 && self.sentence[start] == symbol {
+            trees.push(Tree::Leaf(symbol));
+        }
+        if let IndexedSymbol::Nonterminal(n) = symbol {
+            'rules: for &rule in &self.automaton.grammar.rules_of[n] {
+                for children in self.sequence(rule, 0, start, end) {
+                    trees.push(Tree::Node(rule, children));
+                    if trees.len() >= DERIVATION_LIMIT {
+                        break 'rules;
+                    }
+                }
+            }
+        }
+        self.in_progress.remove(&key);
+        self.symbols.insert(key, trees.clone());
+        trees
+    }
+
+    /// Derivations of the symbols `start..end` from the right-hand symbols of a rule after `from`.
+    fn sequence(&mut self, rule: usize, from: usize, start: usize, end: usize) -> Vec<Vec<Tree>> {
+        let key = (rule, from, start, end);
+        if let Some(sequences) = self.sequences.get(&key) {
+            return sequences.clone();
+        }
+        let right_hand = self.automaton.right_hand(rule);
+        if from == right_hand.len() {
+            return if start == end { vec![vec![]] } else { vec![] };
+        }
+        let symbol = right_hand[from];
+        let mut sequences = vec![];
+        'split: for split in start..=end {
+            let heads = self.symbol(symbol, start, split);
+            if heads.is_empty() {
+                continue;
+            }
+            let tails = self.sequence(rule, from + 1, split, end);
+            for head in &heads {
+                for tail in &tails {
+                    let mut sequence = Vec::with_capacity(tail.len() + 1);
+                    sequence.push(head.clone());
+                    sequence.extend(tail.iter().cloned());
+                    sequences.push(sequence);
+                    if sequences.len() >= DERIVATION_LIMIT {
+                        break 'split;
+                    }
+                }
+            }
+        }
+        self.sequences.insert(key, sequences.clone());
+        sequences
+    }
+}
+
+fn write_symbol<Terminal, Nonterminal>(
+    f: &mut fmt::Formatter<'_>,
+    symbol: &Symbol<Terminal, Nonterminal>,
+) -> fmt::Result
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    match symbol {
+        Symbol::Terminal(t) => write!(f, "{:?}", t),
+        Symbol::Nonterminal(n) => write!(f, "{:?}", n),
+    }
+}
+
+impl<Terminal, Nonterminal> fmt::Display for Derivation<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    /// Formats the derivation as `"E" → [ "E" "+" "E" • ]`, symbols with `Debug`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Derivation::Symbol(symbol) => write_symbol(f, symbol),
+            Derivation::Dot => write!(f, "•"),
+            Derivation::Rule {
+                nonterminal,
+                children,
+                ..
+            } => {
+                write!(f, "{:?} → [", nonterminal)?;
+                if children.is_empty() {
+                    write!(f, " ε")?;
+                }
+                for child in children {
+                    write!(f, " {}", child)?;
+                }
+                write!(f, " ]")
+            }
+        }
+    }
+}
+
+impl<Terminal, Nonterminal> fmt::Display for Example<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug + Clone,
+    Nonterminal: fmt::Debug + Clone,
+{
+    /// Formats the sentential form with the conflict point.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = self.position();
+        let symbols = self.sentential_form();
+        for i in 0..=symbols.len() {
+            if i == position {
+                write!(f, "{}•", if i == 0 { "" } else { " " })?;
+            }
+            if let Some(symbol) = symbols.get(i) {
+                if i > 0 || position == 0 {
+                    write!(f, " ")?;
+                }
+                write_symbol(f, symbol)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Terminal, Nonterminal> fmt::Display for Counterexample<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug + Clone,
+    Nonterminal: fmt::Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n  shortest prefix:", self.conflict)?;
+        for symbol in &self.prefix {
+            write!(f, " ")?;
+            write_symbol(f, symbol)?;
+        }
+        write!(f, " •")?;
+        let write_derivations = |f: &mut fmt::Formatter<'_>, example: &Example<_, _>| {
+            write!(f, "\n    {}:", example.action)?;
+            for derivation in &example.derivations {
+                write!(f, " {}", derivation)?;
+            }
+            Ok(())
+        };
+        match &self.unifying {
+            Some((first, second)) => {
+                write!(f, "\n  unifying example: {}", first)?;
+                write_derivations(f, first)?;
+                write_derivations(f, second)?;
+            }
+            None => {
+                for example in &self.examples {
+                    write!(f, "\n  example: {}", example)?;
+                    write_derivations(f, example)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Grammar, GrammarBuilder, Rule, Symbol};
+    use crate::lr::{Action, Algorithm, Conflict, ParseTable, TableError};
+
+    type TestGrammar = Grammar<&'static str, &'static str>;
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn build_grammar(rules: Vec<Rule<&'static str, &'static str>>) -> TestGrammar {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    fn table_conflicts(
+        grammar: &TestGrammar,
+    ) -> (
+        ParseTable<&'static str, &'static str>,
+        Vec<Conflict<&'static str>>,
+    ) {
+        match ParseTable::new(grammar, Algorithm::Lalr1) {
+            Err(TableError::Conflicts { conflicts, table }) => (*table, conflicts),
+            _ => panic!("the grammar has no conflicts"),
+        }
+    }
+
+    #[test]
+    fn dangling_else() {
+        // S -> if E then S | if E then S else S | x; E -> e
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("if"), n("E"), t("then"), n("S")]),
+            Rule::new(
+                "S",
+                vec![t("if"), n("E"), t("then"), n("S"), t("else"), n("S")],
+            ),
+            Rule::new("S", vec![t("x")]),
+            Rule::new("E", vec![t("e")]),
+        ]);
+        let (table, conflicts) = table_conflicts(&grammar);
+        assert_eq!(conflicts.len(), 1);
+        let counterexample = table.counterexample(&conflicts[0]);
+        assert_eq!(
+            counterexample.prefix,
+            vec![t("if"), n("E"), t("then"), n("S")]
+        );
+        assert_eq!(counterexample.examples.len(), 2);
+        assert_eq!(counterexample.examples[0].position(), 4);
+        assert_eq!(
+            counterexample.examples[0].to_string(),
+            "\"if\" \"E\" \"then\" \"S\" • \"else\" \"S\""
+        );
+
+        let (shift, reduce) = counterexample.unifying.clone().unwrap();
+        assert!(matches!(shift.action, Action::Shift(_)));
+        assert_eq!(reduce.action, Action::Reduce(0));
+        assert_eq!(shift.sentential_form(), reduce.sentential_form());
+        assert_eq!(
+            shift.to_string(),
+            "\"if\" \"E\" \"then\" \"if\" \"E\" \"then\" \"S\" • \"else\" \"S\""
+        );
+        assert_eq!(
+            reduce.derivations[0].to_string(),
+            "\"S\" → [ \"if\" \"E\" \"then\" \"S\" → [ \"if\" \"E\" \"then\" \"S\" • ] \
+             \"else\" \"S\" ]"
+        );
+        assert_eq!(
+            shift.derivations[0].to_string(),
+            "\"S\" → [ \"if\" \"E\" \"then\" \"S\" → [ \"if\" \"E\" \"then\" \"S\" • \
+             \"else\" \"S\" ] ]"
+        );
+        assert!(counterexample
+            .to_string()
+            .contains("\n  unifying example: \"if\" \"E\" \"then\" \"if\""));
+    }
+
+    #[test]
+    fn ambiguous_reductions() {
+        // S -> A y | B y; A -> x; B -> x
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("A"), t("y")]),
+            Rule::new("S", vec![n("B"), t("y")]),
+            Rule::new("A", vec![t("x")]),
+            Rule::new("B", vec![t("x")]),
+        ]);
+        let (table, conflicts) = table_conflicts(&grammar);
+        let counterexample = &table.counterexamples(&conflicts)[0];
+        let (first, second) = counterexample.unifying.as_ref().unwrap();
+        assert_eq!(first.action, Action::Reduce(2));
+        assert_eq!(second.action, Action::Reduce(3));
+        assert_eq!(first.to_string(), "\"x\" • \"y\"");
+        assert_eq!(
+            second.derivations[0].to_string(),
+            "\"S\" → [ \"B\" → [ \"x\" • ] \"y\" ]"
+        );
+    }
+
+    #[test]
+    fn nonunifying_examples() {
+        // LALR(1) merges the contexts of A -> c and B -> c
+        // S -> a A d | b B d | a B e | b A e; A -> c; B -> c
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("A"), t("d")]),
+            Rule::new("S", vec![t("b"), n("B"), t("d")]),
+            Rule::new("S", vec![t("a"), n("B"), t("e")]),
+            Rule::new("S", vec![t("b"), n("A"), t("e")]),
+            Rule::new("A", vec![t("c")]),
+            Rule::new("B", vec![t("c")]),
+        ]);
+        let (table, conflicts) = table_conflicts(&grammar);
+        let counterexample = table.counterexample(&conflicts[0]);
+        assert_eq!(counterexample.conflict, conflicts[0]);
+        assert_eq!(counterexample.prefix, vec![t("a"), t("c")]);
+        assert_eq!(counterexample.unifying, None);
+        let examples: Vec<String> = counterexample
+            .examples
+            .iter()
+            .map(|example| example.to_string())
+            .collect();
+        assert_eq!(examples, vec!["\"a\" \"c\" • \"d\"", "\"b\" \"c\" • \"d\""]);
+        assert_eq!(
+            counterexample
+                .to_string()
+                .lines()
+                .skip(1)
+                .collect::<Vec<_>>(),
+            vec![
+                "  shortest prefix: \"a\" \"c\" •",
+                "  example: \"a\" \"c\" • \"d\"",
+                "    reduce by rule 4: \"S\" → [ \"a\" \"A\" → [ \"c\" • ] \"d\" ]",
+                "  example: \"b\" \"c\" • \"d\"",
+                "    reduce by rule 5: \"S\" → [ \"b\" \"B\" → [ \"c\" • ] \"d\" ]",
+            ]
+        );
+    }
+
+    #[test]
+    fn lookahead_from_nonterminal() {
+        // S -> A B | x C; A -> x; C -> ; B -> y | C y
+        // the lookahead after the reduction comes from a derivation of B
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("A"), n("B")]),
+            Rule::new("S", vec![t("x"), n("C"), n("B")]),
+            Rule::new("A", vec![t("x")]),
+            Rule::new("C", vec![]),
+            Rule::new("B", vec![t("y")]),
+            Rule::new("B", vec![n("C"), t("y")]),
+        ]);
+        let (table, conflicts) = table_conflicts(&grammar);
+        let counterexample = table.counterexample(&conflicts[0]);
+        assert_eq!(
+            counterexample.conflict.lookahead,
+            crate::grammar::Lookahead::Terminal("y")
+        );
+        let reduce_a = counterexample
+            .examples
+            .iter()
+            .find(|example| example.action == Action::Reduce(2))
+            .unwrap();
+        assert_eq!(reduce_a.to_string(), "\"x\" • \"y\"");
+        assert_eq!(
+            reduce_a.derivations[0].to_string(),
+            "\"S\" → [ \"A\" → [ \"x\" • ] \"B\" → [ \"y\" ] ]"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Checking the conflicts of a parse table against the expected conflicts of the grammar.
+
+use super::{Action, Conflict, ConflictKind, Lr0Automaton};
+use crate::grammar::{ExpectedConflicts, Grammar, IndexedSymbol};
+use std::fmt;
+
+/// A declared number of conflicts that differs from the number of conflicts of the table.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ConflictCountMismatch {
+    /// The rule with the declaration, `None` for the declaration of the grammar.
+    pub rule: Option<usize>,
+    pub kind: ConflictKind,
+    pub expected: usize,
+    pub found: usize,
+}
+
+fn expected_count(expected: ExpectedConflicts, kind: ConflictKind) -> Option<usize> {
+    match kind {
+        ConflictKind::ShiftReduce => expected.shift_reduce,
+        ConflictKind::ReduceReduce => expected.reduce_reduce,
+    }
+}
+
+/// Compare the conflicts with the expected conflicts of the grammar and its rules.
+///
+/// Returns `None` if the grammar declares no expected conflicts at all.
+pub(crate) fn check_conflict_counts<Terminal, Nonterminal>(
+    grammar: &Grammar<Terminal, Nonterminal>,
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+    conflicts: &[Conflict<Terminal>],
+) -> Option<Vec<ConflictCountMismatch>>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let rules = grammar.rules();
+    if grammar.expected_conflicts().is_empty()
+        && rules
+            .iter()
+            .all(|rule| rule.expected_conflicts().is_empty())
+    {
+        return None;
+    }
+
+    let kinds = [ConflictKind::ShiftReduce, ConflictKind::ReduceReduce];
+    let mut global = [0; 2];
+    let mut per_rule = vec![[0; 2]; rules.len()];
+    for conflict in conflicts {
+        let kind = conflict.kind();
+        let k = kinds.iter().position(|&other| other == kind).unwrap();
+        let mut involved: Vec<usize> = conflict
+            .actions
+            .iter()
+            .filter_map(|action| match *action {
+                Action::Reduce(rule) => Some(rule),
+                _ => None,
+            })
+            .collect();
+        if let Some(t) = automaton.grammar.lookahead_index(&conflict.lookahead) {
+            involved.extend(
+                automaton.states[conflict.state]
+                    .items
+                    .iter()
+                    .filter(|&&item| {
+                        item.rule < rules.len()
+                            && automaton.indexed_next_symbol(item)
+                                == Some(IndexedSymbol::Terminal(t))
+                    })
+                    .map(|item| item.rule),
+            );
+        }
+        involved.sort_unstable();
+        involved.dedup();
+        involved.retain(|&rule| expected_count(rules[rule].expected_conflicts(), kind).is_some());
+        if involved.is_empty() {
+            global[k] += 1;
+        }
+        for rule in involved {
+            per_rule[rule][k] += 1;
+        }
+    }
+
+    let mut mismatches = vec![];
+    for (k, &kind) in kinds.iter().enumerate() {
+        let expected = expected_count(grammar.expected_conflicts(), kind).unwrap_or(0);
+        if expected != global[k] {
+            mismatches.push(ConflictCountMismatch {
+                rule: None,
+                kind,
+                expected,
+                found: global[k],
+            });
+        }
+    }
+    for (rule, counts) in per_rule.iter().enumerate() {
+        for (k, &kind) in kinds.iter().enumerate() {
+            if let Some(expected) = expected_count(rules[rule].expected_conflicts(), kind) {
+                if expected != counts[k] {
+                    mismatches.push(ConflictCountMismatch {
+                        rule: Some(rule),
+                        kind,
+                        expected,
+                        found: counts[k],
+                    });
+                }
+            }
+        }
+    }
+    Some(mismatches)
+}
+
+impl fmt::Display for ConflictCountMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} {} conflicts", self.expected, self.kind)?;
+        if let Some(rule) = self.rule {
+            write!(f, " involving rule {}", rule)?;
+        }
+        write!(f, ", found {}", self.found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule, Symbol};
+    use crate::lr::{Algorithm, ParseTable, TableError};
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn expect(shift_reduce: Option<usize>, reduce_reduce: Option<usize>) -> ExpectedConflicts {
+        ExpectedConflicts {
+            shift_reduce,
+            reduce_reduce,
+        }
+    }
+
+    /// S -> if e then S | if e then S else S | x | A; A -> y | B; B -> y
+    fn build_grammar(
+        expected: ExpectedConflicts,
+        if_then: ExpectedConflicts,
+        a: ExpectedConflicts,
+    ) -> Grammar<&'static str, &'static str> {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![t("if"), t("e"), t("then"), n("S")]).expect_conflicts(if_then),
+                Rule::new(
+                    "S",
+                    vec![t("if"), t("e"), t("then"), n("S"), t("else"), n("S")],
+                ),
+                Rule::new("S", vec![t("x")]),
+                Rule::new("S", vec![n("A")]),
+                Rule::new("A", vec![t("y")]).expect_conflicts(a),
+                Rule::new("A", vec![n("B")]),
+                Rule::new("B", vec![t("y")]),
+            ])
+            .symbol_precedence(vec![])
+            .expected_conflicts(expected)
+            .build()
+            .unwrap()
+    }
+
+    fn mismatches(grammar: &Grammar<&'static str, &'static str>) -> Vec<ConflictCountMismatch> {
+        match ParseTable::new(grammar, Algorithm::Lalr1) {
+            Err(TableError::UnexpectedConflicts { mismatches, .. }) => mismatches,
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the conflicts are expected"),
+        }
+    }
+
+    #[test]
+    fn undeclared_conflicts_are_errors() {
+        let none = ExpectedConflicts::default();
+        let grammar = build_grammar(none, none, none);
+        assert!(matches!(
+            ParseTable::new(&grammar, Algorithm::Lalr1),
+            Err(TableError::Conflicts { .. })
+        ));
+    }
+
+    #[test]
+    fn global_expectations() {
+        let none = ExpectedConflicts::default();
+        let grammar = build_grammar(expect(Some(1), Some(2)), none, none);
+        let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+        assert_eq!(table.conflicts().len(), 3);
+        let shift_reduce = table
+            .conflicts()
+            .iter()
+            .filter(|conflict| conflict.kind() == ConflictKind::ShiftReduce);
+        assert_eq!(shift_reduce.count(), 1);
+
+        // undeclared kinds expect no conflicts
+        let grammar = build_grammar(expect(Some(1), None), none, none);
+        let mismatches = mismatches(&grammar);
+        assert_eq!(
+            mismatches,
+            vec![ConflictCountMismatch {
+                rule: None,
+                kind: ConflictKind::ReduceReduce,
+                expected: 0,
+                found: 2,
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "expected 0 reduce/reduce conflicts, found 2"
+        );
+    }
+
+    #[test]
+    fn rule_expectations() {
+        let none = ExpectedConflicts::default();
+        let grammar = build_grammar(none, expect(Some(1), None), expect(None, Some(2)));
+        assert!(ParseTable::new(&grammar, Algorithm::Lalr1).is_ok());
+
+        // conflicts of rules with declarations are not counted globally
+        let grammar = build_grammar(
+            expect(Some(1), Some(0)),
+            expect(Some(2), None),
+            expect(None, Some(2)),
+        );
+        let mismatches = mismatches(&grammar);
+        let messages: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected 1 shift/reduce conflicts, found 0",
+                "expected 2 shift/reduce conflicts involving rule 0, found 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! IELR(1) state splitting (Denny and Malloy).
+//!
+//! LALR(1) merges LR(1) states with the same core, which can introduce conflicts
+//! that the canonical LR(1) automaton does not have. IELR(1) annotates the states
+//! with the tokens of such conflicts and splits only the states whose lookahead
+//! contributions to those tokens differ, so the result has the LR(1) behavior
+//! with nearly the LALR(1) number of states.
+//!
+//! A conflict on a token in a state can only depend on merging if one of
+//! the conflicting reductions does not have the token as a lookahead on every
+//! path to the state. Such tokens are tracked in the kernel lookaheads of the state
+//! and of all states it can be reached from, and the LR(0) states are split by them.
+//! The lookaheads of the split automaton are then recomputed by DeRemer and Pennello.
+//!
+//! Splitting by these tokens gives the LR(1) lookaheads of the tracked tokens. A conflict
+//! the split states still have is inherent to LR(1), so the tokens are only kept for
+//! the states where a split state prefers another action than the merged state,
+//! and the LR(0) states are split by the remaining tokens.
+
+use super::lalr::{lalr_lookaheads, Lookaheads};
+use super::lr1::{closure_lookaheads, split_automaton, Merging};
+use super::precedence::resolve_conflicts;
+use super::table::action_preference;
+use super::{Action, Item, Lr0Automaton};
+use crate::grammar::{GrammarAnalysis, IndexedSymbol};
+use fixedbitset::FixedBitSet;
+
+/// Split the states of an LR(0) automaton that cause conflicts not present in LR(1).
+///
+/// The automaton is returned unchanged if it has no such conflicts.
+pub(crate) fn ielr_automaton<Terminal, Nonterminal>(
+    lr0: Lr0Automaton<Terminal, Nonterminal>,
+) -> Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let lookaheads = lalr_lookaheads(&lr0);
+    let conflicts = conflict_tokens(&lr0, &lookaheads);
+    let masks = propagate(&lr0, conflicts.clone());
+    if masks.iter().all(|mask| mask.count_ones(..) == 0) {
+        return lr0;
+    }
+    // the split automaton has the LR(1) lookaheads of the annotated tokens,
+    // splitting is only needed where they change the preferred action
+    let split = split_automaton(&lr0, &masks, Merging::Identical);
+    let conflicts = changed_actions(&lr0, &lookaheads, &split, conflicts);
+    let masks = propagate(&lr0, conflicts);
+    if masks.iter().all(|mask| mask.count_ones(..) == 0) {
+        lr0
+    } else {
+        split_automaton(&lr0, &masks, Merging::Identical)
+    }
+}
+
+/// Compute the tokens of the conflicts of each state that depend on merged lookaheads.
+fn conflict_tokens<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    lookaheads: &Lookaheads,
+) -> Vec<FixedBitSet>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let end = lr0.grammar.end();
+    let analysis = GrammarAnalysis::from_indexed(lr0.grammar.clone());
+    let inherent = inherent_lookaheads(lr0, &analysis);
+
+    let mut conflicts = vec![FixedBitSet::with_capacity(end + 1); lr0.states.len()];
+    for (state, data) in lr0.states.iter().enumerate() {
+        for token in 0..=end {
+            let mut actions = 0;
+            let mut contributed = false;
+            if shift_action(lr0, state, token).is_some() {
+                actions += 1;
+            }
+            for (&rule, set) in &lookaheads[state] {
+                if set.contains(token) {
+                    actions += 1;
+                    let item = Item::new(rule, lr0.right_hand(rule).len());
+                    let index = data.item_index(item).unwrap();
+                    contributed |= !inherent[state][index].contains(token);
+                }
+            }
+            if actions > 1 && contributed {
+                conflicts[state].insert(token);
+            }
+        }
+    }
+    conflicts
+}
+
+/// Add the tokens of every state to the states it can be reached from.
+fn propagate<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    mut masks: Vec<FixedBitSet>,
+) -> Vec<FixedBitSet> {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for state in 0..lr0.states.len() {
+            for &target in lr0.states[state].transitions.values() {
+                if !masks[target].is_subset(&masks[state]) {
+                    let mask = masks[target].clone();
+                    masks[state].union_with(&mask);
+                    changed = true;
+                }
+            }
+        }
+    }
+    masks
+}
+
+/// Keep the conflict tokens of the states for which a state of the split automaton
+/// with the same core prefers another action than the merged state.
+///
+/// The other conflicts are the conflicts of the LR(1) states themselves,
+/// which splitting cannot remove.
+fn changed_actions<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    lookaheads: &Lookaheads,
+    split: &Lr0Automaton<Terminal, Nonterminal>,
+    mut conflicts: Vec<FixedBitSet>,
+) -> Vec<FixedBitSet>
+where
+    Terminal: Ord + Clone,
+{
+    let split_lookaheads = lalr_lookaheads(split);
+    for (state, tokens) in conflicts.iter_mut().enumerate() {
+        let kept: Vec<usize> = tokens
+            .ones()
+            .filter(|&token| {
+                let merged = preferred_action(lr0, lookaheads, state, token);
+                split
+                    .states
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, data)| data.core == state)
+                    .any(|(split_state, _)| {
+                        preferred_action(split, &split_lookaheads, split_state, token) != merged
+                    })
+            })
+            .collect();
+        tokens.clear();
+        tokens.extend(kept);
+    }
+    conflicts
+}
+
+/// The shift or accept action of a state on a token, with the target state of shifts omitted.
+fn shift_action<Terminal, Nonterminal>(
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+    state: usize,
+    token: usize,
+) -> Option<Action> {
+    let data = &automaton.states[state];
+    if token == automaton.grammar.end() {
+        let accepting = Item::new(automaton.augmented_rule(), 1);
+        Some(Action::Accept).filter(|_| data.kernel.contains(&accepting))
+    } else {
+        Some(Action::Shift(0)).filter(|_| {
+            data.transitions
+                .contains_key(&IndexedSymbol::Terminal(token))
+        })
+    }
+}
+
+/// The action a parse table prefers for a state and a token, after resolving
+/// its conflicts by precedence.
+fn preferred_action<Terminal, Nonterminal>(
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+    lookaheads: &Lookaheads,
+    state: usize,
+    token: usize,
+) -> Option<Action>
+where
+    Terminal: Clone,
+{
+    let grammar = &automaton.grammar;
+    let mut actions = vec![vec![vec![]; grammar.end() + 1]];
+    let cell = &mut actions[0][token];
+    cell.extend(shift_action(automaton, state, token));
+    for (&rule, set) in &lookaheads[state] {
+        if set.contains(token) {
+            cell.push(Action::Reduce(rule));
+        }
+    }
+    resolve_conflicts(grammar, &mut actions);
+    actions[0][token]
+        .iter()
+        .copied()
+        .min_by_key(action_preference)
+}
+
+/// Compute the lookaheads that the items of each state have on every path to the state.
+///
+/// The result is the greatest fixed point of intersecting the lookaheads of the predecessors,
+/// aligned with `State::items`.
+fn inherent_lookaheads<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    analysis: &GrammarAnalysis<Terminal, Nonterminal>,
+) -> Vec<Vec<FixedBitSet>>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let size = lr0.grammar.end() + 1;
+    let mut all = FixedBitSet::with_capacity(size);
+    all.insert_range(..);
+
+    let mut predecessors = vec![vec![]; lr0.states.len()];
+    for (state, data) in lr0.states.iter().enumerate() {
+        for &target in data.transitions.values() {
+            if predecessors[target].last() != Some(&state) {
+                predecessors[target].push(state);
+            }
+        }
+    }
+
+    let mut kernels: Vec<Vec<FixedBitSet>> = lr0
+        .states
+        .iter()
+        .map(|state| vec![all.clone(); state.kernel.len()])
+        .collect();
+    kernels[0][0].clear();
+    kernels[0][0].insert(lr0.grammar.end());
+    let mut inherent: Vec<Vec<FixedBitSet>> = lr0
+        .states
+        .iter()
+        .zip(&kernels)
+        .map(|(state, kernel)| closure_lookaheads(lr0, analysis, state, kernel))
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for state in 1..lr0.states.len() {
+            let mut state_changed = false;
+            for (k, item) in lr0.states[state].kernel.iter().enumerate() {
+                let previous = Item::new(item.rule, item.dot - 1);
+                let mut set = all.clone();
+                for &p in &predecessors[state] {
+                    let index = lr0.states[p].item_index(previous).unwrap();
+                    set.intersect_with(&inherent[p][index]);
+                }
+                if set != kernels[state][k] {
+                    kernels[state][k] = set;
+                    state_changed = true;
+                }
+            }
+            if state_changed {
+                inherent[state] =
+                    closure_lookaheads(lr0, analysis, &lr0.states[state], &kernels[state]);
+                changed = true;
+            }
+        }
+    }
+    inherent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Grammar, GrammarBuilder, Rule, Symbol};
+    use crate::lr::lalr::is_reduction;
+
+    type TestGrammar = Grammar<&'static str, &'static str>;
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn build_grammar(rules: Vec<Rule<&'static str, &'static str>>) -> TestGrammar {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    /// The tokens each LR(0) state tracks before removing the conflicts inherent to LR(1).
+    fn annotate(lr0: &Lr0Automaton<&'static str, &'static str>) -> Vec<FixedBitSet> {
+        propagate(lr0, conflict_tokens(lr0, &lalr_lookaheads(lr0)))
+    }
+
+    fn mask_tokens(
+        lr0: &Lr0Automaton<&'static str, &'static str>,
+        mask: &FixedBitSet,
+    ) -> Vec<String> {
+        mask.ones()
+            .map(|t| format!("{:?}", lr0.grammar.lookahead(t)))
+            .collect()
+    }
+
+    #[test]
+    fn mysterious_conflicts_are_annotated() {
+        // S -> a A d | b B d | a B e | b A e; A -> c; B -> c
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("A"), t("d")]),
+            Rule::new("S", vec![t("b"), n("B"), t("d")]),
+            Rule::new("S", vec![t("a"), n("B"), t("e")]),
+            Rule::new("S", vec![t("b"), n("A"), t("e")]),
+            Rule::new("A", vec![t("c")]),
+            Rule::new("B", vec![t("c")]),
+        ]);
+        let lr0 = Lr0Automaton::new(&grammar);
+        let masks = annotate(&lr0);
+        let conflicted = lr0.goto(lr0.goto(0, &t("a")).unwrap(), &t("c")).unwrap();
+        let expected = vec!["Terminal(\"d\")", "Terminal(\"e\")"];
+        assert_eq!(mask_tokens(&lr0, &masks[conflicted]), expected);
+        assert_eq!(mask_tokens(&lr0, &masks[0]), expected);
+        assert_eq!(
+            mask_tokens(&lr0, &masks[lr0.accepting_state()]),
+            Vec::<String>::new()
+        );
+
+        let ielr = ielr_automaton(lr0.clone());
+        assert_eq!(ielr.states.len(), lr0.states.len() + 1);
+        let via_a = ielr.goto(ielr.goto(0, &t("a")).unwrap(), &t("c")).unwrap();
+        let via_b = ielr.goto(ielr.goto(0, &t("b")).unwrap(), &t("c")).unwrap();
+        assert_ne!(via_a, via_b);
+        assert_eq!(ielr.states[via_a].core(), conflicted);
+        assert_eq!(ielr.states[via_b].core(), conflicted);
+    }
+
+    #[test]
+    fn inherent_conflicts_are_not_annotated() {
+        // S -> S + S | S * S | id
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("S"), t("+"), n("S")]),
+            Rule::new("S", vec![n("S"), t("*"), n("S")]),
+            Rule::new("S", vec![t("id")]),
+        ]);
+        let lr0 = Lr0Automaton::new(&grammar);
+        assert!(annotate(&lr0).iter().all(|mask| mask.count_ones(..) == 0));
+        assert_eq!(ielr_automaton(lr0.clone()).states, lr0.states);
+    }
+
+    #[test]
+    fn reductions_have_inherent_lookaheads() {
+        // S -> A c | x A d; A -> a
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("A"), t("c")]),
+            Rule::new("S", vec![t("x"), n("A"), t("d")]),
+            Rule::new("A", vec![t("a")]),
+        ]);
+        let lr0 = Lr0Automaton::new(&grammar);
+        let analysis = GrammarAnalysis::from_indexed(lr0.grammar.clone());
+        let inherent = inherent_lookaheads(&lr0, &analysis);
+        let lookaheads = lalr_lookaheads(&lr0);
+
+        // A -> a . is reached with c from the start and with d after x
+        let state = lr0.goto(0, &t("a")).unwrap();
+        assert_eq!(
+            lr0.goto(lr0.goto(0, &t("x")).unwrap(), &t("a")),
+            Some(state)
+        );
+        let index = lr0.states[state].item_index(Item::new(2, 1)).unwrap();
+        assert!(is_reduction(&lr0, Item::new(2, 1)));
+        assert_eq!(inherent[state][index].count_ones(..), 0);
+        assert_eq!(lookaheads[state][&2].count_ones(..), 2);
+
+        // A -> . a in the start state always has c
+        let index = lr0.states[0].item_index(Item::new(2, 0)).unwrap();
+        assert_eq!(
+            mask_tokens(&lr0, &inherent[0][index]),
+            vec!["Terminal(\"c\")"]
+        );
+    }
+}
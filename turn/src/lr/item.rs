@@ -0,0 +1,27 @@
+/// An LR(0) item: a grammar rule with a position in its right-hand side.
+///
+/// The rule is referenced by its index in `Grammar::rules`;
+/// the augmented start rule `S' -> S` has the index `Grammar::rules().len()`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct Item {
+    /// The index of the rule.
+    pub rule: usize,
+    /// The number of right-hand symbols before the dot.
+    pub dot: usize,
+}
+
+impl Item {
+    #[inline]
+    pub fn new(rule: usize, dot: usize) -> Item {
+        Item { rule, dot }
+    }
+
+    /// The item with the dot moved over the next symbol.
+    #[inline]
+    pub fn advance(self) -> Item {
+        Item {
+            rule: self.rule,
+            dot: self.dot + 1,
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! LALR(1) lookahead computation by DeRemer and Pennello.
+//!
+//! The lookaheads are computed from the LR(0) automaton with the relations
+//! `reads`, `includes` and `lookback` over its nonterminal transitions.
+//! The computation does not depend on the automaton being the canonical LR(0) one,
+//! so it is also used on automata with split states.
+
+use super::{Item, Lr0Automaton};
+use crate::grammar::IndexedSymbol;
+use fixedbitset::FixedBitSet;
+use std::collections::{BTreeMap, HashMap};
+
+/// The lookahead sets of the reductions of each state, keyed by rule.
+pub(crate) type Lookaheads = Vec<BTreeMap<usize, FixedBitSet>>;
+
+/// Compute the LALR(1) lookaheads of all reductions in an automaton.
+///
+/// The end of input is included in the lookaheads of reductions that can precede
+/// the acceptance of the input.
+pub(crate) fn lalr_lookaheads<Terminal, Nonterminal>(
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+) -> Lookaheads {
+    let grammar = &automaton.grammar;
+    let size = grammar.end() + 1;
+    let nullable = grammar.nullable();
+
+    // nonterminal transitions (state, nonterminal)
+    let mut transitions = vec![];
+    let mut indices = HashMap::new();
+    for (state, data) in automaton.states.iter().enumerate() {
+        for &symbol in data.transitions.keys() {
+            if let IndexedSymbol::Nonterminal(n) = symbol {
+                indices.insert((state, n), transitions.len());
+                transitions.push((state, n));
+            }
+        }
+    }
+
+    // directly read terminals and the reads relation
+    let mut sets = vec![FixedBitSet::with_capacity(size); transitions.len()];
+    let mut reads = vec![vec![]; transitions.len()];
+    for (i, &(state, nonterminal)) in transitions.iter().enumerate() {
+        let target = automaton
+            .indexed_goto(state, IndexedSymbol::Nonterminal(nonterminal))
+            .unwrap();
+        for &symbol in automaton.states[target].transitions.keys() {
+            match symbol {
+                IndexedSymbol::Terminal(t) => sets[i].insert(t),
+                IndexedSymbol::Nonterminal(n) if nullable.contains(n) => {
+                    reads[i].push(indices[&(target, n)])
+                }
+                IndexedSymbol::Nonterminal(_) => {}
+            }
+        }
+        if state == 0 && nonterminal == grammar.start {
+            sets[i].insert(grammar.end());
+        }
+    }
+    digraph(&reads, &mut sets);
+
+    // the includes and lookback relations
+    let mut includes = vec![vec![]; transitions.len()];
+    let mut lookback: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, &(state, nonterminal)) in transitions.iter().enumerate() {
+        for &rule in &grammar.rules_of[nonterminal] {
+            let right_hand = &grammar.rules[rule].right_hand;
+            let mut current = state;
+            for (position, &symbol) in right_hand.iter().enumerate() {
+                if let IndexedSymbol::Nonterminal(n) = symbol {
+                    let rest_nullable = right_hand[position + 1..].iter().all(|s| match *s {
+                        IndexedSymbol::Terminal(_) => false,
+                        IndexedSymbol::Nonterminal(n) => nullable.contains(n),
+                    });
+                    if rest_nullable {
+                        includes[indices[&(current, n)]].push(i);
+                    }
+                }
+                current = automaton.indexed_goto(current, symbol).unwrap();
+            }
+            lookback.entry((current, rule)).or_default().push(i);
+        }
+    }
+    digraph(&includes, &mut sets);
+
+    automaton
+        .states
+        .iter()
+        .enumerate()
+        .map(|(state, data)| {
+            data.items
+                .iter()
+                .filter(|&&item| is_reduction(automaton, item))
+                .map(|item| {
+                    let mut lookaheads = FixedBitSet::with_capacity(size);
+                    for &i in lookback.get(&(state, item.rule)).into_iter().flatten() {
+                        lookaheads.union_with(&sets[i]);
+                    }
+                    (item.rule, lookaheads)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns true if the item is complete and is not the augmented start item.
+pub(crate) fn is_reduction<Terminal, Nonterminal>(
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+    item: Item,
+) -> bool {
+    item.rule != automaton.augmented_rule() && item.dot == automaton.right_hand(item.rule).len()
+}
+
+/// Propagate sets along a relation: afterwards, each set contains the sets
+/// of all elements reachable from it.
+///
+/// Strongly connected components are collapsed as they are found (DeRemer and Pennello).
+pub(crate) fn digraph(relation: &[Vec<usize>], sets: &mut [FixedBitSet]) {
+    let mut traversal = Digraph {
+        relation,
+        sets,
+        stack: vec![],
+        depth: vec![0; relation.len()],
+    };
+    for x in 0..relation.len() {
+        if traversal.depth[x] == 0 {
+            traversal.traverse(x);
+        }
+    }
+}
+
+struct Digraph<'a> {
+    relation: &'a [Vec<usize>],
+    sets: &'a mut [FixedBitSet],
+    stack: Vec<usize>,
+    /// 0 for unvisited elements, `usize::MAX` for finished ones.
+    depth: Vec<usize>,
+}
+
+impl Digraph<'_> {
+    fn traverse(&mut self, x: usize) {
+        self.stack.push(x);
+        let depth = self.stack.len();
+        self.depth[x] = depth;
+        for &y in &self.relation[x] {
+            if self.depth[y] == 0 {
+                self.traverse(y);
+            }
+            self.depth[x] = self.depth[x].min(self.depth[y]);
+            if x != y {
+                let set = self.sets[y].clone();
+                self.sets[x].union_with(&set);
+            }
+        }
+        if self.depth[x] == depth {
+            while let Some(top) = self.stack.pop() {
+                self.depth[top] = usize::MAX;
+                if top == x {
+                    break;
+                }
+                self.sets[top] = self.sets[x].clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Grammar, GrammarBuilder, Lookahead, Rule, Symbol};
+    use std::collections::BTreeSet;
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    /// The lookaheads of a reduction in the state reached by a sequence of symbols.
+    fn lookaheads(
+        automaton: &Lr0Automaton<&'static str, &'static str>,
+        lookaheads: &Lookaheads,
+        path: &[Symbol<&'static str, &'static str>],
+        rule: usize,
+    ) -> BTreeSet<Lookahead<&'static str>> {
+        let state = path.iter().fold(0, |state, symbol| {
+            automaton.goto(state, symbol).expect("missing transition")
+        });
+        lookaheads[state][&rule]
+            .ones()
+            .map(|t| automaton.grammar.lookahead(t))
+            .collect()
+    }
+
+    fn set(items: &[Lookahead<&'static str>]) -> BTreeSet<Lookahead<&'static str>> {
+        items.iter().cloned().collect()
+    }
+
+    #[test]
+    fn lalr_not_slr() {
+        // the dragon book grammar 4.49
+        // S -> L = R | R; L -> * R | id; R -> L
+        let grammar: Grammar<&'static str, &'static str> = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![n("L"), t("="), n("R")]),
+                Rule::new("S", vec![n("R")]),
+                Rule::new("L", vec![t("*"), n("R")]),
+                Rule::new("L", vec![t("id")]),
+                Rule::new("R", vec![n("L")]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let automaton = Lr0Automaton::new(&grammar);
+        let result = lalr_lookaheads(&automaton);
+        let eq = Lookahead::Terminal("=");
+
+        // FOLLOW(R) contains '=', but it is not a lookahead of R -> L after L
+        assert_eq!(
+            lookaheads(&automaton, &result, &[n("L")], 4),
+            set(&[Lookahead::End])
+        );
+        assert_eq!(
+            lookaheads(&automaton, &result, &[t("id")], 3),
+            set(&[eq, Lookahead::End])
+        );
+        assert_eq!(
+            lookaheads(&automaton, &result, &[t("*"), n("L")], 4),
+            set(&[eq, Lookahead::End])
+        );
+        assert_eq!(
+            lookaheads(&automaton, &result, &[n("L"), t("="), n("R")], 0),
+            set(&[Lookahead::End])
+        );
+    }
+
+    #[test]
+    fn nullable_reads_and_includes() {
+        // S -> A B 'c'; A -> 'a' | ε; B -> 'b' | ε
+        let grammar: Grammar<&'static str, &'static str> = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![n("A"), n("B"), t("c")]),
+                Rule::new("A", vec![t("a")]),
+                Rule::new("A", vec![]),
+                Rule::new("B", vec![t("b")]),
+                Rule::new("B", vec![]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let automaton = Lr0Automaton::new(&grammar);
+        let result = lalr_lookaheads(&automaton);
+
+        assert_eq!(
+            lookaheads(&automaton, &result, &[], 2),
+            set(&[Lookahead::Terminal("b"), Lookahead::Terminal("c")])
+        );
+        assert_eq!(
+            lookaheads(&automaton, &result, &[n("A")], 4),
+            set(&[Lookahead::Terminal("c")])
+        );
+        assert_eq!(
+            lookaheads(&automaton, &result, &[n("A"), n("B"), t("c")], 0),
+            set(&[Lookahead::End])
+        );
+    }
+
+    #[test]
+    fn digraph_cycles() {
+        let relation = vec![vec![1], vec![2], vec![0, 3], vec![]];
+        let mut sets: Vec<FixedBitSet> = (0..4)
+            .map(|i| {
+                let mut set = FixedBitSet::with_capacity(4);
+                set.insert(i);
+                set
+            })
+            .collect();
+        digraph(&relation, &mut sets);
+        for set in &sets[0..3] {
+            assert_eq!(set.ones().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        }
+        assert_eq!(sets[3].ones().collect::<Vec<_>>(), vec![3]);
+    }
+}
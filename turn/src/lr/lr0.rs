@@ -0,0 +1,495 @@
+use super::Item;
+use crate::grammar::{Grammar, IndexedGrammar, IndexedSymbol, Symbol};
+use fixedbitset::FixedBitSet;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// A state of an LR automaton: a set of items and its transitions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct State {
+    pub(crate) kernel: Vec<Item>,
+    pub(crate) items: Vec<Item>,
+    pub(crate) transitions: BTreeMap<IndexedSymbol, usize>,
+    pub(crate) accessing_symbol: Option<IndexedSymbol>,
+    pub(crate) core: usize,
+}
+
+impl State {
+    /// The kernel items of the state, sorted.
+    ///
+    /// These are the items the state was created from; the kernel of the starting state
+    /// is the augmented start item `S' -> . S`.
+    #[inline]
+    pub fn kernel(&self) -> &[Item] {
+        &self.kernel
+    }
+
+    /// The closure of the kernel: the kernel items followed by the added items, sorted.
+    #[inline]
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// The index of the LR(0) state with the same items.
+    ///
+    /// States of the canonical LR(0) automaton are their own cores;
+    /// automata with split states can have multiple states with the same core.
+    #[inline]
+    pub fn core(&self) -> usize {
+        self.core
+    }
+
+    /// The index of an item of the state.
+    pub(crate) fn item_index(&self, item: Item) -> Option<usize> {
+        let (kernel, closure) = self.items.split_at(self.kernel.len());
+        match kernel.binary_search(&item) {
+            Ok(index) => Some(index),
+            Err(_) => closure
+                .binary_search(&item)
+                .ok()
+                .map(|index| kernel.len() + index),
+        }
+    }
+}
+
+/// The canonical collection of LR(0) item sets of a grammar and the goto transitions between them.
+///
+/// The grammar is augmented with the start rule `S' -> S`, which has the index
+/// `grammar.rules().len()`. State 0 is the starting state.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+/// use turn::lr::{Item, Lr0Automaton};
+///
+/// // S -> '(' S ')' | 'x'
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("S")
+///     .rules(vec![
+///         Rule::new(
+///             "S",
+///             vec![Symbol::Terminal('('), Symbol::Nonterminal("S"), Symbol::Terminal(')')],
+///         ),
+///         Rule::new("S", vec![Symbol::Terminal('x')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let automaton = Lr0Automaton::new(&grammar);
+///
+/// assert_eq!(automaton.states().len(), 6);
+/// assert_eq!(automaton.states()[0].kernel(), &[Item::new(2, 0)]);
+/// let state = automaton.goto(0, &Symbol::Terminal('(')).unwrap();
+/// assert_eq!(automaton.goto(state, &Symbol::Terminal('(')), Some(state));
+/// assert_eq!(
+///     automaton.display_item(Item::new(0, 1)).to_string(),
+///     "\"S\" -> '(' . \"S\" ')'"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lr0Automaton<Terminal, Nonterminal> {
+    pub(crate) grammar: IndexedGrammar<Terminal, Nonterminal>,
+    pub(crate) states: Vec<State>,
+}
+
+impl<Terminal, Nonterminal> Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Construct the canonical LR(0) automaton of a grammar.
+    pub fn new(grammar: &Grammar<Terminal, Nonterminal>) -> Self {
+        Lr0Automaton::from_indexed(IndexedGrammar::new(grammar))
+    }
+
+    pub(crate) fn from_indexed(grammar: IndexedGrammar<Terminal, Nonterminal>) -> Self {
+        let start = vec![Item::new(grammar.augmented_rule(), 0)];
+        let mut automaton = Lr0Automaton {
+            grammar,
+            states: vec![],
+        };
+        let mut indices = HashMap::new();
+        automaton.add_state(start, None, &mut indices);
+
+        let mut current = 0;
+        while current < automaton.states.len() {
+            let mut successors: BTreeMap<IndexedSymbol, Vec<Item>> = BTreeMap::new();
+            for &item in &automaton.states[current].items {
+                if let Some(symbol) = automaton.indexed_next_symbol(item) {
+                    successors.entry(symbol).or_default().push(item.advance());
+                }
+            }
+            for (symbol, mut kernel) in successors {
+                kernel.sort();
+                let target = match indices.get(&kernel) {
+                    Some(&target) => target,
+                    None => automaton.add_state(kernel, Some(symbol), &mut indices),
+                };
+                automaton.states[current].transitions.insert(symbol, target);
+            }
+            current += 1;
+        }
+        automaton
+    }
+
+    fn add_state(
+        &mut self,
+        kernel: Vec<Item>,
+        accessing_symbol: Option<IndexedSymbol>,
+        indices: &mut HashMap<Vec<Item>, usize>,
+    ) -> usize {
+        let index = self.states.len();
+        indices.insert(kernel.clone(), index);
+        self.states.push(State {
+            items: self.indexed_closure(&kernel),
+            kernel,
+            transitions: BTreeMap::new(),
+            accessing_symbol,
+            core: index,
+        });
+        index
+    }
+}
+
+impl<Terminal, Nonterminal> Lr0Automaton<Terminal, Nonterminal> {
+    /// The states of the automaton; state 0 is the starting state.
+    #[inline]
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    /// The index of the augmented start rule `S' -> S`.
+    #[inline]
+    pub fn augmented_rule(&self) -> usize {
+        self.grammar.augmented_rule()
+    }
+
+    /// The symbol after the dot of an item in the indexed grammar.
+    pub(crate) fn indexed_next_symbol(&self, item: Item) -> Option<IndexedSymbol> {
+        self.right_hand(item.rule).get(item.dot).copied()
+    }
+
+    /// The right-hand side of a rule, including the augmented start rule.
+    pub(crate) fn right_hand(&self, rule: usize) -> &[IndexedSymbol] {
+        self.grammar.right_hand(rule)
+    }
+
+    /// The closure of a sorted kernel in the indexed grammar.
+    pub(crate) fn indexed_closure(&self, kernel: &[Item]) -> Vec<Item> {
+        let mut added = FixedBitSet::with_capacity(self.grammar.nonterminals.len());
+        let mut stack: Vec<usize> = vec![];
+        let mut closure = vec![];
+        for &item in kernel {
+            if let Some(IndexedSymbol::Nonterminal(n)) = self.indexed_next_symbol(item) {
+                stack.push(n);
+            }
+        }
+        while let Some(nonterminal) = stack.pop() {
+            if added.put(nonterminal) {
+                continue;
+            }
+            for &rule in &self.grammar.rules_of[nonterminal] {
+                closure.push(Item::new(rule, 0));
+                if let Some(&IndexedSymbol::Nonterminal(n)) = self.right_hand(rule).first() {
+                    stack.push(n);
+                }
+            }
+        }
+        closure.sort();
+        kernel.iter().cloned().chain(closure).collect()
+    }
+
+    /// The state following the state on a transition over an indexed symbol.
+    #[inline]
+    pub(crate) fn indexed_goto(&self, state: usize, symbol: IndexedSymbol) -> Option<usize> {
+        self.states[state].transitions.get(&symbol).copied()
+    }
+}
+
+impl<Terminal, Nonterminal> Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Compute the closure of a set of items.
+    ///
+    /// The result contains the sorted kernel followed by the sorted added items.
+    pub fn closure(&self, kernel: &[Item]) -> Vec<Item> {
+        let mut kernel = kernel.to_vec();
+        kernel.sort();
+        kernel.dedup();
+        self.indexed_closure(&kernel)
+    }
+
+    /// The symbol after the dot of an item, `None` if the item is complete.
+    pub fn next_symbol(&self, item: Item) -> Option<Symbol<Terminal, Nonterminal>> {
+        self.indexed_next_symbol(item)
+            .map(|symbol| self.grammar.original_symbol(symbol))
+    }
+
+    /// The state following a state on a transition over a symbol.
+    pub fn goto(&self, state: usize, symbol: &Symbol<Terminal, Nonterminal>) -> Option<usize> {
+        self.grammar
+            .symbol(symbol)
+            .and_then(|symbol| self.indexed_goto(state, symbol))
+    }
+
+    /// The transitions of a state, ordered by terminals first.
+    pub fn transitions(
+        &self,
+        state: usize,
+    ) -> impl Iterator<Item = (Symbol<Terminal, Nonterminal>, usize)> + '_ {
+        self.states[state]
+            .transitions
+            .iter()
+            .map(move |(&symbol, &target)| (self.grammar.original_symbol(symbol), target))
+    }
+
+    /// The symbol all transitions into a state are labeled with, `None` for the starting state.
+    pub fn accessing_symbol(&self, state: usize) -> Option<Symbol<Terminal, Nonterminal>> {
+        self.states[state]
+            .accessing_symbol
+            .map(|symbol| self.grammar.original_symbol(symbol))
+    }
+
+    /// The state reached after reading the starting nonterminal from the starting state,
+    /// in which the input is accepted at the end of input.
+    pub fn accepting_state(&self) -> usize {
+        self.indexed_goto(0, IndexedSymbol::Nonterminal(self.grammar.start))
+            .expect("the starting state has a transition over the starting nonterminal")
+    }
+
+    /// Format an item as `A -> a . b`.
+    pub fn display_item(&self, item: Item) -> ItemDisplay<'_, Terminal, Nonterminal> {
+        ItemDisplay {
+            automaton: self,
+            item,
+        }
+    }
+}
+
+/// An item of an `Lr0Automaton` formatted as `A -> a . b`.
+///
+/// Symbols are formatted with `Debug`, the augmented start nonterminal as `<start>`.
+pub struct ItemDisplay<'a, Terminal, Nonterminal> {
+    automaton: &'a Lr0Automaton<Terminal, Nonterminal>,
+    item: Item,
+}
+
+impl<Terminal, Nonterminal> fmt::Display for ItemDisplay<'_, Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grammar = &self.automaton.grammar;
+        match grammar.rules.get(self.item.rule) {
+            Some(rule) => write!(f, "{:?} ->", grammar.nonterminals[rule.left_hand])?,
+            None => write!(f, "<start> ->")?,
+        }
+        let right_hand = self.automaton.right_hand(self.item.rule);
+        for (i, symbol) in right_hand.iter().enumerate() {
+            if i == self.item.dot {
+                write!(f, " .")?;
+            }
+            match *symbol {
+                IndexedSymbol::Terminal(t) => write!(f, " {:?}", grammar.terminals[t])?,
+                IndexedSymbol::Nonterminal(n) => write!(f, " {:?}", grammar.nonterminals[n])?,
+            }
+        }
+        if self.item.dot >= right_hand.len() {
+            write!(f, " .")?;
+        }
+        Ok(())
+    }
+}
+
+impl<Terminal, Nonterminal> fmt::Display for Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    /// List the items and transitions of all states.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, state) in self.states.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "state {}", i)?;
+            for &item in &state.items {
+                writeln!(
+                    f,
+                    "    {}",
+                    ItemDisplay {
+                        automaton: self,
+                        item
+                    }
+                )?;
+            }
+            for (&symbol, &target) in &state.transitions {
+                match symbol {
+                    IndexedSymbol::Terminal(t) => write!(f, "    {:?}", self.grammar.terminals[t])?,
+                    IndexedSymbol::Nonterminal(n) => {
+                        write!(f, "    {:?}", self.grammar.nonterminals[n])?
+                    }
+                }
+                writeln!(f, " => state {}", target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule};
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    /// The SLR expression grammar from the dragon book (4.1).
+    fn expression_grammar() -> Grammar<&'static str, &'static str> {
+        GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![n("E"), t("+"), n("T")]),
+                Rule::new("E", vec![n("T")]),
+                Rule::new("T", vec![n("T"), t("*"), n("F")]),
+                Rule::new("T", vec![n("F")]),
+                Rule::new("F", vec![t("("), n("E"), t(")")]),
+                Rule::new("F", vec![t("id")]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    fn goto_path(
+        automaton: &Lr0Automaton<&'static str, &'static str>,
+        symbols: &[Symbol<&'static str, &'static str>],
+    ) -> usize {
+        symbols.iter().fold(0, |state, symbol| {
+            automaton.goto(state, symbol).expect("missing transition")
+        })
+    }
+
+    #[test]
+    fn expression_grammar_collection() {
+        let automaton = Lr0Automaton::new(&expression_grammar());
+        assert_eq!(automaton.states().len(), 12);
+        assert_eq!(automaton.augmented_rule(), 6);
+
+        let start = &automaton.states()[0];
+        assert_eq!(start.kernel(), &[Item::new(6, 0)]);
+        assert_eq!(
+            start.items(),
+            &[
+                Item::new(6, 0),
+                Item::new(0, 0),
+                Item::new(1, 0),
+                Item::new(2, 0),
+                Item::new(3, 0),
+                Item::new(4, 0),
+                Item::new(5, 0),
+            ]
+        );
+
+        let accepting = automaton.accepting_state();
+        assert_eq!(
+            automaton.states()[accepting].kernel(),
+            &[Item::new(0, 1), Item::new(6, 1)]
+        );
+
+        // E + T . * F
+        let state = goto_path(&automaton, &[n("E"), t("+"), n("T")]);
+        assert_eq!(
+            automaton.states()[state].kernel(),
+            &[Item::new(0, 3), Item::new(2, 1)]
+        );
+        assert_eq!(automaton.accessing_symbol(state), Some(n("T")));
+        assert_eq!(
+            automaton.transitions(state).collect::<Vec<_>>(),
+            vec![(t("*"), goto_path(&automaton, &[n("T"), t("*")]))]
+        );
+
+        // ( and id lead to the same states everywhere
+        let open = automaton.goto(0, &t("(")).unwrap();
+        assert_eq!(automaton.goto(open, &t("(")), Some(open));
+        assert_eq!(
+            goto_path(&automaton, &[n("E"), t("+"), t("id")]),
+            automaton.goto(0, &t("id")).unwrap()
+        );
+        assert_eq!(automaton.goto(0, &t(")")), None);
+        assert_eq!(automaton.goto(0, &t("unknown")), None);
+    }
+
+    #[test]
+    fn accessing_symbols() {
+        let automaton = Lr0Automaton::new(&expression_grammar());
+        assert_eq!(automaton.accessing_symbol(0), None);
+        for state in 0..automaton.states().len() {
+            for (symbol, target) in automaton.transitions(state) {
+                assert_eq!(automaton.accessing_symbol(target), Some(symbol.clone()));
+                for item in automaton.states()[target].kernel() {
+                    let previous = Item::new(item.rule, item.dot - 1);
+                    assert_eq!(automaton.next_symbol(previous), Some(symbol.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_rules() {
+        // S -> A 'b'; A -> ε
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![n("A"), t("b")]),
+                Rule::new("A", vec![]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let automaton = Lr0Automaton::new(&grammar);
+        assert_eq!(automaton.states().len(), 4);
+        assert_eq!(
+            automaton.closure(&[Item::new(2, 0)]),
+            vec![Item::new(2, 0), Item::new(0, 0), Item::new(1, 0)]
+        );
+        assert_eq!(automaton.next_symbol(Item::new(1, 0)), None);
+        assert_eq!(
+            automaton.display_item(Item::new(1, 0)).to_string(),
+            "\"A\" -> ."
+        );
+        assert_eq!(
+            automaton.display_item(Item::new(2, 1)).to_string(),
+            "<start> -> \"S\" ."
+        );
+    }
+
+    #[test]
+    fn display() {
+        // S -> 'x'
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![Rule::new("S", vec![t("x")])])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let automaton = Lr0Automaton::new(&grammar);
+        assert_eq!(
+            automaton.to_string(),
+            "state 0\n    <start> -> . \"S\"\n    \"S\" -> . \"x\"\n    \"x\" => state 1\n    \"S\" => state 2\n\
+             \n\
+             state 1\n    \"S\" -> \"x\" .\n\
+             \n\
+             state 2\n    <start> -> \"S\" .\n"
+        );
+    }
+}
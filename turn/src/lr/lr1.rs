@@ -0,0 +1,258 @@
+//! Automata with LR(0) states split by the LR(1) lookaheads of their kernel items.
+//!
+//! Each state is identified by its LR(0) core and the lookaheads of its kernel items
+//! restricted to a set of tokens chosen for the core. Tracking no tokens gives
+//! the LR(0) automaton, tracking all tokens gives the canonical LR(1) automaton.
+
+use super::{Item, Lr0Automaton, State};
+use crate::grammar::{GrammarAnalysis, IndexedSymbol};
+use fixedbitset::FixedBitSet;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Compute the LR(1) lookaheads of all items of a state from the lookaheads of its kernel.
+///
+/// The result is aligned with `State::items`.
+pub(crate) fn closure_lookaheads<Terminal, Nonterminal>(
+    automaton: &Lr0Automaton<Terminal, Nonterminal>,
+    analysis: &GrammarAnalysis<Terminal, Nonterminal>,
+    state: &State,
+    kernel: &[FixedBitSet],
+) -> Vec<FixedBitSet>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let size = automaton.grammar.end() + 1;
+    let mut lookaheads = kernel.to_vec();
+    lookaheads.resize(state.items.len(), FixedBitSet::with_capacity(size));
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, &item) in state.items.iter().enumerate() {
+            let nonterminal = match automaton.indexed_next_symbol(item) {
+                Some(IndexedSymbol::Nonterminal(n)) => n,
+                _ => continue,
+            };
+            let mut set = FixedBitSet::with_capacity(size);
+            let rest = &automaton.right_hand(item.rule)[item.dot + 1..];
+            if analysis.indexed_first_of_sequence(rest, &mut set) {
+                set.union_with(&lookaheads[i]);
+            }
+            for &rule in &automaton.grammar.rules_of[nonterminal] {
+                let child = state.item_index(Item::new(rule, 0)).unwrap();
+                let count = lookaheads[child].count_ones(..);
+                lookaheads[child].union_with(&set);
+                changed |= lookaheads[child].count_ones(..) != count;
+            }
+        }
+    }
+    lookaheads
+}
+
+/// How states with the same core and different kernel lookaheads are merged.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Merging {
+    /// Only states with identical tracked kernel lookaheads are merged.
+    Identical,
+    /// Weakly compatible states are merged (Pager).
+    WeaklyCompatible,
+}
+
+/// Construct the canonical LR(1) automaton, optionally with Pager's state merging.
+pub(crate) fn lr1_automaton<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    merging: Merging,
+) -> Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let mut all = FixedBitSet::with_capacity(lr0.grammar.end() + 1);
+    all.insert_range(..);
+    split_automaton(lr0, &vec![all; lr0.states.len()], merging)
+}
+
+/// Split the states of an LR(0) automaton by the kernel lookaheads of the tokens in `masks`.
+///
+/// `masks` contains the tokens tracked for each LR(0) state. A state's mask should contain
+/// the masks of all its successors, otherwise states are merged arbitrarily.
+pub(crate) fn split_automaton<Terminal, Nonterminal>(
+    lr0: &Lr0Automaton<Terminal, Nonterminal>,
+    masks: &[FixedBitSet],
+    merging: Merging,
+) -> Lr0Automaton<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    let analysis = GrammarAnalysis::from_indexed(lr0.grammar.clone());
+    let mut start = FixedBitSet::with_capacity(lr0.grammar.end() + 1);
+    start.insert(lr0.grammar.end());
+    start.intersect_with(&masks[0]);
+
+    let mut states = vec![lr0.states[0].clone()];
+    states[0].transitions.clear();
+    let mut signatures = vec![vec![start]];
+    let mut indices: HashMap<(usize, Vec<FixedBitSet>), usize> = HashMap::new();
+    indices.insert((0, signatures[0].clone()), 0);
+    let mut states_of_core = vec![vec![]; lr0.states.len()];
+    states_of_core[0].push(0);
+    let mut queue: VecDeque<usize> = std::iter::once(0).collect();
+    let mut queued = vec![true];
+
+    while let Some(current) = queue.pop_front() {
+        queued[current] = false;
+        let core = &lr0.states[states[current].core];
+        let lookaheads = closure_lookaheads(lr0, &analysis, core, &signatures[current]);
+        for (&symbol, &target_core) in &core.transitions {
+            let target_state = &lr0.states[target_core];
+            let signature: Vec<FixedBitSet> = target_state
+                .kernel
+                .iter()
+                .map(|item| {
+                    let previous = core.item_index(Item::new(item.rule, item.dot - 1));
+                    let mut set = lookaheads[previous.unwrap()].clone();
+                    set.intersect_with(&masks[target_core]);
+                    set
+                })
+                .collect();
+
+            let existing = match merging {
+                Merging::Identical => indices.get(&(target_core, signature.clone())).copied(),
+                Merging::WeaklyCompatible => {
+                    // prefer the current target to keep the transitions stable
+                    let current_target = states[current].transitions.get(&symbol).copied();
+                    current_target
+                        .into_iter()
+                        .chain(states_of_core[target_core].iter().copied())
+                        .find(|&state| weakly_compatible(&signatures[state], &signature))
+                }
+            };
+            let target = match existing {
+                Some(target) => {
+                    let merged = &mut signatures[target];
+                    if merged
+                        .iter()
+                        .zip(&signature)
+                        .any(|(merged, set)| !set.is_subset(merged))
+                    {
+                        for (merged, set) in merged.iter_mut().zip(&signature) {
+                            merged.union_with(set);
+                        }
+                        if !queued[target] {
+                            queued[target] = true;
+                            queue.push_back(target);
+                        }
+                    }
+                    target
+                }
+                None => {
+                    let target = states.len();
+                    states.push(State {
+                        kernel: target_state.kernel.clone(),
+                        items: target_state.items.clone(),
+                        transitions: BTreeMap::new(),
+                        accessing_symbol: target_state.accessing_symbol,
+                        core: target_core,
+                    });
+                    if merging == Merging::Identical {
+                        indices.insert((target_core, signature.clone()), target);
+                    }
+                    signatures.push(signature);
+                    states_of_core[target_core].push(target);
+                    queue.push_back(target);
+                    queued.push(true);
+                    target
+                }
+            };
+            states[current].transitions.insert(symbol, target);
+        }
+    }
+
+    Lr0Automaton {
+        grammar: lr0.grammar.clone(),
+        states: remove_unreachable(states),
+    }
+}
+
+/// Pager's weak compatibility of the kernel lookaheads of two states with the same core.
+///
+/// Merging weakly compatible states does not introduce reduce/reduce conflicts
+/// that are not present in either state.
+fn weakly_compatible(first: &[FixedBitSet], second: &[FixedBitSet]) -> bool {
+    for i in 0..first.len() {
+        for j in i + 1..first.len() {
+            let crossed = !first[i].is_disjoint(&second[j]) || !first[j].is_disjoint(&second[i]);
+            if crossed && first[i].is_disjoint(&first[j]) && second[i].is_disjoint(&second[j]) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Remove the states that are no longer reachable after merging and renumber the rest.
+fn remove_unreachable(states: Vec<State>) -> Vec<State> {
+    let mut numbers = vec![None; states.len()];
+    let mut order = vec![0];
+    numbers[0] = Some(0);
+    let mut current = 0;
+    while current < order.len() {
+        for &target in states[order[current]].transitions.values() {
+            if numbers[target].is_none() {
+                numbers[target] = Some(order.len());
+                order.push(target);
+            }
+        }
+        current += 1;
+    }
+    if order.len() == states.len() {
+        return states;
+    }
+    let mut states: Vec<Option<State>> = states.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|state| {
+            let mut state = states[state].take().unwrap();
+            for target in state.transitions.values_mut() {
+                *target = numbers[*target].unwrap();
+            }
+            state
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sets(items: &[&[usize]]) -> Vec<FixedBitSet> {
+        items
+            .iter()
+            .map(|items| {
+                let mut set = FixedBitSet::with_capacity(4);
+                set.extend(items.iter().copied());
+                set
+            })
+            .collect()
+    }
+
+    #[test]
+    fn weak_compatibility() {
+        assert!(weakly_compatible(&sets(&[&[0]]), &sets(&[&[1]])));
+        // merging would add a reduce/reduce conflict on both 0 and 1
+        assert!(!weakly_compatible(
+            &sets(&[&[0], &[1]]),
+            &sets(&[&[1], &[0]])
+        ));
+        // the first state already has a conflict on 0
+        assert!(weakly_compatible(
+            &sets(&[&[0], &[0, 1]]),
+            &sets(&[&[1], &[0]])
+        ));
+        assert!(weakly_compatible(
+            &sets(&[&[0], &[1]]),
+            &sets(&[&[0, 2], &[1, 3]])
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+//! LR automata and parse tables.
+
+mod counterexample;
+mod expect;
+mod ielr;
+mod item;
+mod lalr;
+mod lr0;
+mod lr1;
+mod precedence;
+mod table;
+
+pub use counterexample::{Counterexample, Derivation, Example};
+pub use expect::ConflictCountMismatch;
+pub use item::Item;
+pub use lr0::{ItemDisplay, Lr0Automaton, State};
+pub use precedence::{PrecedenceWarning, Resolution, ResolvedConflict};
+pub use table::{Action, Algorithm, Conflict, ConflictKind, ParseTable, TableError};
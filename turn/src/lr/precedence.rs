@@ -0,0 +1,190 @@
+//! Yacc-style resolution of shift/reduce conflicts by precedence and associativity.
+
+use super::Action;
+use crate::grammar::{Associativity, IndexedGrammar};
+use fixedbitset::FixedBitSet;
+use std::fmt;
+
+/// How a shift/reduce conflict was resolved.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Resolution {
+    Shift,
+    Reduce,
+    /// Both actions were replaced by `Action::Error`, because the terminal is nonassociative.
+    Error,
+}
+
+/// A shift/reduce conflict resolved by precedence and associativity.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ResolvedConflict<Terminal> {
+    pub state: usize,
+    /// The lookahead terminal.
+    pub terminal: Terminal,
+    /// The index of the reduced rule.
+    pub rule: usize,
+    /// The precedence level of the rule; lower levels have higher precedence.
+    pub rule_level: usize,
+    /// The precedence level of the terminal.
+    pub terminal_level: usize,
+    pub resolution: Resolution,
+}
+
+/// A precedence declaration that did not affect the parse table.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum PrecedenceWarning<Terminal> {
+    /// The precedence of the terminal never resolved a conflict.
+    UselessPrecedence(Terminal),
+    /// The precedence of the terminal resolved conflicts,
+    /// but never between the same precedence levels.
+    UselessAssociativity(Terminal),
+}
+
+/// Resolve the shift/reduce conflicts of the actions of all states.
+///
+/// A conflict is resolved when both the lookahead terminal and the rule have a precedence:
+/// the higher precedence wins, equal precedences are resolved by the associativity
+/// of the level: `Left` reduces, `Right` shifts and `None` results in an error.
+pub(crate) fn resolve_conflicts<Terminal, Nonterminal>(
+    grammar: &IndexedGrammar<Terminal, Nonterminal>,
+    actions: &mut [Vec<Vec<Action>>],
+) -> (
+    Vec<ResolvedConflict<Terminal>>,
+    Vec<PrecedenceWarning<Terminal>>,
+)
+where
+    Terminal: Clone,
+{
+    let mut resolved = vec![];
+    let mut used_precedence = FixedBitSet::with_capacity(grammar.terminals.len());
+    let mut used_associativity = FixedBitSet::with_capacity(grammar.terminals.len());
+    for (state, row) in actions.iter_mut().enumerate() {
+        for (terminal, cell) in row.iter_mut().enumerate().take(grammar.end()) {
+            if cell.len() < 2 {
+                continue;
+            }
+            let (terminal_level, associativity) = match grammar.precedence[terminal] {
+                Some(precedence) => precedence,
+                None => continue,
+            };
+            let reductions: Vec<usize> = cell
+                .iter()
+                .filter_map(|action| match *action {
+                    Action::Reduce(rule) => Some(rule),
+                    _ => None,
+                })
+                .collect();
+            for rule in reductions {
+                if !cell.iter().any(|action| matches!(action, Action::Shift(_))) {
+                    break;
+                }
+                let rule_terminal = match grammar.rules[rule].precedence {
+                    Some(rule_terminal) => rule_terminal,
+                    None => continue,
+                };
+                let rule_level = match grammar.precedence[rule_terminal] {
+                    Some((level, _)) => level,
+                    None => continue,
+                };
+                used_precedence.insert(terminal);
+                used_precedence.insert(rule_terminal);
+                let resolution = if rule_level < terminal_level {
+                    Resolution::Reduce
+                } else if rule_level > terminal_level {
+                    Resolution::Shift
+                } else {
+                    used_associativity.insert(terminal);
+                    used_associativity.insert(rule_terminal);
+                    match associativity {
+                        Associativity::Left => Resolution::Reduce,
+                        Associativity::Right => Resolution::Shift,
+                        Associativity::None => Resolution::Error,
+                    }
+                };
+                match resolution {
+                    Resolution::Shift => cell.retain(|&action| action != Action::Reduce(rule)),
+                    Resolution::Reduce => cell.retain(|action| !matches!(action, Action::Shift(_))),
+                    Resolution::Error => {
+                        cell.retain(|&action| {
+                            action != Action::Reduce(rule) && !matches!(action, Action::Shift(_))
+                        });
+                        cell.push(Action::Error);
+                    }
+                }
+                resolved.push(ResolvedConflict {
+                    state,
+                    terminal: grammar.terminals[terminal].clone(),
+                    rule,
+                    rule_level,
+                    terminal_level,
+                    resolution,
+                });
+            }
+            // other reductions take precedence over a nonassociative error
+            if cell.len() > 1 {
+                cell.retain(|&action| action != Action::Error);
+            }
+        }
+    }
+
+    let mut declared: Vec<(usize, usize)> = grammar
+        .precedence
+        .iter()
+        .enumerate()
+        .filter_map(|(terminal, precedence)| precedence.map(|(level, _)| (level, terminal)))
+        .collect();
+    declared.sort();
+    let warnings = declared
+        .into_iter()
+        .filter_map(|(_, terminal)| {
+            let symbol = grammar.terminals[terminal].clone();
+            if !used_precedence.contains(terminal) {
+                Some(PrecedenceWarning::UselessPrecedence(symbol))
+            } else if !used_associativity.contains(terminal) {
+                Some(PrecedenceWarning::UselessAssociativity(symbol))
+            } else {
+                None
+            }
+        })
+        .collect();
+    (resolved, warnings)
+}
+
+impl<Terminal: fmt::Debug> fmt::Display for ResolvedConflict<Terminal> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "conflict in state {} between rule {} and {:?} resolved as ",
+            self.state, self.rule, self.terminal
+        )?;
+        let resolution = match self.resolution {
+            Resolution::Shift => "shift",
+            Resolution::Reduce => "reduce",
+            Resolution::Error => "an error",
+        };
+        let reason = if self.rule_level == self.terminal_level {
+            match self.resolution {
+                Resolution::Shift => "right associative",
+                Resolution::Reduce => "left associative",
+                Resolution::Error => "nonassociative",
+            }
+        } else if self.rule_level < self.terminal_level {
+            "the rule has higher precedence"
+        } else {
+            "the terminal has higher precedence"
+        };
+        write!(f, "{} ({})", resolution, reason)
+    }
+}
+
+impl<Terminal: fmt::Debug> fmt::Display for PrecedenceWarning<Terminal> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrecedenceWarning::UselessPrecedence(terminal) => {
+                write!(f, "useless precedence and associativity for {:?}", terminal)
+            }
+            PrecedenceWarning::UselessAssociativity(terminal) => {
+                write!(f, "useless associativity for {:?}", terminal)
+            }
+        }
+    }
+}
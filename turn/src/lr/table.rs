@@ -0,0 +1,965 @@
+use super::counterexample::{Counterexample, CounterexampleSearch};
+use super::expect::{check_conflict_counts, ConflictCountMismatch};
+use super::ielr::ielr_automaton;
+use super::lalr::{lalr_lookaheads, Lookaheads};
+use super::lr1::{lr1_automaton, Merging};
+use super::precedence::{resolve_conflicts, PrecedenceWarning, ResolvedConflict};
+use super::Lr0Automaton;
+use crate::grammar::{Grammar, GrammarError, IndexedSymbol, Lookahead};
+use std::fmt;
+
+/// The algorithm used to compute the lookaheads of a parse table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    /// LALR(1) lookaheads over the LR(0) automaton (DeRemer and Pennello).
+    Lalr1,
+    /// LALR(1) with the states split that cause conflicts not present in LR(1) (Denny and Malloy).
+    ///
+    /// The table has the same conflicts as a canonical LR(1) table,
+    /// usually with the number of states of an LALR(1) table.
+    Ielr1,
+    /// Canonical LR(1) (Knuth).
+    ///
+    /// The table can be much larger than the others; it is mostly useful
+    /// to check whether a grammar with conflicts is LR(1) at all.
+    Lr1,
+    /// Canonical LR(1) with the weakly compatible states merged (Pager).
+    ///
+    /// Merging does not introduce new reduce/reduce conflicts,
+    /// the table is usually close to LALR(1) in size.
+    Pager,
+}
+
+/// A parser action for a state and a lookahead.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Action {
+    /// Shift the lookahead and go to the state.
+    Shift(usize),
+    /// Reduce by the rule with the index.
+    Reduce(usize),
+    /// Accept the input.
+    Accept,
+    /// Report a syntax error.
+    ///
+    /// Set where a conflict was resolved by a nonassociative precedence; unlike a missing action,
+    /// an explicit error is never replaced by a default reduction.
+    Error,
+}
+
+/// The kind of a conflict.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum ConflictKind {
+    /// The lookahead can be shifted (or the input accepted) and reduced.
+    ShiftReduce,
+    /// The lookahead can be reduced by multiple rules.
+    ReduceReduce,
+}
+
+/// Multiple actions of a parse table for the same state and lookahead.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict<Terminal> {
+    pub state: usize,
+    pub lookahead: Lookahead<Terminal>,
+    /// The conflicting actions, in the order of preference:
+    /// shift or accept first, then reductions by rule index.
+    pub actions: Vec<Action>,
+}
+
+impl<Terminal> Conflict<Terminal> {
+    pub fn kind(&self) -> ConflictKind {
+        if self
+            .actions
+            .iter()
+            .any(|action| matches!(action, Action::Shift(_) | Action::Accept))
+        {
+            ConflictKind::ShiftReduce
+        } else {
+            ConflictKind::ReduceReduce
+        }
+    }
+}
+
+/// An error returned by `ParseTable::new`.
+#[derive(Clone, Debug)]
+pub enum TableError<Terminal, Nonterminal> {
+    /// The grammar did not pass `Grammar::validate`.
+    InvalidGrammar(Vec<GrammarError<Terminal, Nonterminal>>),
+    /// The table has conflicts and the grammar does not declare expected conflicts.
+    ///
+    /// The table keeps all conflicting actions; `ParseTable::action` prefers shifts
+    /// to reductions and reductions by earlier rules.
+    Conflicts {
+        conflicts: Vec<Conflict<Terminal>>,
+        table: Box<ParseTable<Terminal, Nonterminal>>,
+    },
+    /// The numbers of conflicts differ from the expected conflicts of the grammar or its rules.
+    UnexpectedConflicts {
+        mismatches: Vec<ConflictCountMismatch>,
+        conflicts: Vec<Conflict<Terminal>>,
+        table: Box<ParseTable<Terminal, Nonterminal>>,
+    },
+}
+
+/// ACTION and GOTO tables of an LR parser.
+///
+/// States are the states of the underlying automaton, state 0 is the starting state.
+/// Tables constructed by different algorithms can be compared state by state
+/// through the LR(0) cores of their states (`State::core`).
+/// Rules are referenced by their indices in `Grammar::rules`.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Lookahead, Rule, Symbol};
+/// use turn::lr::{Action, Algorithm, ParseTable};
+///
+/// // S -> '(' S ')' | 'x'
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("S")
+///     .rules(vec![
+///         Rule::new(
+///             "S",
+///             vec![Symbol::Terminal('('), Symbol::Nonterminal("S"), Symbol::Terminal(')')],
+///         ),
+///         Rule::new("S", vec![Symbol::Terminal('x')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+///
+/// let x = match table.action(0, &Lookahead::Terminal('x')) {
+///     Some(Action::Shift(state)) => state,
+///     action => panic!("unexpected action {:?}", action),
+/// };
+/// assert_eq!(table.action(x, &Lookahead::End), Some(Action::Reduce(1)));
+/// assert_eq!(table.action(x, &Lookahead::Terminal('(')), None);
+///
+/// let s = table.goto(0, &"S").unwrap();
+/// assert_eq!(table.action(s, &Lookahead::End), Some(Action::Accept));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParseTable<Terminal, Nonterminal> {
+    automaton: Lr0Automaton<Terminal, Nonterminal>,
+    /// The actions of each state, indexed by lookahead.
+    actions: Vec<Vec<Vec<Action>>>,
+    /// The gotos of each state, indexed by nonterminal.
+    gotos: Vec<Vec<Option<usize>>>,
+    resolved_conflicts: Vec<ResolvedConflict<Terminal>>,
+    precedence_warnings: Vec<PrecedenceWarning<Terminal>>,
+    /// The conflicts left in the table, all of them expected.
+    conflicts: Vec<Conflict<Terminal>>,
+}
+
+impl<Terminal, Nonterminal> ParseTable<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    /// Validate the grammar and construct its parse table.
+    ///
+    /// Shift/reduce conflicts are resolved by the precedences of the grammar if possible,
+    /// see `ParseTable::resolved_conflicts`. The remaining conflicts are returned together
+    /// with the table containing all conflicting actions, unless the grammar declares
+    /// expected conflicts and their numbers match (`Grammar::expected_conflicts`,
+    /// `Rule::expected_conflicts`).
+    pub fn new(
+        grammar: &Grammar<Terminal, Nonterminal>,
+        algorithm: Algorithm,
+    ) -> Result<Self, TableError<Terminal, Nonterminal>> {
+        let (mut table, conflicts) = ParseTable::build(grammar, algorithm)?;
+        match check_conflict_counts(grammar, &table.automaton, &conflicts) {
+            None if conflicts.is_empty() => Ok(table),
+            None => Err(TableError::Conflicts {
+                conflicts,
+                table: Box::new(table),
+            }),
+            Some(mismatches) if mismatches.is_empty() => {
+                table.conflicts = conflicts;
+                Ok(table)
+            }
+            Some(mismatches) => Err(TableError::UnexpectedConflicts {
+                mismatches,
+                conflicts,
+                table: Box::new(table),
+            }),
+        }
+    }
+
+    /// Validate the grammar and construct its parse table, keeping all conflicts.
+    ///
+    /// Conflicts that precedences do not resolve are not errors, whether the grammar
+    /// expects them or not; they are listed by `ParseTable::conflicts`. Generalized parsers
+    /// (`GlrParser`) follow all conflicting actions of such a table.
+    pub fn with_conflicts(
+        grammar: &Grammar<Terminal, Nonterminal>,
+        algorithm: Algorithm,
+    ) -> Result<Self, TableError<Terminal, Nonterminal>> {
+        let (mut table, conflicts) = ParseTable::build(grammar, algorithm)?;
+        table.conflicts = conflicts;
+        Ok(table)
+    }
+
+    /// Construct the automaton and the table of a valid grammar.
+    #[allow(clippy::type_complexity)]
+    fn build(
+        grammar: &Grammar<Terminal, Nonterminal>,
+        algorithm: Algorithm,
+    ) -> Result<(Self, Vec<Conflict<Terminal>>), TableError<Terminal, Nonterminal>> {
+        grammar.validate().map_err(TableError::InvalidGrammar)?;
+        let automaton = Lr0Automaton::new(grammar);
+        let automaton = match algorithm {
+            Algorithm::Lalr1 => automaton,
+            Algorithm::Ielr1 => ielr_automaton(automaton),
+            Algorithm::Lr1 => lr1_automaton(&automaton, Merging::Identical),
+            Algorithm::Pager => lr1_automaton(&automaton, Merging::WeaklyCompatible),
+        };
+        let lookaheads = lalr_lookaheads(&automaton);
+        Ok(ParseTable::from_automaton(automaton, &lookaheads))
+    }
+
+    /// Fill the tables from the transitions and the lookaheads of the reductions of an automaton.
+    pub(crate) fn from_automaton(
+        automaton: Lr0Automaton<Terminal, Nonterminal>,
+        lookaheads: &Lookaheads,
+    ) -> (Self, Vec<Conflict<Terminal>>) {
+        let grammar = &automaton.grammar;
+        let augmented = automaton.augmented_rule();
+        let mut actions = vec![vec![vec![]; grammar.end() + 1]; automaton.states.len()];
+        let mut gotos = vec![vec![None; grammar.nonterminals.len()]; automaton.states.len()];
+        for (state, data) in automaton.states.iter().enumerate() {
+            for (&symbol, &target) in &data.transitions {
+                match symbol {
+                    IndexedSymbol::Terminal(t) => actions[state][t].push(Action::Shift(target)),
+                    IndexedSymbol::Nonterminal(n) => gotos[state][n] = Some(target),
+                }
+            }
+            if data
+                .kernel
+                .iter()
+                .any(|item| item.rule == augmented && item.dot == 1)
+            {
+                actions[state][grammar.end()].push(Action::Accept);
+            }
+            for (&rule, set) in &lookaheads[state] {
+                for t in set.ones() {
+                    actions[state][t].push(Action::Reduce(rule));
+                }
+            }
+        }
+
+        let (resolved_conflicts, precedence_warnings) = resolve_conflicts(grammar, &mut actions);
+        let mut conflicts = vec![];
+        for (state, row) in actions.iter_mut().enumerate() {
+            for (t, cell) in row.iter_mut().enumerate() {
+                cell.sort_by_key(action_preference);
+                if cell.len() > 1 {
+                    conflicts.push(Conflict {
+                        state,
+                        lookahead: grammar.lookahead(t),
+                        actions: cell.clone(),
+                    });
+                }
+            }
+        }
+        (
+            ParseTable {
+                automaton,
+                actions,
+                gotos,
+                resolved_conflicts,
+                precedence_warnings,
+                conflicts: vec![],
+            },
+            conflicts,
+        )
+    }
+
+    /// The preferred action for a state and a lookahead, `None` for a syntax error.
+    pub fn action(&self, state: usize, lookahead: &Lookahead<Terminal>) -> Option<Action> {
+        self.actions(state, lookahead).first().copied()
+    }
+
+    /// All actions for a state and a lookahead, in the order of preference.
+    ///
+    /// There is more than one action only if the table has conflicts.
+    pub fn actions(&self, state: usize, lookahead: &Lookahead<Terminal>) -> &[Action] {
+        match self.automaton.grammar.lookahead_index(lookahead) {
+            Some(t) => &self.actions[state][t],
+            None => &[],
+        }
+    }
+
+    /// The state to go to after reducing to a nonterminal.
+    pub fn goto(&self, state: usize, nonterminal: &Nonterminal) -> Option<usize> {
+        self.automaton
+            .grammar
+            .nonterminal(nonterminal)
+            .and_then(|n| self.gotos[state][n])
+    }
+
+    /// Explain a conflict of the table by examples of sentential forms reaching it.
+    ///
+    /// See `ParseTable::counterexamples`.
+    pub fn counterexample(
+        &self,
+        conflict: &Conflict<Terminal>,
+    ) -> Counterexample<Terminal, Nonterminal> {
+        CounterexampleSearch::new(&self.automaton).counterexample(conflict)
+    }
+
+    /// Explain the conflicts of the table by examples of sentential forms reaching them.
+    ///
+    /// For each conflicting action, the counterexample contains a shortest derivation
+    /// in which the parser reaches the conflict state with the lookahead next and takes
+    /// the action. If the derived sentential form of one action can also be derived with
+    /// another action, the two derivations are returned as a unifying example,
+    /// which proves the grammar ambiguous.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+    /// use turn::lr::{Algorithm, ParseTable, TableError};
+    ///
+    /// // E -> E '+' E | 'x'
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("E")
+    ///     .rules(vec![
+    ///         Rule::new(
+    ///             "E",
+    ///             vec![Symbol::Nonterminal("E"), Symbol::Terminal('+'), Symbol::Nonterminal("E")],
+    ///         ),
+    ///         Rule::new("E", vec![Symbol::Terminal('x')]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .build()
+    ///     .unwrap();
+    /// let (conflicts, table) = match ParseTable::new(&grammar, Algorithm::Lalr1) {
+    ///     Err(TableError::Conflicts { conflicts, table }) => (conflicts, table),
+    ///     _ => panic!("the grammar is ambiguous"),
+    /// };
+    ///
+    /// let counterexample = &table.counterexamples(&conflicts)[0];
+    /// let (shift, reduce) = counterexample.unifying.as_ref().unwrap();
+    /// assert_eq!(shift.to_string(), "\"E\" '+' \"E\" • '+' \"E\"");
+    /// assert_eq!(
+    ///     shift.derivations[0].to_string(),
+    ///     "\"E\" → [ \"E\" '+' \"E\" → [ \"E\" • '+' \"E\" ] ]"
+    /// );
+    /// assert_eq!(
+    ///     reduce.derivations[0].to_string(),
+    ///     "\"E\" → [ \"E\" → [ \"E\" '+' \"E\" • ] '+' \"E\" ]"
+    /// );
+    /// ```
+    pub fn counterexamples(
+        &self,
+        conflicts: &[Conflict<Terminal>],
+    ) -> Vec<Counterexample<Terminal, Nonterminal>> {
+        let search = CounterexampleSearch::new(&self.automaton);
+        conflicts
+            .iter()
+            .map(|conflict| search.counterexample(conflict))
+            .collect()
+    }
+}
+
+impl<Terminal, Nonterminal> ParseTable<Terminal, Nonterminal> {
+    /// The actions for a state and the index of a lookahead.
+    #[inline]
+    pub(crate) fn indexed_actions(&self, state: usize, lookahead: usize) -> &[Action] {
+        &self.actions[state][lookahead]
+    }
+
+    /// The number of right-hand symbols of a rule.
+    #[inline]
+    pub(crate) fn rule_length(&self, rule: usize) -> usize {
+        self.automaton.right_hand(rule).len()
+    }
+
+    /// The state to go to from a state after reducing by a rule.
+    #[inline]
+    pub(crate) fn rule_goto(&self, state: usize, rule: usize) -> Option<usize> {
+        self.gotos[state][self.automaton.grammar.rules[rule].left_hand]
+    }
+
+    /// The number of states.
+    #[inline]
+    pub fn state_count(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// The shift/reduce conflicts resolved by precedence and associativity,
+    /// ordered by state and terminal.
+    #[inline]
+    pub fn resolved_conflicts(&self) -> &[ResolvedConflict<Terminal>] {
+        &self.resolved_conflicts
+    }
+
+    /// The precedence declarations that did not resolve any conflict,
+    /// ordered by precedence level.
+    #[inline]
+    pub fn precedence_warnings(&self) -> &[PrecedenceWarning<Terminal>] {
+        &self.precedence_warnings
+    }
+
+    /// The conflicts left in the table, ordered by state and lookahead:
+    /// the expected conflicts, or all conflicts of a table from `ParseTable::with_conflicts`.
+    #[inline]
+    pub fn conflicts(&self) -> &[Conflict<Terminal>] {
+        &self.conflicts
+    }
+
+    /// The automaton the table was constructed from.
+    #[inline]
+    pub fn automaton(&self) -> &Lr0Automaton<Terminal, Nonterminal> {
+        &self.automaton
+    }
+}
+
+/// The order of preference of the actions of a cell: shifts, then reductions
+/// of earlier rules, then errors.
+pub(crate) fn action_preference(action: &Action) -> (usize, usize) {
+    match *action {
+        Action::Shift(_) | Action::Accept => (0, 0),
+        Action::Reduce(rule) => (1, rule),
+        Action::Error => (2, 0),
+    }
+}
+
+impl<Terminal: fmt::Debug> fmt::Display for Conflict<Terminal> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} conflict in state {} on ", self.kind(), self.state)?;
+        match &self.lookahead {
+            Lookahead::Terminal(t) => write!(f, "{:?}", t)?,
+            Lookahead::End => write!(f, "end of input")?,
+        }
+        for (i, action) in self.actions.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ": " } else { ", " }, action)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictKind::ShiftReduce => write!(f, "shift/reduce"),
+            ConflictKind::ReduceReduce => write!(f, "reduce/reduce"),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Shift(state) => write!(f, "shift to state {}", state),
+            Action::Reduce(rule) => write!(f, "reduce by rule {}", rule),
+            Action::Accept => write!(f, "accept"),
+            Action::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl<Terminal, Nonterminal> fmt::Display for TableError<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::InvalidGrammar(errors) => {
+                write!(f, "invalid grammar")?;
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+            }
+            TableError::Conflicts { conflicts, .. } => {
+                write!(f, "{} conflicts", conflicts.len())?;
+                for conflict in conflicts {
+                    write!(f, "\n{}", conflict)?;
+                }
+            }
+            TableError::UnexpectedConflicts {
+                mismatches,
+                conflicts,
+                ..
+            } => {
+                write!(f, "unexpected conflicts")?;
+                for mismatch in mismatches {
+                    write!(f, "\n{}", mismatch)?;
+                }
+                for conflict in conflicts {
+                    write!(f, "\n{}", conflict)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Terminal, Nonterminal> std::error::Error for TableError<Terminal, Nonterminal>
+where
+    Terminal: fmt::Debug,
+    Nonterminal: fmt::Debug,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Associativity, GrammarBuilder, Rule, Symbol};
+    use crate::lr::Resolution;
+
+    type TestGrammar = Grammar<&'static str, &'static str>;
+
+    fn t(terminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Terminal(terminal)
+    }
+
+    fn n(nonterminal: &'static str) -> Symbol<&'static str, &'static str> {
+        Symbol::Nonterminal(nonterminal)
+    }
+
+    fn build_grammar(rules: Vec<Rule<&'static str, &'static str>>) -> TestGrammar {
+        GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    fn conflicts(
+        result: Result<
+            ParseTable<&'static str, &'static str>,
+            TableError<&'static str, &'static str>,
+        >,
+    ) -> Vec<Conflict<&'static str>> {
+        match result {
+            Err(TableError::Conflicts { conflicts, .. }) => conflicts,
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the table has no conflicts"),
+        }
+    }
+
+    /// Parse a sequence of terminals with the preferred actions, returning the applied rules.
+    fn parse(
+        grammar: &TestGrammar,
+        table: &ParseTable<&'static str, &'static str>,
+        input: &[&'static str],
+    ) -> Option<Vec<usize>> {
+        let mut stack = vec![0];
+        let mut rules = vec![];
+        let mut input = input
+            .iter()
+            .map(|&t| Lookahead::Terminal(t))
+            .chain(std::iter::once(Lookahead::End))
+            .peekable();
+        loop {
+            let state = *stack.last().unwrap();
+            match table.action(state, input.peek().unwrap())? {
+                Action::Shift(target) => {
+                    input.next();
+                    stack.push(target);
+                }
+                Action::Reduce(rule) => {
+                    let rule_data = &grammar.rules()[rule];
+                    stack.truncate(stack.len() - rule_data.right_hand().len());
+                    let target = table.goto(*stack.last().unwrap(), rule_data.left_hand())?;
+                    stack.push(target);
+                    rules.push(rule);
+                }
+                Action::Accept => return Some(rules),
+                Action::Error => return None,
+            }
+        }
+    }
+
+    #[test]
+    fn lalr_table() {
+        // S -> L = R | R; L -> * R | id; R -> L
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("L"), t("="), n("R")]),
+            Rule::new("S", vec![n("R")]),
+            Rule::new("L", vec![t("*"), n("R")]),
+            Rule::new("L", vec![t("id")]),
+            Rule::new("R", vec![n("L")]),
+        ]);
+        let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+        assert_eq!(table.state_count(), 10);
+
+        let l = table.goto(0, &"L").unwrap();
+        assert!(matches!(
+            table.action(l, &Lookahead::Terminal("=")),
+            Some(Action::Shift(_))
+        ));
+        assert_eq!(table.action(l, &Lookahead::End), Some(Action::Reduce(4)));
+        assert_eq!(table.goto(0, &"X"), None);
+        assert_eq!(table.actions(0, &Lookahead::Terminal("unknown")), &[]);
+
+        assert_eq!(parse(&grammar, &table, &["id"]), Some(vec![3, 4, 1]));
+        assert_eq!(
+            parse(&grammar, &table, &["*", "id", "=", "id"]),
+            Some(vec![3, 4, 2, 3, 4, 0])
+        );
+        assert_eq!(parse(&grammar, &table, &["id", "="]), None);
+    }
+
+    #[test]
+    fn shift_reduce_conflicts() {
+        // S -> S + S | id
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("S"), t("+"), n("S")]),
+            Rule::new("S", vec![t("id")]),
+        ]);
+        let conflicts = conflicts(ParseTable::new(&grammar, Algorithm::Lalr1));
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.kind(), ConflictKind::ShiftReduce);
+        assert_eq!(conflict.lookahead, Lookahead::Terminal("+"));
+        assert!(matches!(
+            conflict.actions[..],
+            [Action::Shift(_), Action::Reduce(0)]
+        ));
+        assert!(conflict.to_string().starts_with(&format!(
+            "shift/reduce conflict in state {} on \"+\": shift to state",
+            conflict.state
+        )));
+
+        // the table prefers shifting
+        if let Err(TableError::Conflicts { table, .. }) =
+            ParseTable::new(&grammar, Algorithm::Lalr1)
+        {
+            assert_eq!(
+                parse(&grammar, &table, &["id", "+", "id", "+", "id"]),
+                Some(vec![1, 1, 1, 0, 0])
+            );
+        }
+    }
+
+    #[test]
+    fn reduce_reduce_conflicts() {
+        // LR(1), but not LALR(1)
+        // S -> a A d | b B d | a B e | b A e; A -> c; B -> c
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("A"), t("d")]),
+            Rule::new("S", vec![t("b"), n("B"), t("d")]),
+            Rule::new("S", vec![t("a"), n("B"), t("e")]),
+            Rule::new("S", vec![t("b"), n("A"), t("e")]),
+            Rule::new("A", vec![t("c")]),
+            Rule::new("B", vec![t("c")]),
+        ]);
+        let conflicts = conflicts(ParseTable::new(&grammar, Algorithm::Lalr1));
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].kind(), ConflictKind::ReduceReduce);
+        assert_eq!(conflicts[0].state, conflicts[1].state);
+        assert_eq!(conflicts[0].lookahead, Lookahead::Terminal("d"));
+        assert_eq!(conflicts[1].lookahead, Lookahead::Terminal("e"));
+        assert_eq!(
+            conflicts[0].actions,
+            vec![Action::Reduce(4), Action::Reduce(5)]
+        );
+    }
+
+    #[test]
+    fn ielr_resolves_mysterious_conflicts() {
+        // S -> a A d | b B d | a B e | b A e; A -> c; B -> c
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("A"), t("d")]),
+            Rule::new("S", vec![t("b"), n("B"), t("d")]),
+            Rule::new("S", vec![t("a"), n("B"), t("e")]),
+            Rule::new("S", vec![t("b"), n("A"), t("e")]),
+            Rule::new("A", vec![t("c")]),
+            Rule::new("B", vec![t("c")]),
+        ]);
+        let table = ParseTable::new(&grammar, Algorithm::Ielr1).unwrap();
+        assert_eq!(table.state_count(), 14);
+        assert_eq!(parse(&grammar, &table, &["a", "c", "d"]), Some(vec![4, 0]));
+        assert_eq!(parse(&grammar, &table, &["b", "c", "d"]), Some(vec![5, 1]));
+        assert_eq!(parse(&grammar, &table, &["a", "c", "e"]), Some(vec![5, 2]));
+        assert_eq!(parse(&grammar, &table, &["b", "c", "e"]), Some(vec![4, 3]));
+        assert_eq!(parse(&grammar, &table, &["a", "c", "c"]), None);
+    }
+
+    #[test]
+    fn ielr_nested_mysterious_conflicts() {
+        // S -> a X | b Y; X -> A c | B d; Y -> A d | B c; A -> C; B -> C; C -> e
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("X")]),
+            Rule::new("S", vec![t("b"), n("Y")]),
+            Rule::new("X", vec![n("A"), t("c")]),
+            Rule::new("X", vec![n("B"), t("d")]),
+            Rule::new("Y", vec![n("A"), t("d")]),
+            Rule::new("Y", vec![n("B"), t("c")]),
+            Rule::new("A", vec![n("C")]),
+            Rule::new("B", vec![n("C")]),
+            Rule::new("C", vec![t("e")]),
+        ]);
+        let lalr = conflicts(ParseTable::new(&grammar, Algorithm::Lalr1));
+        assert_eq!(lalr.len(), 2);
+        let table = ParseTable::new(&grammar, Algorithm::Ielr1).unwrap();
+        assert_eq!(
+            parse(&grammar, &table, &["a", "e", "c"]),
+            Some(vec![8, 6, 2, 0])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["b", "e", "c"]),
+            Some(vec![8, 7, 5, 1])
+        );
+        assert_eq!(parse(&grammar, &table, &["b", "e", "e"]), None);
+    }
+
+    #[test]
+    fn ielr_keeps_lr1_conflicts() {
+        // S -> S + S | id
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("S"), t("+"), n("S")]),
+            Rule::new("S", vec![t("id")]),
+        ]);
+        let lalr = conflicts(ParseTable::new(&grammar, Algorithm::Lalr1));
+        let ielr = conflicts(ParseTable::new(&grammar, Algorithm::Ielr1));
+        assert_eq!(lalr, ielr);
+
+        // LALR(1) grammars get the LALR(1) table
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("L"), t("="), n("R")]),
+            Rule::new("S", vec![n("R")]),
+            Rule::new("L", vec![t("*"), n("R")]),
+            Rule::new("L", vec![t("id")]),
+            Rule::new("R", vec![n("L")]),
+        ]);
+        let table = ParseTable::new(&grammar, Algorithm::Ielr1).unwrap();
+        assert_eq!(table.state_count(), 10);
+    }
+
+    #[test]
+    fn ielr_does_not_split_lr1_conflicts() {
+        // S -> b S A | c; A -> A c | b b | ε
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("b"), n("S"), n("A")]),
+            Rule::new("S", vec![t("c")]),
+            Rule::new("A", vec![n("A"), t("c")]),
+            Rule::new("A", vec![t("b"), t("b")]),
+            Rule::new("A", vec![]),
+        ]);
+        let lalr = ParseTable::with_conflicts(&grammar, Algorithm::Lalr1).unwrap();
+        let ielr = ParseTable::with_conflicts(&grammar, Algorithm::Ielr1).unwrap();
+        let lr1 = ParseTable::with_conflicts(&grammar, Algorithm::Lr1).unwrap();
+        assert_eq!(lalr.state_count(), 9);
+        assert_eq!(ielr.state_count(), 9);
+        assert_eq!(lr1.state_count(), 16);
+        assert_eq!(ielr.conflicts().len(), 2);
+        assert_eq!(lr1.conflicts().len(), 2);
+    }
+
+    #[test]
+    fn canonical_lr1() {
+        // S -> L = R | R; L -> * R | id; R -> L
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("L"), t("="), n("R")]),
+            Rule::new("S", vec![n("R")]),
+            Rule::new("L", vec![t("*"), n("R")]),
+            Rule::new("L", vec![t("id")]),
+            Rule::new("R", vec![n("L")]),
+        ]);
+        let lr1 = ParseTable::new(&grammar, Algorithm::Lr1).unwrap();
+        let pager = ParseTable::new(&grammar, Algorithm::Pager).unwrap();
+        let lalr = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+        assert_eq!(lr1.state_count(), 14);
+        assert_eq!(pager.state_count(), 10);
+        for input in &[
+            &["id"][..],
+            &["*", "id", "=", "*", "*", "id"],
+            &["id", "=", "="],
+        ] {
+            assert_eq!(parse(&grammar, &lr1, input), parse(&grammar, &lalr, input));
+            assert_eq!(
+                parse(&grammar, &pager, input),
+                parse(&grammar, &lalr, input)
+            );
+        }
+
+        // every LR(1) state has the actions of its LALR(1) core, restricted to its lookaheads
+        let automaton = lr1.automaton();
+        let terminals = ["=", "*", "id"];
+        let lookaheads = terminals
+            .iter()
+            .map(|&t| Lookahead::Terminal(t))
+            .chain(std::iter::once(Lookahead::End));
+        for lookahead in lookaheads {
+            for state in 0..lr1.state_count() {
+                let core = automaton.states()[state].core();
+                match (lr1.action(state, &lookahead), lalr.action(core, &lookahead)) {
+                    (Some(Action::Shift(a)), Some(Action::Shift(b))) => {
+                        assert_eq!(automaton.states()[a].core(), b)
+                    }
+                    (Some(action), core_action) => assert_eq!(Some(action), core_action),
+                    (None, _) => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_lr1_conflicts() {
+        // S -> a A d | b B d | a B e | b A e; A -> c; B -> c
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![t("a"), n("A"), t("d")]),
+            Rule::new("S", vec![t("b"), n("B"), t("d")]),
+            Rule::new("S", vec![t("a"), n("B"), t("e")]),
+            Rule::new("S", vec![t("b"), n("A"), t("e")]),
+            Rule::new("A", vec![t("c")]),
+            Rule::new("B", vec![t("c")]),
+        ]);
+        let lr1 = ParseTable::new(&grammar, Algorithm::Lr1).unwrap();
+        let pager = ParseTable::new(&grammar, Algorithm::Pager).unwrap();
+        assert_eq!(lr1.state_count(), 14);
+        assert_eq!(pager.state_count(), 14);
+
+        // ambiguous grammars are not LR(1)
+        let grammar = build_grammar(vec![
+            Rule::new("S", vec![n("S"), t("+"), n("S")]),
+            Rule::new("S", vec![t("id")]),
+        ]);
+        let lr1 = conflicts(ParseTable::new(&grammar, Algorithm::Lr1));
+        assert!(lr1
+            .iter()
+            .all(|conflict| conflict.kind() == ConflictKind::ShiftReduce
+                && conflict.lookahead == Lookahead::Terminal("+")));
+        assert_eq!(
+            conflicts(ParseTable::new(&grammar, Algorithm::Pager)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn precedence_resolution() {
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![n("E"), t("+"), n("E")]),
+                Rule::new("E", vec![n("E"), t("-"), n("E")]),
+                Rule::new("E", vec![n("E"), t("*"), n("E")]),
+                Rule::new("E", vec![n("E"), t("^"), n("E")]),
+                Rule::new("E", vec![n("E"), t("<"), n("E")]),
+                Rule::with_precedence("E", vec![t("-"), n("E")], "UMINUS"),
+                Rule::new("E", vec![t("id")]),
+            ])
+            .symbol_precedence(vec![
+                (Associativity::Right, vec!["^"]),
+                (Associativity::Right, vec!["UMINUS"]),
+                (Associativity::Left, vec!["*"]),
+                (Associativity::Left, vec!["+", "-"]),
+                (Associativity::None, vec!["<"]),
+                (Associativity::Left, vec!["?"]),
+            ])
+            .build()
+            .unwrap();
+        let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+
+        assert_eq!(
+            parse(&grammar, &table, &["id", "+", "id", "*", "id"]),
+            Some(vec![6, 6, 6, 2, 0])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["id", "-", "id", "-", "id"]),
+            Some(vec![6, 6, 1, 6, 1])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["id", "^", "id", "^", "id"]),
+            Some(vec![6, 6, 6, 3, 3])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["-", "id", "^", "id"]),
+            Some(vec![6, 6, 3, 5])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["-", "id", "+", "id"]),
+            Some(vec![6, 5, 6, 0])
+        );
+        assert_eq!(
+            parse(&grammar, &table, &["id", "<", "id"]),
+            Some(vec![6, 6, 4])
+        );
+        assert_eq!(parse(&grammar, &table, &["id", "<", "id", "<", "id"]), None);
+
+        let resolved = table.resolved_conflicts();
+        assert_eq!(resolved.len(), 5 * 5 + 5);
+        let nonassociative = resolved
+            .iter()
+            .find(|conflict| conflict.rule == 4 && conflict.terminal == "<")
+            .unwrap();
+        assert_eq!(nonassociative.resolution, Resolution::Error);
+        assert_eq!(
+            table.actions(nonassociative.state, &Lookahead::Terminal("<")),
+            &[Action::Error]
+        );
+        let sum = resolved
+            .iter()
+            .find(|conflict| conflict.rule == 0 && conflict.terminal == "*")
+            .unwrap();
+        assert_eq!(
+            sum.to_string(),
+            format!(
+                "conflict in state {} between rule 0 and \"*\" resolved as shift \
+                 (the terminal has higher precedence)",
+                sum.state
+            )
+        );
+
+        assert_eq!(
+            table.precedence_warnings(),
+            &[
+                PrecedenceWarning::UselessAssociativity("UMINUS"),
+                PrecedenceWarning::UselessPrecedence("?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_precedence() {
+        // S -> S + S | S * S | id, only + has a precedence
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![n("S"), t("+"), n("S")]),
+                Rule::new("S", vec![n("S"), t("*"), n("S")]),
+                Rule::new("S", vec![t("id")]),
+            ])
+            .symbol_precedence(vec![(Associativity::Left, vec!["+"])])
+            .build()
+            .unwrap();
+        match ParseTable::new(&grammar, Algorithm::Lalr1) {
+            Err(TableError::Conflicts { conflicts, table }) => {
+                assert_eq!(conflicts.len(), 3);
+                assert!(conflicts
+                    .iter()
+                    .all(|conflict| conflict.kind() == ConflictKind::ShiftReduce));
+                assert_eq!(table.resolved_conflicts().len(), 1);
+                assert_eq!(table.resolved_conflicts()[0].resolution, Resolution::Reduce);
+                assert!(table.precedence_warnings().is_empty());
+            }
+            result => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn invalid_grammar() {
+        let grammar = build_grammar(vec![Rule::new("S", vec![n("A")])]);
+        match ParseTable::new(&grammar, Algorithm::Lalr1) {
+            Err(TableError::InvalidGrammar(errors)) => assert_eq!(
+                errors,
+                vec![
+                    GrammarError::UndefinedNonterminal {
+                        nonterminal: "A",
+                        rules: vec![0]
+                    },
+                    GrammarError::UnproductiveNonterminal {
+                        nonterminal: "S",
+                        rules: vec![0]
+                    }
+                ]
+            ),
+            result => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+}
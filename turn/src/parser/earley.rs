@@ -0,0 +1,703 @@
+//! Earley parsing of arbitrary context-free grammars.
+
+use super::forest::{Forest, ForestBuilder, PackedNode};
+use super::{Input, ParseError, SyntaxError};
+use crate::grammar::{Grammar, IndexedGrammar, IndexedSymbol};
+use crate::lexer::{LexError, Location, Token};
+use fixedbitset::FixedBitSet;
+use std::collections::{HashMap, HashSet};
+
+/// An Earley item: a rule with a dot before the symbol at `dot`,
+/// predicted at the position `origin`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+struct Item {
+    rule: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// The items of a position of the input.
+#[derive(Clone, Default, Debug)]
+struct EarleySet {
+    items: Vec<Item>,
+    contains: HashSet<Item>,
+    /// The items with the dot before each nonterminal.
+    waiting: HashMap<usize, Vec<Item>>,
+    /// The nonterminals completed through a Leo item, with their origins.
+    leo_completions: Vec<(usize, usize)>,
+}
+
+/// The Earley sets of an input, one more than the tokens.
+struct Chart<'a, Terminal> {
+    sets: Vec<EarleySet>,
+    tokens: Vec<Token<'a, Terminal>>,
+    /// The locations of the tokens, followed by the location of the end of input.
+    locations: Vec<Location>,
+    /// The topmost item of the deterministic reduction path of each position and
+    /// nonterminal, as the rule and origin of the completed item.
+    leo: HashMap<(usize, usize), Option<(usize, usize)>>,
+}
+
+/// The completed items of a set by their nonterminals and origins, including the
+/// items skipped by Leo items, whose deterministic reduction paths are followed
+/// only as far down as the origins asked for.
+#[derive(Default)]
+struct Completions {
+    /// The rules of the completed items of each nonterminal and origin.
+    rules: HashMap<(usize, usize), Vec<usize>>,
+    /// The next position and nonterminal of each path still to be followed.
+    paths: Vec<(usize, usize)>,
+    /// The positions and nonterminals reached by the paths, where joining paths end.
+    visited: HashSet<(usize, usize)>,
+}
+
+/// A parser for any context-free grammar, using Earley's algorithm.
+///
+/// Unlike the LR parsers, the Earley parser needs no table, so it can run grammars
+/// that are not yet free of conflicts: ambiguous, left or right recursive, with epsilon
+/// rules or even cyclic. Like the `GlrParser`, it returns a shared packed parse forest
+/// of all parses of the input (see `Forest`). Right recursion is parsed in linear time
+/// thanks to Leo's optimization, which completes deterministic reduction paths at once;
+/// epsilon rules are handled as proposed by Aycock and Horspool.
+///
+/// Earley parsers have the correct prefix property: a syntax error is reported at the
+/// first token that cannot continue any parse, the furthest position reached in the
+/// input, with all terminals that could continue a parse there.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+/// use turn::parser::{EarleyParser, ParseError};
+/// use turn::{Location, Token};
+///
+/// // S -> S S | '(' S ')' | , ambiguous and not LR(k) for any k
+/// let s = || Symbol::Nonterminal("S");
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("S")
+///     .rules(vec![
+///         Rule::new("S", vec![s(), s()]),
+///         Rule::new("S", vec![Symbol::Terminal('('), s(), Symbol::Terminal(')')]),
+///         Rule::new("S", vec![]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let parser = EarleyParser::new(&grammar);
+///
+/// let tokens = |source: &'static str| {
+///     source.char_indices().map(|(i, symbol)| {
+///         let mut location = Location::new();
+///         location.span = (i, i + 1);
+///         Ok(Token { symbol, attribute: "", location })
+///     })
+/// };
+/// let forest = parser.parse(tokens("()()")).unwrap();
+/// assert!(forest.is_ambiguous());
+/// // empty strings can be added anywhere
+/// assert_eq!(forest.count(), None);
+///
+/// match parser.parse(tokens("(()))(")) {
+///     Err(ParseError::Syntax(error)) => assert_eq!(error.location.span, (4, 5)),
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EarleyParser<Terminal, Nonterminal> {
+    grammar: IndexedGrammar<Terminal, Nonterminal>,
+    nullable: FixedBitSet,
+}
+
+impl<Terminal, Nonterminal> EarleyParser<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    pub fn new(grammar: &Grammar<Terminal, Nonterminal>) -> Self {
+        let grammar = IndexedGrammar::new(grammar);
+        let nullable = grammar.nullable();
+        EarleyParser { grammar, nullable }
+    }
+
+    /// Check whether the tokens of a lexer are a sentence of the grammar,
+    /// without building a forest.
+    pub fn recognize<'a, Lexer>(&self, lexer: Lexer) -> Result<(), ParseError<'a, Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Terminal>, LexError<Terminal>>>,
+    {
+        self.chart(lexer).map(|_| ())
+    }
+
+    /// Parse the tokens of a lexer and return the forest of all parses.
+    ///
+    /// The first lexical error stops the parser, as does a token that no parse
+    /// can continue with.
+    pub fn parse<'a, Lexer>(
+        &self,
+        lexer: Lexer,
+    ) -> Result<Forest<'a, Terminal, Nonterminal>, ParseError<'a, Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Terminal>, LexError<Terminal>>>,
+    {
+        let chart = self.chart(lexer)?;
+        Ok(self.forest(chart))
+    }
+
+    /// Fill the Earley sets of the input, up to the end of input or the first error.
+    fn chart<'a, Lexer>(
+        &self,
+        lexer: Lexer,
+    ) -> Result<Chart<'a, Terminal>, ParseError<'a, Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Terminal>, LexError<Terminal>>>,
+    {
+        let grammar = &self.grammar;
+        let mut input = Input::new(lexer.into_iter());
+        let mut chart = Chart {
+            sets: vec![EarleySet::default()],
+            tokens: vec![],
+            locations: vec![],
+            leo: HashMap::new(),
+        };
+        for &rule in &grammar.rules_of[grammar.start] {
+            self.add(
+                &mut chart.sets[0],
+                Item {
+                    rule,
+                    dot: 0,
+                    origin: 0,
+                },
+            );
+        }
+        for position in 0.. {
+            let token = input.next_token()?;
+            let location = input.location(token.as_ref());
+            chart.locations.push(location);
+            let lookahead = token
+                .as_ref()
+                .and_then(|token| grammar.terminal(&token.symbol));
+            chart.sets.push(EarleySet::default());
+            self.complete_set(&mut chart, position, lookahead);
+
+            let token = match token {
+                Some(token) if !chart.sets[position + 1].items.is_empty() => token,
+                token => {
+                    // Leo items never skip completions from the origin 0,
+                    // since the items waiting there start at 0 too
+                    let accepted = chart.sets[position].items.iter().any(|item| {
+                        item.origin == 0
+                            && grammar.rules[item.rule].left_hand == grammar.start
+                            && item.dot == grammar.rules[item.rule].right_hand.len()
+                    });
+                    if token.is_none() && accepted {
+                        chart.sets.pop();
+                        return Ok(chart);
+                    }
+                    let mut expected: Vec<usize> = chart.sets[position]
+                        .items
+                        .iter()
+                        .filter_map(|item| match self.next_symbol(*item) {
+                            Some(IndexedSymbol::Terminal(t)) => Some(t),
+                            _ => None,
+                        })
+                        .chain(if accepted { Some(grammar.end()) } else { None })
+                        .filter(|&t| Some(t) != grammar.error)
+                        .collect();
+                    expected.sort_unstable();
+                    expected.dedup();
+                    let error = SyntaxError::from_grammar(grammar, token, location, expected);
+                    return Err(ParseError::Syntax(Box::new(error)));
+                }
+            };
+            chart.tokens.push(token);
+        }
+        unreachable!()
+    }
+
+    /// Predict and complete the items of a set, and scan the lookahead into the next set.
+    fn complete_set(
+        &self,
+        chart: &mut Chart<'_, Terminal>,
+        position: usize,
+        lookahead: Option<usize>,
+    ) {
+        let grammar = &self.grammar;
+        let mut next = 0;
+        while let Some(&item) = chart.sets[position].items.get(next) {
+            next += 1;
+            match self.next_symbol(item) {
+                Some(IndexedSymbol::Terminal(t)) => {
+                    if lookahead == Some(t) {
+                        self.add(&mut chart.sets[position + 1], advance(item));
+                    }
+                }
+                Some(IndexedSymbol::Nonterminal(n)) => {
+                    let set = &mut chart.sets[position];
+                    for &rule in &grammar.rules_of[n] {
+                        self.add(
+                            set,
+                            Item {
+                                rule,
+                                dot: 0,
+                                origin: position,
+                            },
+                        );
+                    }
+                    if self.nullable.contains(n) {
+                        self.add(set, advance(item));
+                    }
+                }
+                // empty derivations are already advanced over when predicted
+                None if item.origin == position => {}
+                None => {
+                    let left_hand = grammar.rules[item.rule].left_hand;
+                    match self.leo(chart, item.origin, left_hand) {
+                        Some((rule, origin)) => {
+                            let set = &mut chart.sets[position];
+                            let dot = grammar.rules[rule].right_hand.len();
+                            self.add(set, Item { rule, dot, origin });
+                            set.leo_completions.push((left_hand, item.origin));
+                        }
+                        None => {
+                            let waiting = chart.sets[item.origin]
+                                .waiting
+                                .get(&left_hand)
+                                .cloned()
+                                .unwrap_or_default();
+                            for waiting in waiting {
+                                self.add(&mut chart.sets[position], advance(waiting));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn add(&self, set: &mut EarleySet, item: Item) {
+        if set.contains.insert(item) {
+            if let Some(IndexedSymbol::Nonterminal(n)) = self.next_symbol(item) {
+                set.waiting.entry(n).or_default().push(item);
+            }
+            set.items.push(item);
+        }
+    }
+
+    fn next_symbol(&self, item: Item) -> Option<IndexedSymbol> {
+        self.grammar.rules[item.rule]
+            .right_hand
+            .get(item.dot)
+            .copied()
+    }
+
+    /// The single item of a set waiting for a nonterminal, if it is the last symbol
+    /// of the item and the item starts before the set, as the rule and origin of the
+    /// item completed by the nonterminal.
+    fn leo_step(
+        &self,
+        chart: &Chart<'_, Terminal>,
+        position: usize,
+        nonterminal: usize,
+    ) -> Option<(usize, usize)> {
+        match chart.sets[position]
+            .waiting
+            .get(&nonterminal)
+            .map(Vec::as_slice)
+        {
+            Some(&[item])
+                if item.origin < position
+                    && item.dot + 1 == self.grammar.rules[item.rule].right_hand.len() =>
+            {
+                Some((item.rule, item.origin))
+            }
+            _ => None,
+        }
+    }
+
+    /// The Leo item of a nonterminal completed from a position: the topmost completed
+    /// item of the deterministic reduction path, `None` if the path is empty.
+    fn leo(
+        &self,
+        chart: &mut Chart<'_, Terminal>,
+        position: usize,
+        nonterminal: usize,
+    ) -> Option<(usize, usize)> {
+        // follow the path down to a known Leo item, then memoize the path
+        let mut path = vec![];
+        let (mut position, mut nonterminal) = (position, nonterminal);
+        let mut top = loop {
+            if let Some(&top) = chart.leo.get(&(position, nonterminal)) {
+                break top;
+            }
+            match self.leo_step(chart, position, nonterminal) {
+                Some((rule, origin)) => {
+                    path.push((position, nonterminal, (rule, origin)));
+                    position = origin;
+                    nonterminal = self.grammar.rules[rule].left_hand;
+                }
+                None => {
+                    chart.leo.insert((position, nonterminal), None);
+                    break None;
+                }
+            }
+        };
+        for (position, nonterminal, step) in path.into_iter().rev() {
+            top = Some(top.unwrap_or(step));
+            chart.leo.insert((position, nonterminal), top);
+        }
+        top
+    }
+
+    /// The completed items of a set, with the Leo paths of the set not yet followed.
+    fn completions(&self, chart: &Chart<'_, Terminal>, position: usize) -> Completions {
+        let set = &chart.sets[position];
+        let mut completions = Completions::default();
+        for item in &set.items {
+            if self.next_symbol(*item).is_none() {
+                let left_hand = self.grammar.rules[item.rule].left_hand;
+                let rules = completions
+                    .rules
+                    .entry((left_hand, item.origin))
+                    .or_default();
+                rules.push(item.rule);
+            }
+        }
+        for &(nonterminal, origin) in &set.leo_completions {
+            if completions.visited.insert((origin, nonterminal)) {
+                completions.paths.push((origin, nonterminal));
+            }
+        }
+        completions
+    }
+
+    /// The rules of the items of a set completing a nonterminal from an origin,
+    /// following the Leo paths of the set down to the origin.
+    fn completed_rules<'c>(
+        &self,
+        chart: &Chart<'_, Terminal>,
+        completions: &'c mut Completions,
+        nonterminal: usize,
+        origin: usize,
+    ) -> &'c [usize] {
+        let mut path = 0;
+        while let Some(&(mut position, mut left_hand)) = completions.paths.get(path) {
+            // the positions of a path decrease
+            let mut open = true;
+            while open && position > origin {
+                match self.leo_step(chart, position, left_hand) {
+                    Some((rule, rule_origin)) => {
+                        left_hand = self.grammar.rules[rule].left_hand;
+                        position = rule_origin;
+                        let rules = completions.rules.entry((left_hand, position)).or_default();
+                        if !rules.contains(&rule) {
+                            rules.push(rule);
+                        }
+                        open = completions.visited.insert((position, left_hand));
+                    }
+                    None => open = false,
+                }
+            }
+            if open {
+                completions.paths[path] = (position, left_hand);
+                path += 1;
+            } else {
+                completions.paths.swap_remove(path);
+            }
+        }
+        completions
+            .rules
+            .get(&(nonterminal, origin))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Build the forest of the completed chart, from the root down.
+    fn forest<'a>(&self, chart: Chart<'a, Terminal>) -> Forest<'a, Terminal, Nonterminal> {
+        let grammar = &self.grammar;
+        let mut forest = ForestBuilder::new();
+        let mut tokens = vec![None; chart.tokens.len()];
+        let mut completions = HashMap::new();
+        // the positions of the sets holding each item
+        let mut positions: HashMap<Item, Vec<usize>> = HashMap::new();
+        for (position, set) in chart.sets.iter().enumerate() {
+            for &item in &set.items {
+                positions.entry(item).or_default().push(position);
+            }
+        }
+        let end = chart.tokens.len();
+        let start_nonterminal = || grammar.nonterminals[grammar.start].clone();
+        let root = forest.nonterminal(grammar.start, start_nonterminal, 0, end);
+        let mut queue = vec![(root, grammar.start, 0, end)];
+        while let Some((node, nonterminal, start, end)) = queue.pop() {
+            let completed = completions
+                .entry(end)
+                .or_insert_with(|| self.completions(&chart, end));
+            let mut rules = self
+                .completed_rules(&chart, completed, nonterminal, start)
+                .to_vec();
+            rules.sort_unstable();
+            for rule in rules {
+                let right_hand = &grammar.rules[rule].right_hand;
+                // the children from the last symbol back: (symbols left, position, children)
+                let mut partial = vec![(right_hand.len(), end, vec![])];
+                while let Some((symbols, position, children)) = partial.pop() {
+                    if symbols == 0 {
+                        let mut children: Vec<usize> = children;
+                        children.reverse();
+                        forest.add_alternative(node, PackedNode { rule, children });
+                        continue;
+                    }
+                    // the item before the symbol has to be in the set of its start
+                    let before = Item {
+                        rule,
+                        dot: symbols - 1,
+                        origin: start,
+                    };
+                    match right_hand[symbols - 1] {
+                        IndexedSymbol::Terminal(t) => {
+                            let index = match position.checked_sub(1) {
+                                Some(index) if chart.sets[index].contains.contains(&before) => {
+                                    index
+                                }
+                                _ => continue,
+                            };
+                            let token = &chart.tokens[index];
+                            if grammar.terminal(&token.symbol) != Some(t) {
+                                continue;
+                            }
+                            let child =
+                                *tokens[index].get_or_insert_with(|| forest.token(token.clone()));
+                            let mut children = children;
+                            children.push(child);
+                            partial.push((symbols - 1, index, children));
+                        }
+                        IndexedSymbol::Nonterminal(n) => {
+                            let completed = completions
+                                .entry(position)
+                                .or_insert_with(|| self.completions(&chart, position));
+                            let origins: Vec<usize> = positions
+                                .get(&before)
+                                .into_iter()
+                                .flatten()
+                                .copied()
+                                .take_while(|&origin| origin <= position)
+                                .filter(|&origin| {
+                                    !self
+                                        .completed_rules(&chart, completed, n, origin)
+                                        .is_empty()
+                                })
+                                .collect();
+                            for origin in origins {
+                                let nodes = forest.nodes.len();
+                                let nonterminal = || grammar.nonterminals[n].clone();
+                                let child = forest.nonterminal(n, nonterminal, origin, position);
+                                if forest.nodes.len() > nodes {
+                                    queue.push((child, n, origin, position));
+                                }
+                                let mut children = children.clone();
+                                children.push(child);
+                                partial.push((symbols - 1, origin, children));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Forest::new(forest.nodes, root, chart.locations)
+    }
+}
+
+fn advance(item: Item) -> Item {
+    Item {
+        dot: item.dot + 1,
+        ..item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule, Symbol};
+    use crate::lr::{Algorithm, ParseTable};
+    use crate::parser::{bracketed, tokens, GlrParser, Tree};
+
+    fn grammar(
+        start: &'static str,
+        rules: Vec<Rule<char, &'static str>>,
+    ) -> Grammar<char, &'static str> {
+        GrammarBuilder::default()
+            .starting_nonterminal(start)
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn ambiguous_expressions() {
+        // E -> E '+' E | E '*' E | 'n'
+        let e = || Symbol::Nonterminal("E");
+        let t = Symbol::Terminal;
+        let grammar = grammar(
+            "E",
+            vec![
+                Rule::new("E", vec![e(), t('+'), e()]),
+                Rule::new("E", vec![e(), t('*'), e()]),
+                Rule::new("E", vec![t('n')]),
+            ],
+        );
+        let parser = EarleyParser::new(&grammar);
+        let table = ParseTable::with_conflicts(&grammar, Algorithm::Lalr1).unwrap();
+        // the Catalan numbers, as with a GLR parser
+        for (input, count) in &[
+            ("n", 1),
+            ("n+n", 1),
+            ("n+n*n", 2),
+            ("n+n*n+n", 5),
+            ("n+n+n+n+n", 14),
+        ] {
+            let forest = parser.parse(tokens(input)).unwrap();
+            assert_eq!(forest.count(), Some(*count));
+            let glr = GlrParser::new(&table).parse(tokens(input)).unwrap();
+            let mut trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+            let mut glr_trees: Vec<String> = glr.trees().map(|tree| bracketed(&tree)).collect();
+            trees.sort();
+            glr_trees.sort();
+            assert_eq!(trees, glr_trees);
+        }
+        assert_eq!(parser.recognize(tokens("n*n+n")), Ok(()));
+    }
+
+    #[test]
+    fn epsilon_rules() {
+        // S -> A A 'x'; A -> 'a' | ; hidden left recursion: L -> B L 'y' | 'y'; B ->
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let grammar = grammar(
+            "S",
+            vec![
+                Rule::new("S", vec![n("A"), n("A"), t('x')]),
+                Rule::new("S", vec![n("L")]),
+                Rule::new("A", vec![t('a')]),
+                Rule::new("A", vec![]),
+                Rule::new("L", vec![n("B"), n("L"), t('y')]),
+                Rule::new("L", vec![t('y')]),
+                Rule::new("B", vec![]),
+            ],
+        );
+        let parser = EarleyParser::new(&grammar);
+        let trees = |input| {
+            let forest = parser.parse(tokens(input)).unwrap();
+            let mut trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+            trees.sort();
+            trees
+        };
+        assert_eq!(trees("x"), vec!["[[][]x]"]);
+        assert_eq!(trees("ax"), vec!["[[]ax]", "[a[]x]"]);
+        assert_eq!(trees("aax"), vec!["[aax]"]);
+        assert_eq!(trees("yyy"), vec!["[[][[]yy]y]"]);
+        assert!(parser.parse(tokens("aaax")).is_err());
+    }
+
+    #[test]
+    fn cyclic_grammar() {
+        // S -> S | 'a'
+        let grammar = grammar(
+            "S",
+            vec![
+                Rule::new("S", vec![Symbol::Nonterminal("S")]),
+                Rule::new("S", vec![Symbol::Terminal('a')]),
+            ],
+        );
+        let forest = EarleyParser::new(&grammar).parse(tokens("a")).unwrap();
+        assert_eq!(forest.count(), None);
+        let trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+        assert_eq!(trees, vec!["a"]);
+    }
+
+    #[test]
+    fn right_recursion() {
+        // L -> 'a' L | 'a' ',' L | 'a'
+        let l = || Symbol::Nonterminal("L");
+        let t = Symbol::Terminal;
+        let grammar = grammar(
+            "L",
+            vec![
+                Rule::new("L", vec![t('a'), l()]),
+                Rule::new("L", vec![t('a'), t(','), l()]),
+                Rule::new("L", vec![t('a')]),
+            ],
+        );
+        let parser = EarleyParser::new(&grammar);
+        let input = "aa,".repeat(200) + "a";
+        let mut chart = parser.chart(tokens(&input)).unwrap();
+        // without Leo items, the last set would hold a completed item for every token
+        let items: usize = chart.sets.iter().map(|set| set.items.len()).sum();
+        assert!(items < 10 * chart.sets.len(), "{} items", items);
+
+        let forest = parser.forest(chart);
+        assert_eq!(forest.count(), Some(1));
+        let tree = forest.tree(|_, _, _| unreachable!()).unwrap();
+        match tree {
+            Tree::Rule { start, end, .. } => assert_eq!((start, end), (0, input.len())),
+            Tree::Token(_) => unreachable!(),
+        }
+
+        chart = parser.chart(tokens("aa,a,a")).unwrap();
+        let forest = parser.forest(chart);
+        let trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+        assert_eq!(trees, vec!["[a[a,[a,a]]]"]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        // S -> '(' S ')' | 'x'
+        let s = Symbol::Nonterminal("S");
+        let t = Symbol::Terminal;
+        let mut grammar = grammar(
+            "S",
+            vec![
+                Rule::new("S", vec![t('('), s, t(')')]),
+                Rule::new("S", vec![t('x')]),
+            ],
+        );
+        let parser = EarleyParser::new(&grammar);
+        let error = |parser: &EarleyParser<char, &str>, input| match parser.recognize(tokens(input))
+        {
+            Err(ParseError::Syntax(error)) => error.to_string(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            error(&parser, "((x)x"),
+            "unexpected x at byte 4; expected )"
+        );
+        assert_eq!(
+            error(&parser, "(("),
+            "unexpected end of input at byte 2; expected one of (, x"
+        );
+        assert_eq!(
+            error(&parser, "x)"),
+            "unexpected ) at byte 1; expected end of input"
+        );
+        assert_eq!(
+            error(&parser, "y"),
+            "unexpected y at byte 0; expected one of (, x"
+        );
+
+        grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(grammar.rules().to_vec())
+            .symbol_precedence(vec![])
+            .terminal_names(vec![('x', "identifier".to_owned())])
+            .build()
+            .unwrap();
+        let parser = EarleyParser::new(&grammar);
+        assert_eq!(error(&parser, "(x("), "unexpected ( at byte 2; expected )");
+        assert_eq!(
+            error(&parser, "(("),
+            "unexpected end of input at byte 2; expected one of (, identifier"
+        );
+        assert_eq!(
+            error(&parser, "(x)x"),
+            "unexpected identifier at byte 3; expected end of input"
+        );
+    }
+}
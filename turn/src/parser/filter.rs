@@ -0,0 +1,452 @@
+//! Declarative disambiguation of parse forests.
+
+use super::forest::{Forest, ForestNode, PackedNode};
+use crate::grammar::{Associativity, Grammar, Preference, Symbol};
+use std::collections::{HashMap, HashSet};
+
+/// A rule as seen by the filters.
+#[derive(Clone, Debug)]
+struct FilterRule {
+    length: usize,
+    preference: Preference,
+    /// The precedence level and associativity of the precedence terminal.
+    precedence: Option<(usize, Associativity)>,
+    /// The first and the last right-hand symbols are nonterminals.
+    left_open: bool,
+    right_open: bool,
+}
+
+/// Disambiguation filters of parse forests, declared by a grammar.
+///
+/// The filters remove trees from a `Forest` by the declarations of its grammar:
+/// - rule priorities (`Grammar::rule_priorities`): a node of the lower rule
+///   cannot be a child of a node of the higher rule;
+/// - precedence and associativity of the rules (`Grammar::symbol_precedence`,
+///   `Rule::precedence`), like yacc: a rule cannot be the first or the last child of a
+///   rule with a higher precedence, or of a rule with the same precedence if the
+///   associativity of the level forbids it, provided the child is open on that side
+///   (`E -> E '+' E` as the last child of `E -> E '*' E`, but not `E -> '(' E ')'`);
+/// - rejected rules (`Preference::Reject`): a nonterminal node with an alternative
+///   by a rejected rule is removed, with all alternatives containing it;
+/// - preferred and avoided rules (`Preference::Prefer`, `Preference::Avoid`):
+///   applied to the remaining alternatives of every ambiguous node.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{Associativity, GrammarBuilder, Rule, Symbol};
+/// use turn::lr::{Algorithm, ParseTable};
+/// use turn::parser::{Filters, GlrParser};
+/// use turn::{Location, Token};
+///
+/// // E -> E '+' E | E '*' E | 'n'
+/// let e = || Symbol::Nonterminal("E");
+/// let t = Symbol::Terminal;
+/// let rules = vec![
+///     Rule::new("E", vec![e(), t('+'), e()]),
+///     Rule::new("E", vec![e(), t('*'), e()]),
+///     Rule::new("E", vec![t('n')]),
+/// ];
+/// // without precedences for the table, with precedences for the filters
+/// let ambiguous = GrammarBuilder::default()
+///     .starting_nonterminal("E")
+///     .rules(rules.clone())
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("E")
+///     .rules(rules)
+///     .symbol_precedence(vec![
+///         (Associativity::Left, vec!['*']),
+///         (Associativity::Left, vec!['+']),
+///     ])
+///     .build()
+///     .unwrap();
+/// let table = ParseTable::with_conflicts(&ambiguous, Algorithm::Lalr1).unwrap();
+///
+/// let tokens = "n+n*n+n".char_indices().map(|(i, symbol)| {
+///     let mut location = Location::new();
+///     location.span = (i, i + 1);
+///     Ok(Token { symbol, attribute: "", location })
+/// });
+/// let mut forest = GlrParser::new(&table).parse(tokens).unwrap();
+/// assert_eq!(forest.count(), Some(5));
+/// assert!(Filters::new(&grammar).apply(&mut forest));
+/// assert_eq!(forest.count(), Some(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Filters {
+    rules: Vec<FilterRule>,
+    priorities: HashSet<(usize, usize)>,
+}
+
+impl Filters {
+    /// The filters declared by a grammar.
+    pub fn new<Terminal, Nonterminal>(grammar: &Grammar<Terminal, Nonterminal>) -> Self
+    where
+        Terminal: PartialEq,
+    {
+        let precedence = |terminal: &Terminal| {
+            grammar
+                .symbol_precedence()
+                .iter()
+                .enumerate()
+                .find(|(_, (_, terminals))| terminals.contains(terminal))
+                .map(|(level, (associativity, _))| (level, *associativity))
+        };
+        let rules = grammar
+            .rules()
+            .iter()
+            .map(|rule| {
+                let right_hand = rule.right_hand();
+                let is_nonterminal =
+                    |symbol: Option<&Symbol<_, _>>| matches!(symbol, Some(Symbol::Nonterminal(_)));
+                FilterRule {
+                    length: right_hand.len(),
+                    preference: rule.preference(),
+                    precedence: rule.precedence().and_then(precedence),
+                    left_open: is_nonterminal(right_hand.first()),
+                    right_open: is_nonterminal(right_hand.last()),
+                }
+            })
+            .collect();
+        Filters {
+            rules,
+            priorities: grammar.rule_priorities().iter().copied().collect(),
+        }
+    }
+
+    /// Remove the trees of a forest that the filters reject.
+    ///
+    /// Nodes shared between allowed and rejected contexts are copied, so the forest
+    /// keeps exactly the trees that pass the filters (except for the preferences,
+    /// which are applied to nodes). Returns false if no tree is left.
+    pub fn apply<Terminal: Clone, Nonterminal: Clone>(
+        &self,
+        forest: &mut Forest<'_, Terminal, Nonterminal>,
+    ) -> bool {
+        let root = forest.root();
+        let nodes = forest.nodes_mut();
+
+        // rejected rules
+        for node in nodes.iter_mut() {
+            if let ForestNode::Nonterminal { alternatives, .. } = node {
+                if alternatives.iter().any(|alternative| {
+                    self.rules[alternative.rule].preference == Preference::Reject
+                }) {
+                    alternatives.clear();
+                }
+            }
+        }
+
+        // priorities, precedences and associativities: the children of an alternative
+        // keep only the alternatives allowed below its rule
+        let mut copies: HashMap<(usize, usize, usize), Option<usize>> = HashMap::new();
+        let mut node = 0;
+        while node < nodes.len() {
+            let mut alternatives = match &mut nodes[node] {
+                ForestNode::Nonterminal { alternatives, .. } => std::mem::take(alternatives),
+                ForestNode::Token(_) => vec![],
+            };
+            alternatives.retain_mut(|alternative| {
+                let rule = alternative.rule;
+                for position in 0..alternative.children.len() {
+                    let child = alternative.children[position];
+                    let key = (child, rule, position);
+                    let copy = match copies.get(&key) {
+                        Some(&copy) => copy,
+                        None => {
+                            let copy = self.filter_child(nodes, child, rule, position);
+                            copies.insert(key, copy);
+                            copy
+                        }
+                    };
+                    match copy {
+                        Some(copy) => alternative.children[position] = copy,
+                        None => return false,
+                    }
+                }
+                true
+            });
+            if let ForestNode::Nonterminal {
+                alternatives: node_alternatives,
+                ..
+            } = &mut nodes[node]
+            {
+                *node_alternatives = alternatives;
+            }
+            node += 1;
+        }
+
+        remove_dead_nodes(nodes);
+
+        // preferences
+        for node in nodes.iter_mut() {
+            if let ForestNode::Nonterminal { alternatives, .. } = node {
+                let preferences: Vec<Preference> = alternatives
+                    .iter()
+                    .map(|alternative| self.rules[alternative.rule].preference)
+                    .collect();
+                if preferences.contains(&Preference::Prefer) {
+                    let mut preferences = preferences.iter();
+                    alternatives.retain(|_| preferences.next() == Some(&Preference::Prefer));
+                } else if preferences.iter().any(|&p| p != Preference::Avoid) {
+                    let mut preferences = preferences.iter();
+                    alternatives.retain(|_| preferences.next() != Some(&Preference::Avoid));
+                }
+            }
+        }
+
+        match &nodes[root] {
+            ForestNode::Nonterminal { alternatives, .. } => !alternatives.is_empty(),
+            ForestNode::Token(_) => true,
+        }
+    }
+
+    /// The node to use as a child of a rule at a position: the child itself if all of its
+    /// alternatives are allowed there, a copy with the allowed alternatives,
+    /// or `None` if no alternative is allowed.
+    fn filter_child<Terminal, Nonterminal: Clone>(
+        &self,
+        nodes: &mut Vec<ForestNode<'_, Terminal, Nonterminal>>,
+        child: usize,
+        rule: usize,
+        position: usize,
+    ) -> Option<usize> {
+        let (nonterminal, start, end, alternatives) = match &nodes[child] {
+            ForestNode::Token(_) => return Some(child),
+            ForestNode::Nonterminal {
+                nonterminal,
+                start,
+                end,
+                alternatives,
+            } => (nonterminal, *start, *end, alternatives),
+        };
+        let allowed: Vec<PackedNode> = alternatives
+            .iter()
+            .filter(|alternative| self.allows(rule, position, alternative.rule))
+            .cloned()
+            .collect();
+        if allowed.len() == alternatives.len() {
+            Some(child)
+        } else if allowed.is_empty() {
+            None
+        } else {
+            let copy = ForestNode::Nonterminal {
+                nonterminal: nonterminal.clone(),
+                start,
+                end,
+                alternatives: allowed,
+            };
+            nodes.push(copy);
+            Some(nodes.len() - 1)
+        }
+    }
+
+    /// Returns true if a node of the rule `child` can be the child of a node of the rule
+    /// `parent` at a position.
+    fn allows(&self, parent: usize, position: usize, child: usize) -> bool {
+        if self.priorities.contains(&(parent, child)) {
+            return false;
+        }
+        let (parent_rule, child_rule) = (&self.rules[parent], &self.rules[child]);
+        let (parent_level, associativity, child_level) =
+            match (parent_rule.precedence, child_rule.precedence) {
+                (Some((parent_level, associativity)), Some((child_level, _))) => {
+                    (parent_level, associativity, child_level)
+                }
+                _ => return true,
+            };
+        // the child is next to the operators of the parent on its open side
+        let first = position == 0 && child_rule.right_open;
+        let last = position + 1 == parent_rule.length && child_rule.left_open;
+        if !first && !last {
+            return true;
+        }
+        if child_level != parent_level {
+            // lower levels have higher precedence
+            return child_level < parent_level;
+        }
+        match associativity {
+            Associativity::Left => !last,
+            Associativity::Right => !first,
+            Associativity::None => false,
+        }
+    }
+}
+
+/// Remove the alternatives containing nodes without alternatives, until all are gone.
+fn remove_dead_nodes<Terminal, Nonterminal>(nodes: &mut [ForestNode<'_, Terminal, Nonterminal>]) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let dead: Vec<bool> = nodes
+            .iter()
+            .map(|node| match node {
+                ForestNode::Nonterminal { alternatives, .. } => alternatives.is_empty(),
+                ForestNode::Token(_) => false,
+            })
+            .collect();
+        for node in nodes.iter_mut() {
+            if let ForestNode::Nonterminal { alternatives, .. } = node {
+                let count = alternatives.len();
+                alternatives.retain(|alternative| !alternative.children.iter().any(|&c| dead[c]));
+                changed |= alternatives.len() != count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule};
+    use crate::lr::{Algorithm, ParseTable};
+    use crate::parser::{bracketed, tokens, GlrParser};
+
+    /// Parse with a table ignoring the declarations, then apply the filters of the grammar.
+    fn filtered_trees(grammar: &Grammar<char, &'static str>, input: &str) -> Option<Vec<String>> {
+        let ambiguous = GrammarBuilder::default()
+            .starting_nonterminal(*grammar.starting_nonterminal())
+            .rules(grammar.rules().to_vec())
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let table = ParseTable::with_conflicts(&ambiguous, Algorithm::Lalr1).unwrap();
+        let mut forest = GlrParser::new(&table).parse(tokens(input)).unwrap();
+        if !Filters::new(grammar).apply(&mut forest) {
+            return None;
+        }
+        Some(forest.trees().map(|tree| bracketed(&tree)).collect())
+    }
+
+    fn operators(
+        symbol_precedence: Vec<(Associativity, Vec<char>)>,
+        priorities: Vec<(usize, usize)>,
+    ) -> Grammar<char, &'static str> {
+        // E -> E '+' E | E '^' E | '-' E | '(' E ')' | n
+        let e = || Symbol::Nonterminal("E");
+        let t = Symbol::Terminal;
+        GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![e(), t('+'), e()]),
+                Rule::new("E", vec![e(), t('^'), e()]),
+                Rule::new("E", vec![t('-'), e()]),
+                Rule::new("E", vec![t('('), e(), t(')')]),
+                Rule::new("E", vec![t('n')]),
+            ])
+            .symbol_precedence(symbol_precedence)
+            .rule_priorities(priorities)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let grammar = operators(
+            vec![
+                (Associativity::Right, vec!['^']),
+                (Associativity::None, vec!['-']),
+                (Associativity::Left, vec!['+']),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            filtered_trees(&grammar, "n+n+n^n^n"),
+            Some(vec!["[[n+n]+[n^[n^n]]]".to_owned()])
+        );
+        // the prefix operator binds tighter than '+', but not than '^'
+        assert_eq!(
+            filtered_trees(&grammar, "-n+n"),
+            Some(vec!["[[-n]+n]".to_owned()])
+        );
+        assert_eq!(
+            filtered_trees(&grammar, "-n^n"),
+            Some(vec!["[-[n^n]]".to_owned()])
+        );
+        // parentheses close the operand
+        assert_eq!(
+            filtered_trees(&grammar, "n^(n+n)"),
+            Some(vec!["[n^[([n+n])]]".to_owned()])
+        );
+    }
+
+    #[test]
+    fn nonassociative_operators() {
+        let grammar = operators(vec![(Associativity::None, vec!['+'])], vec![]);
+        assert_eq!(filtered_trees(&grammar, "n+n+n"), None);
+        assert_eq!(
+            filtered_trees(&grammar, "n+n"),
+            Some(vec!["[n+n]".to_owned()])
+        );
+    }
+
+    #[test]
+    fn rule_priorities() {
+        // '^' > '+': no sum directly below a power, at any position
+        let grammar = operators(vec![], vec![(1, 0)]);
+        assert_eq!(
+            filtered_trees(&grammar, "n^n+n"),
+            Some(vec!["[[n^n]+n]".to_owned()])
+        );
+        // the sum is still ambiguous
+        assert_eq!(filtered_trees(&grammar, "n+n+n").unwrap().len(), 2);
+
+        let invalid = GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(grammar.rules().to_vec())
+            .symbol_precedence(vec![])
+            .rule_priorities(vec![(1, 5)])
+            .build()
+            .unwrap();
+        assert_eq!(
+            invalid.validate(),
+            Err(vec![crate::grammar::GrammarError::UndefinedRule {
+                rule: 5
+            }])
+        );
+    }
+
+    #[test]
+    fn preferences() {
+        // S -> I | K; I -> I c | c; K -> 'i' 'f' (rejected as an identifier)
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let rules = |keyword: Preference, reject: Preference| {
+            vec![
+                Rule::new("S", vec![n("I")]),
+                Rule::new("S", vec![n("K")]).with_preference(keyword),
+                Rule::new("I", vec![n("I"), n("C")]),
+                Rule::new("I", vec![n("C")]),
+                Rule::new("I", vec![t('i'), t('f')]).with_preference(reject),
+                Rule::new("C", vec![t('i')]),
+                Rule::new("C", vec![t('f')]),
+                Rule::new("C", vec![t('x')]),
+                Rule::new("K", vec![t('i'), t('f')]),
+            ]
+        };
+        let grammar = |keyword, reject| {
+            GrammarBuilder::default()
+                .starting_nonterminal("S")
+                .rules(rules(keyword, reject))
+                .symbol_precedence(vec![])
+                .build()
+                .unwrap()
+        };
+
+        let ambiguous = grammar(Preference::Normal, Preference::Normal);
+        assert_eq!(filtered_trees(&ambiguous, "if").unwrap().len(), 3);
+        let prefer = grammar(Preference::Prefer, Preference::Normal);
+        assert_eq!(filtered_trees(&prefer, "if"), Some(vec!["[if]".to_owned()]));
+        let avoid = grammar(Preference::Avoid, Preference::Normal);
+        assert_eq!(
+            filtered_trees(&avoid, "if").unwrap(),
+            vec!["[if]".to_owned(), "[if]".to_owned()]
+        );
+        // the rejected rule removes the identifier `if`, but not `ix`
+        let reject = grammar(Preference::Normal, Preference::Reject);
+        assert_eq!(filtered_trees(&reject, "if"), Some(vec!["[if]".to_owned()]));
+        assert_eq!(filtered_trees(&reject, "ix"), Some(vec!["[ix]".to_owned()]));
+    }
+}
@@ -0,0 +1,375 @@
+//! Shared packed parse forests: all parse trees of an ambiguous input in one graph.
+
+use super::{Actions, Locations};
+use crate::lexer::{Location, Token};
+use std::collections::HashMap;
+
+/// A node of a `Forest`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ForestNode<'a, Terminal, Nonterminal> {
+    /// The token at a position of the input.
+    Token(Token<'a, Terminal>),
+    /// A nonterminal derived from the tokens at the positions `start..end`.
+    Nonterminal {
+        nonterminal: Nonterminal,
+        start: usize,
+        end: usize,
+        /// The derivations of the nonterminal, more than one if it is ambiguous.
+        alternatives: Vec<PackedNode>,
+    },
+}
+
+/// A derivation of a nonterminal node by a rule.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct PackedNode {
+    /// The index of the rule in `Grammar::rules`.
+    pub rule: usize,
+    /// The nodes of the right-hand symbols of the rule.
+    pub children: Vec<usize>,
+}
+
+/// A shared packed parse forest (SPPF).
+///
+/// Every token and every nonterminal derived from a range of the input is a single node,
+/// shared by all trees containing it; the alternative derivations of an ambiguous
+/// nonterminal are packed into its node. Nodes are referenced by their indices.
+/// Cyclic grammars (`A -> A`) result in cycles in the forest: `Forest::count` reports
+/// infinitely many trees, while `Forest::trees` and `Forest::tree` skip the cyclic ones.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Forest<'a, Terminal, Nonterminal> {
+    nodes: Vec<ForestNode<'a, Terminal, Nonterminal>>,
+    root: usize,
+    /// The locations of the tokens, followed by the location of the end of input.
+    locations: Vec<Location>,
+}
+
+/// A parse tree selected from a `Forest`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Tree<'a, Terminal> {
+    Token(Token<'a, Terminal>),
+    /// A rule with the trees of its right-hand symbols,
+    /// derived from the tokens at the positions `start..end`.
+    Rule {
+        rule: usize,
+        start: usize,
+        end: usize,
+        children: Vec<Tree<'a, Terminal>>,
+    },
+}
+
+impl<'a, Terminal, Nonterminal> Forest<'a, Terminal, Nonterminal> {
+    /// The node of the starting nonterminal.
+    #[inline]
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// The node with an index.
+    #[inline]
+    pub fn node(&self, node: usize) -> &ForestNode<'a, Terminal, Nonterminal> {
+        &self.nodes[node]
+    }
+
+    /// All nodes of the forest.
+    #[inline]
+    pub fn nodes(&self) -> &[ForestNode<'a, Terminal, Nonterminal>] {
+        &self.nodes
+    }
+
+    /// The alternatives of a node, empty for tokens.
+    pub fn alternatives(&self, node: usize) -> &[PackedNode] {
+        match &self.nodes[node] {
+            ForestNode::Token(_) => &[],
+            ForestNode::Nonterminal { alternatives, .. } => alternatives,
+        }
+    }
+
+    /// Returns true if a node reachable from the root has multiple alternatives.
+    pub fn is_ambiguous(&self) -> bool {
+        self.reachable()
+            .into_iter()
+            .any(|node| self.alternatives(node).len() > 1)
+    }
+
+    /// The number of trees, `None` if there are infinitely many because of a cycle.
+    ///
+    /// The count saturates at `u128::MAX`.
+    pub fn count(&self) -> Option<u128> {
+        // the nodes in the order of a depth-first search, children before parents
+        let mut counts: Vec<Option<u128>> = vec![None; self.nodes.len()];
+        let mut on_path = vec![false; self.nodes.len()];
+        let mut stack = vec![(self.root, false)];
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                let count = self
+                    .alternatives(node)
+                    .iter()
+                    .map(|alternative| {
+                        alternative.children.iter().fold(1u128, |product, &child| {
+                            product.saturating_mul(counts[child].unwrap())
+                        })
+                    })
+                    .fold(0u128, u128::saturating_add);
+                counts[node] = Some(match self.nodes[node] {
+                    ForestNode::Token(_) => 1,
+                    ForestNode::Nonterminal { .. } => count,
+                });
+                on_path[node] = false;
+            } else if counts[node].is_none() {
+                if on_path[node] {
+                    return None;
+                }
+                on_path[node] = true;
+                stack.push((node, true));
+                for alternative in self.alternatives(node) {
+                    for &child in &alternative.children {
+                        if on_path[child] {
+                            return None;
+                        }
+                        stack.push((child, false));
+                    }
+                }
+            }
+        }
+        counts[self.root]
+    }
+
+    /// Select a tree by choosing one alternative of every ambiguous node.
+    ///
+    /// `choose` is called with the forest, the index of the node and its alternatives,
+    /// and returns the index of the chosen alternative. The tree is `None`
+    /// if the choices lead into a cycle.
+    pub fn tree<Choose>(&self, mut choose: Choose) -> Option<Tree<'a, Terminal>>
+    where
+        Terminal: Clone,
+        Choose: FnMut(&Self, usize, &[PackedNode]) -> usize,
+    {
+        let mut path = vec![];
+        self.build_tree(
+            self.root,
+            &mut path,
+            &mut |forest, node, alternatives| match alternatives.len() {
+                1 => 0,
+                _ => choose(forest, node, alternatives),
+            },
+        )
+    }
+
+    /// All trees of the forest without cycles, in the order of the alternatives.
+    pub fn trees(&self) -> Trees<'_, 'a, Terminal, Nonterminal> {
+        Trees {
+            forest: self,
+            choices: vec![],
+            done: false,
+        }
+    }
+
+    /// Compute the value of a tree with semantic actions, like an `LrParser`.
+    pub fn evaluate<A>(&self, tree: &Tree<'a, Terminal>, actions: &mut A) -> A::Value
+    where
+        Terminal: Clone,
+        A: Actions<'a, Terminal>,
+    {
+        self.evaluate_located(tree, actions).0
+    }
+
+    pub(crate) fn new(
+        nodes: Vec<ForestNode<'a, Terminal, Nonterminal>>,
+        root: usize,
+        locations: Vec<Location>,
+    ) -> Self {
+        Forest {
+            nodes,
+            root,
+            locations,
+        }
+    }
+
+    pub(crate) fn nodes_mut(&mut self) -> &mut Vec<ForestNode<'a, Terminal, Nonterminal>> {
+        &mut self.nodes
+    }
+
+    /// The nodes reachable from the root.
+    fn reachable(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        let mut reachable = vec![];
+        while let Some(node) = stack.pop() {
+            if !std::mem::replace(&mut visited[node], true) {
+                reachable.push(node);
+                for alternative in self.alternatives(node) {
+                    stack.extend(&alternative.children);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Build the tree of a node with the alternatives chosen by a callback,
+    /// `None` if a chosen alternative contains a node on the path from the root.
+    fn build_tree(
+        &self,
+        node: usize,
+        path: &mut Vec<usize>,
+        choose: &mut dyn FnMut(&Self, usize, &[PackedNode]) -> usize,
+    ) -> Option<Tree<'a, Terminal>>
+    where
+        Terminal: Clone,
+    {
+        let (start, end, alternatives) = match &self.nodes[node] {
+            ForestNode::Token(token) => return Some(Tree::Token(token.clone())),
+            ForestNode::Nonterminal {
+                start,
+                end,
+                alternatives,
+                ..
+            } => (*start, *end, alternatives),
+        };
+        let alternative = &alternatives[choose(self, node, alternatives)];
+        path.push(node);
+        if alternative
+            .children
+            .iter()
+            .any(|child| path.contains(child))
+        {
+            path.pop();
+            return None;
+        }
+        let children = alternative
+            .children
+            .iter()
+            .map(|&child| self.build_tree(child, path, choose))
+            .collect::<Option<Vec<_>>>();
+        path.pop();
+        Some(Tree::Rule {
+            rule: alternative.rule,
+            start,
+            end,
+            children: children?,
+        })
+    }
+
+    fn evaluate_located<A>(
+        &self,
+        tree: &Tree<'a, Terminal>,
+        actions: &mut A,
+    ) -> (A::Value, Location)
+    where
+        Terminal: Clone,
+        A: Actions<'a, Terminal>,
+    {
+        match tree {
+            Tree::Token(token) => (actions.shift(token.clone()), token.location),
+            Tree::Rule {
+                rule,
+                end,
+                children,
+                ..
+            } => {
+                let (mut values, locations): (Vec<_>, Vec<_>) = children
+                    .iter()
+                    .map(|child| self.evaluate_located(child, actions))
+                    .unzip();
+                let locations = Locations::new(&locations, self.locations[*end]);
+                let location = locations.rule;
+                (actions.reduce(*rule, values.drain(..), locations), location)
+            }
+        }
+    }
+}
+
+/// An iterator over the trees of a `Forest`, see `Forest::trees`.
+#[derive(Clone, Debug)]
+pub struct Trees<'f, 'a, Terminal, Nonterminal> {
+    forest: &'f Forest<'a, Terminal, Nonterminal>,
+    /// The alternatives chosen for the ambiguous nodes of the last tree, in the order
+    /// of a depth-first traversal, with their numbers of alternatives.
+    choices: Vec<(usize, usize)>,
+    done: bool,
+}
+
+impl<'a, Terminal: Clone, Nonterminal> Iterator for Trees<'_, 'a, Terminal, Nonterminal> {
+    type Item = Tree<'a, Terminal>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            // repeat the previous choices, then take the first alternatives
+            let previous = std::mem::take(&mut self.choices);
+            let choices = &mut self.choices;
+            let tree = self.forest.tree(|_, _, alternatives| {
+                let choice = previous.get(choices.len()).map_or(0, |&(choice, _)| choice);
+                choices.push((choice, alternatives.len()));
+                choice
+            });
+            // advance the last choice that has further alternatives
+            while let Some((choice, count)) = self.choices.pop() {
+                if choice + 1 < count {
+                    self.choices.push((choice + 1, count));
+                    break;
+                }
+            }
+            self.done = self.choices.is_empty();
+            if tree.is_some() {
+                return tree;
+            }
+        }
+        None
+    }
+}
+
+/// Builds the nodes of a forest, sharing the nodes of equal nonterminals and ranges.
+pub(crate) struct ForestBuilder<'a, Terminal, Nonterminal> {
+    pub nodes: Vec<ForestNode<'a, Terminal, Nonterminal>>,
+    /// The node of each nonterminal index and range.
+    nonterminals: HashMap<(usize, usize, usize), usize>,
+}
+
+impl<'a, Terminal, Nonterminal> ForestBuilder<'a, Terminal, Nonterminal> {
+    pub fn new() -> Self {
+        ForestBuilder {
+            nodes: vec![],
+            nonterminals: HashMap::new(),
+        }
+    }
+
+    pub fn token(&mut self, token: Token<'a, Terminal>) -> usize {
+        self.nodes.push(ForestNode::Token(token));
+        self.nodes.len() - 1
+    }
+
+    /// The node of a nonterminal (by its index) derived from the tokens at `start..end`.
+    pub fn nonterminal(
+        &mut self,
+        index: usize,
+        nonterminal: impl FnOnce() -> Nonterminal,
+        start: usize,
+        end: usize,
+    ) -> usize {
+        let nodes = &mut self.nodes;
+        *self
+            .nonterminals
+            .entry((index, start, end))
+            .or_insert_with(|| {
+                nodes.push(ForestNode::Nonterminal {
+                    nonterminal: nonterminal(),
+                    start,
+                    end,
+                    alternatives: vec![],
+                });
+                nodes.len() - 1
+            })
+    }
+
+    /// Add an alternative to a nonterminal node; returns false if the node already has it.
+    pub fn add_alternative(&mut self, node: usize, alternative: PackedNode) -> bool {
+        match &mut self.nodes[node] {
+            ForestNode::Nonterminal { alternatives, .. }
+                if !alternatives.contains(&alternative) =>
+            {
+                alternatives.push(alternative);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use super::forest::{Forest, ForestBuilder, PackedNode};
+use super::{Input, LrTable, ParseError, SyntaxError};
+use crate::lexer::{LexError, Token};
+use crate::lr::{Action, ParseTable};
+use std::collections::VecDeque;
+
+/// A node of the graph-structured stack.
+#[derive(Clone, Debug)]
+struct StackNode {
+    state: usize,
+    /// The number of tokens shifted before the node was created.
+    position: usize,
+    /// The nodes below this node, with the forest nodes of the symbols between them.
+    edges: Vec<(usize, usize)>,
+}
+
+/// A generalized LR (GLR) parser that follows all conflicting actions of a table.
+///
+/// The parser runs on a table from `ParseTable::with_conflicts` and keeps the stacks
+/// of all actions in a graph-structured stack. Instead of calling semantic actions,
+/// it returns a shared packed parse forest of all parses of the input, whose trees
+/// can be counted, enumerated or selected, and then evaluated (see `Forest`).
+/// Epsilon rules, hidden left recursion and cyclic grammars are supported.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+/// use turn::lr::{Algorithm, ParseTable};
+/// use turn::parser::{Callbacks, ForestNode, GlrParser, Locations};
+/// use turn::{Location, Token};
+///
+/// // E -> E '-' E | 'n', ambiguous without precedences
+/// let e = || Symbol::Nonterminal("E");
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("E")
+///     .rules(vec![
+///         Rule::new("E", vec![e(), Symbol::Terminal('-'), e()]),
+///         Rule::new("E", vec![Symbol::Terminal('n')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let table = ParseTable::with_conflicts(&grammar, Algorithm::Lalr1).unwrap();
+/// assert_eq!(table.conflicts().len(), 1);
+///
+/// let source = "8-4-2";
+/// let tokens = source.char_indices().map(|(i, c)| {
+///     let symbol = if c == '-' { '-' } else { 'n' };
+///     let mut location = Location::new();
+///     location.span = (i, i + 1);
+///     Ok(Token { symbol, attribute: &source[i..i + 1], location })
+/// });
+/// let forest = GlrParser::new(&table).parse(tokens).unwrap();
+/// assert!(forest.is_ambiguous());
+/// assert_eq!(forest.count(), Some(2));
+///
+/// let mut actions = Callbacks {
+///     shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+///     reduce: |rule, mut values: std::vec::Drain<i32>, _: Locations| match rule {
+///         0 => values.next().unwrap() - values.nth(1).unwrap(),
+///         _ => values.next().unwrap(),
+///     },
+/// };
+/// let values: Vec<i32> = forest
+///     .trees()
+///     .map(|tree| forest.evaluate(&tree, &mut actions))
+///     .collect();
+/// assert_eq!(values, vec![2, 6]);
+///
+/// // choose the alternative whose first child ends last: left associativity
+/// let tree = forest
+///     .tree(|forest, _, alternatives| {
+///         let ends = alternatives.iter().map(|alternative| {
+///             match forest.node(alternative.children[0]) {
+///                 ForestNode::Nonterminal { end, .. } => *end,
+///                 ForestNode::Token(_) => 0,
+///             }
+///         });
+///         ends.enumerate().max_by_key(|&(_, end)| end).unwrap().0
+///     })
+///     .unwrap();
+/// assert_eq!(forest.evaluate(&tree, &mut actions), 2);
+/// ```
+#[derive(Debug)]
+pub struct GlrParser<'t, Terminal, Nonterminal> {
+    table: &'t ParseTable<Terminal, Nonterminal>,
+}
+
+impl<Terminal, Nonterminal> Clone for GlrParser<'_, Terminal, Nonterminal> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Terminal, Nonterminal> Copy for GlrParser<'_, Terminal, Nonterminal> {}
+
+impl<'t, Terminal, Nonterminal> GlrParser<'t, Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    pub fn new(table: &'t ParseTable<Terminal, Nonterminal>) -> Self {
+        GlrParser { table }
+    }
+
+    /// Parse the tokens of a lexer and return the forest of all parses.
+    ///
+    /// The first lexical error stops the parser, as does a token that no stack can shift.
+    /// The expected terminals of a syntax error are those that some stack reading the
+    /// offending token could shift after its reductions for them.
+    pub fn parse<'a, Lexer>(
+        &self,
+        lexer: Lexer,
+    ) -> Result<Forest<'a, Terminal, Nonterminal>, ParseError<'a, Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Terminal>, LexError<Terminal>>>,
+    {
+        let table = self.table;
+        let end = LrTable::end(table);
+        let mut input = Input::new(lexer.into_iter());
+        let mut forest = ForestBuilder::new();
+        let mut locations = vec![];
+        let mut stack = vec![StackNode {
+            state: 0,
+            position: 0,
+            edges: vec![],
+        }];
+        // the stack nodes of the current position
+        let mut frontier = vec![0];
+        for position in 0.. {
+            let token = input.next_token()?;
+            let location = input.location(token.as_ref());
+            locations.push(location);
+            let lookahead = match &token {
+                Some(token) => table.terminal_index(&token.symbol),
+                None => Some(end),
+            };
+            let shifted = frontier.clone();
+            // the stack before the reductions: its nodes and the forest nodes of its edges
+            let before = (stack.len(), forest.nodes.len());
+            if let Some(lookahead) = lookahead {
+                self.reduce(&mut stack, &mut frontier, &mut forest, lookahead, position);
+            }
+
+            let mut next_frontier: Vec<usize> = vec![];
+            let mut token_node = None;
+            for &node in &frontier {
+                let state = stack[node].state;
+                for &action in lookahead.map_or(&[][..], |t| table.indexed_actions(state, t)) {
+                    match (action, &token) {
+                        (Action::Shift(target), Some(token)) => {
+                            let label =
+                                *token_node.get_or_insert_with(|| forest.token(token.clone()));
+                            match next_frontier.iter().find(|&&n| stack[n].state == target) {
+                                Some(&shifter) => stack[shifter].edges.push((node, label)),
+                                None => {
+                                    stack.push(StackNode {
+                                        state: target,
+                                        position: position + 1,
+                                        edges: vec![(node, label)],
+                                    });
+                                    next_frontier.push(stack.len() - 1);
+                                }
+                            }
+                        }
+                        (Action::Accept, None) => {
+                            let root = stack[node].edges[0].1;
+                            return Ok(Forest::new(forest.nodes, root, locations));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if next_frontier.is_empty() {
+                let expected = self.expected(&stack, &shifted, before, position);
+                let error = SyntaxError::from_table(table, token, location, expected);
+                return Err(ParseError::Syntax(Box::new(error)));
+            }
+            frontier = next_frontier;
+        }
+        unreachable!()
+    }
+
+    /// The lookaheads that some stack of a frontier can shift or accept after performing
+    /// the reductions for them.
+    ///
+    /// The stack is restored to `before`: the nodes and the forest nodes that existed
+    /// before the reductions for the offending token, which only add edges labelled by
+    /// new forest nodes.
+    fn expected(
+        &self,
+        stack: &[StackNode],
+        frontier: &[usize],
+        before: (usize, usize),
+        position: usize,
+    ) -> Vec<usize> {
+        let table = self.table;
+        let (nodes, labels) = before;
+        let stack: Vec<StackNode> = stack[..nodes]
+            .iter()
+            .map(|node| StackNode {
+                edges: node
+                    .edges
+                    .iter()
+                    .copied()
+                    .filter(|&(_, label)| label < labels)
+                    .collect(),
+                ..*node
+            })
+            .collect();
+        (0..=LrTable::end(table))
+            .filter(|&t| Some(t) != LrTable::error_terminal(table))
+            .filter(|&t| {
+                let mut stack = stack.clone();
+                let mut frontier = frontier.to_vec();
+                let mut forest = ForestBuilder::new();
+                self.reduce(&mut stack, &mut frontier, &mut forest, t, position);
+                frontier.iter().any(|&node| {
+                    table
+                        .indexed_actions(stack[node].state, t)
+                        .iter()
+                        .any(|action| matches!(action, Action::Shift(_) | Action::Accept))
+                })
+            })
+            .collect()
+    }
+
+    /// Perform all reductions for a lookahead on the stacks of the frontier,
+    /// adding the stack nodes they reach to the frontier.
+    fn reduce<'a>(
+        &self,
+        stack: &mut Vec<StackNode>,
+        frontier: &mut Vec<usize>,
+        forest: &mut ForestBuilder<'a, Terminal, Nonterminal>,
+        lookahead: usize,
+        position: usize,
+    ) {
+        let table = self.table;
+        let grammar = &table.automaton().grammar;
+        let reductions = |stack: &[StackNode], node: usize| {
+            table
+                .indexed_actions(stack[node].state, lookahead)
+                .iter()
+                .filter_map(move |&action| match action {
+                    Action::Reduce(rule) => Some((node, rule)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut queue: VecDeque<(usize, usize)> = frontier
+            .iter()
+            .flat_map(|&node| reductions(stack, node))
+            .collect();
+        while let Some((node, rule)) = queue.pop_front() {
+            let left_hand = grammar.rules[rule].left_hand;
+            for (bottom, children) in paths(stack, node, table.rule_length(rule)) {
+                let target = match table.rule_goto(stack[bottom].state, rule) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let start = stack[bottom].position;
+                let label = forest.nonterminal(
+                    left_hand,
+                    || grammar.nonterminals[left_hand].clone(),
+                    start,
+                    position,
+                );
+                forest.add_alternative(label, PackedNode { rule, children });
+                match frontier.iter().find(|&&n| stack[n].state == target) {
+                    Some(&reduced) => {
+                        if stack[reduced].edges.iter().all(|&(n, _)| n != bottom) {
+                            stack[reduced].edges.push((bottom, label));
+                            // the reductions through the new edge
+                            for &n in frontier.iter() {
+                                queue.extend(
+                                    reductions(stack, n)
+                                        .into_iter()
+                                        .filter(|&(_, rule)| table.rule_length(rule) > 0),
+                                );
+                            }
+                        }
+                    }
+                    None => {
+                        stack.push(StackNode {
+                            state: target,
+                            position,
+                            edges: vec![(bottom, label)],
+                        });
+                        frontier.push(stack.len() - 1);
+                        queue.extend(reductions(stack, stack.len() - 1));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The paths of a length down the stack from a node: the nodes they end at and the
+/// forest nodes of their edges, from the bottom to the top.
+fn paths(stack: &[StackNode], node: usize, length: usize) -> Vec<(usize, Vec<usize>)> {
+    let mut paths = vec![(node, vec![])];
+    for _ in 0..length {
+        paths = paths
+            .into_iter()
+            .flat_map(|(node, labels)| {
+                stack[node].edges.iter().map(move |&(below, label)| {
+                    let mut labels = labels.clone();
+                    labels.push(label);
+                    (below, labels)
+                })
+            })
+            .collect();
+    }
+    for (_, labels) in &mut paths {
+        labels.reverse();
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule, Symbol};
+    use crate::lr::Algorithm;
+    use crate::parser::{bracketed, tokens, Callbacks, Locations};
+    use std::vec::Drain;
+
+    fn table(rules: Vec<Rule<char, &'static str>>) -> ParseTable<char, &'static str> {
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(rules)
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        ParseTable::with_conflicts(&grammar, Algorithm::Lalr1).unwrap()
+    }
+
+    #[test]
+    fn ambiguous_expressions() {
+        // S -> S '+' S | n
+        let t = Symbol::Terminal;
+        let s = || Symbol::Nonterminal("S");
+        let table = table(vec![
+            Rule::new("S", vec![s(), t('+'), s()]),
+            Rule::new("S", vec![t('n')]),
+        ]);
+        let forest = GlrParser::new(&table).parse(tokens("1+2+3+4")).unwrap();
+        // the Catalan number of three operators
+        assert_eq!(forest.count(), Some(5));
+        let trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+        assert_eq!(trees.len(), 5);
+        for tree in ["[1+[2+[3+4]]]", "[[[1+2]+3]+4]", "[[1+2]+[3+4]]"].iter() {
+            assert!(
+                trees.iter().any(|t| t == tree),
+                "{} not in {:?}",
+                tree,
+                trees
+            );
+        }
+
+        let mut actions = Callbacks {
+            shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+            reduce: |rule, mut values: Drain<u32>, _: Locations| match rule {
+                0 => values.next().unwrap() + values.nth(1).unwrap(),
+                _ => values.next().unwrap(),
+            },
+        };
+        for tree in forest.trees() {
+            assert_eq!(forest.evaluate(&tree, &mut actions), 10);
+        }
+
+        let forest = GlrParser::new(&table).parse(tokens("1+2")).unwrap();
+        assert!(!forest.is_ambiguous());
+        assert_eq!(forest.count(), Some(1));
+    }
+
+    #[test]
+    fn epsilon_rules() {
+        // S -> A S 'x' | 'y'; A -> ε | 'a': hidden left recursion
+        let t = Symbol::Terminal;
+        let s = || Symbol::Nonterminal("S");
+        let a = || Symbol::Nonterminal("A");
+        let table = table(vec![
+            Rule::new("S", vec![a(), s(), t('x')]),
+            Rule::new("S", vec![t('y')]),
+            Rule::new("A", vec![]),
+            Rule::new("A", vec![t('a')]),
+        ]);
+        let parser = GlrParser::new(&table);
+        let forest = parser.parse(tokens("yxx")).unwrap();
+        assert_eq!(forest.count(), Some(1));
+        let forest = parser.parse(tokens("ayxx")).unwrap();
+        assert_eq!(forest.count(), Some(2));
+        let trees: Vec<String> = forest.trees().map(|tree| bracketed(&tree)).collect();
+        assert_eq!(trees, vec!["[[][ayx]x]", "[a[[]yx]x]"]);
+
+        // the locations of empty rules are at the next token
+        let mut actions = Callbacks {
+            shift: |token: Token<'_, char>| token.location.span,
+            reduce: |_, _: Drain<(usize, usize)>, locations: Locations<'_>| locations.rule.span,
+        };
+        let tree = forest.trees().next().unwrap();
+        assert_eq!(forest.evaluate(&tree, &mut actions), (0, 4));
+    }
+
+    #[test]
+    fn cyclic_grammar() {
+        // S -> S | 'a'
+        let table = table(vec![
+            Rule::new("S", vec![Symbol::Nonterminal("S")]),
+            Rule::new("S", vec![Symbol::Terminal('a')]),
+        ]);
+        let forest = GlrParser::new(&table).parse(tokens("a")).unwrap();
+        assert_eq!(forest.count(), None);
+        assert!(forest.is_ambiguous());
+        let trees: Vec<_> = forest.trees().collect();
+        assert_eq!(trees.len(), 1);
+        let cycle = |_: &Forest<char, &str>, _, alternatives: &[PackedNode]| {
+            alternatives.iter().position(|a| a.rule == 0).unwrap()
+        };
+        assert_eq!(forest.tree(cycle), None);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        // S -> S '+' S | n
+        let t = Symbol::Terminal;
+        let s = || Symbol::Nonterminal("S");
+        let table = table(vec![
+            Rule::new("S", vec![s(), t('+'), s()]),
+            Rule::new("S", vec![t('n')]),
+        ]);
+        let parser = GlrParser::new(&table);
+        let message = |input| parser.parse(tokens(input)).unwrap_err().to_string();
+        assert_eq!(
+            message("1+2+"),
+            "unexpected end of input at byte 4; expected n"
+        );
+        assert_eq!(
+            message("1+2 "),
+            "unexpected   at byte 3; expected one of +, end of input"
+        );
+        assert_eq!(
+            message("12"),
+            "unexpected n at byte 1; expected one of +, end of input"
+        );
+    }
+
+    #[test]
+    fn expected_after_reductions() {
+        // S -> 'c' | 'b' S 'b': the merged LALR(1) state after 'c' reduces on both
+        // lookaheads, but only 'b' can follow it after 'b'
+        let t = Symbol::Terminal;
+        let s = || Symbol::Nonterminal("S");
+        let table = table(vec![
+            Rule::new("S", vec![t('c')]),
+            Rule::new("S", vec![t('b'), s(), t('b')]),
+        ]);
+        let parser = GlrParser::new(&table);
+        assert_eq!(
+            parser.parse(tokens("bcc")).unwrap_err().to_string(),
+            "unexpected c at byte 2; expected b"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use super::{Actions, Input, Locations, ParseError, SyntaxError};
+use crate::grammar::IndexedSymbol;
+use crate::lexer::{LexError, Token};
+use crate::ll::LlTable;
+use fixedbitset::FixedBitSet;
+
+/// An entry of the stack of a predictive parser.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Frame {
+    /// A symbol still to be matched.
+    Symbol(IndexedSymbol),
+    /// The end of the right-hand side of a predicted rule,
+    /// whose values start at `base` on the value stack.
+    Reduce { rule: usize, base: usize },
+}
+
+/// A table-driven predictive parser, running on an LL(1) table.
+///
+/// The parser reads tokens from a lexer like an `LrParser` and calls the same
+/// semantic actions: every token is shifted when it is matched, and a rule is reduced
+/// once all of its right-hand symbols are matched, so the values are computed bottom-up
+/// in the same order as by an LR parser of the grammar. Tables with conflicts predict
+/// the earliest rule; a nonterminal predicted again by its own expansion without
+/// matching a token, as by a left-recursive rule, is reported as a syntax error
+/// at the token for which it would be expanded forever.
+///
+/// # Examples
+/// ```
+/// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+/// use turn::ll::LlTable;
+/// use turn::parser::{Callbacks, LlParser, Locations};
+/// use turn::{Location, Token};
+///
+/// // Entries -> Entry Entries | ε; Entry -> 'k' '=' 'v' ';'
+/// let n = Symbol::Nonterminal;
+/// let t = Symbol::Terminal;
+/// let grammar = GrammarBuilder::default()
+///     .starting_nonterminal("Entries")
+///     .rules(vec![
+///         Rule::new("Entries", vec![n("Entry"), n("Entries")]),
+///         Rule::new("Entries", vec![]),
+///         Rule::new("Entry", vec![t('k'), t('='), t('v'), t(';')]),
+///     ])
+///     .symbol_precedence(vec![])
+///     .build()
+///     .unwrap();
+/// let table = LlTable::new(&grammar).unwrap();
+///
+/// let tokens = |source: &'static str| {
+///     source.char_indices().map(move |(i, symbol)| {
+///         let mut location = Location::new();
+///         location.span = (i, i + 1);
+///         Ok(Token { symbol, attribute: &source[i..i + 1], location })
+///     })
+/// };
+/// // count the entries
+/// let mut actions = Callbacks {
+///     shift: |_: Token<char>| 0,
+///     reduce: |rule, values: std::vec::Drain<u32>, _: Locations| match rule {
+///         0 => values.sum(),
+///         1 => 0,
+///         _ => 1,
+///     },
+/// };
+/// let parser = LlParser::new(&table);
+/// assert_eq!(parser.parse(tokens("k=v;k=v;"), &mut actions), Ok(2));
+/// assert_eq!(
+///     parser.parse(tokens("k=v;k;"), &mut actions).unwrap_err().to_string(),
+///     "unexpected ; at byte 5; expected ="
+/// );
+/// ```
+#[derive(Debug)]
+pub struct LlParser<'t, Terminal, Nonterminal> {
+    table: &'t LlTable<Terminal, Nonterminal>,
+}
+
+impl<Terminal, Nonterminal> Clone for LlParser<'_, Terminal, Nonterminal> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Terminal, Nonterminal> Copy for LlParser<'_, Terminal, Nonterminal> {}
+
+impl<'t, Terminal, Nonterminal> LlParser<'t, Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    pub fn new(table: &'t LlTable<Terminal, Nonterminal>) -> Self {
+        LlParser { table }
+    }
+
+    /// Parse the tokens of a lexer and return the value of the starting nonterminal.
+    ///
+    /// The first lexical or syntax error stops the parser. The expected terminals
+    /// of a syntax error are those that could follow the last matched token.
+    pub fn parse<'a, Lexer, A>(
+        &self,
+        lexer: Lexer,
+        actions: &mut A,
+    ) -> Result<A::Value, ParseError<'a, Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Terminal>, LexError<Terminal>>>,
+        A: Actions<'a, Terminal>,
+    {
+        let table = self.table;
+        let grammar = table.grammar();
+        let mut input = Input::new(lexer.into_iter());
+        let mut stack = vec![Frame::Symbol(IndexedSymbol::Nonterminal(grammar.start))];
+        let mut values = vec![];
+        let mut locations = vec![];
+        // the stack before the predictions for the current lookahead is
+        // `stack[..matched]` followed by the frames popped by the predictions
+        let mut matched = 1;
+        let mut popped = vec![];
+        let mut token = input.next_token()?;
+        let mut lookahead = self.lookahead_index(token.as_ref());
+        loop {
+            let frame = stack.pop();
+            if stack.len() < matched {
+                popped.extend(frame);
+                matched = stack.len();
+            }
+            match frame {
+                Some(Frame::Symbol(IndexedSymbol::Terminal(t))) if lookahead == Some(t) => {
+                    let shifted = token.take().unwrap();
+                    locations.push(shifted.location);
+                    values.push(actions.shift(shifted));
+                    matched = stack.len();
+                    popped.clear();
+                    token = input.next_token()?;
+                    lookahead = self.lookahead_index(token.as_ref());
+                    continue;
+                }
+                Some(Frame::Symbol(IndexedSymbol::Nonterminal(n))) => {
+                    // the rules predicted for the current lookahead that are still expanded
+                    let recursive = stack[matched..].iter().any(|frame| match *frame {
+                        Frame::Reduce { rule, .. } => grammar.rules[rule].left_hand == n,
+                        Frame::Symbol(_) => false,
+                    });
+                    if recursive {
+                        let location = input.location(token.as_ref());
+                        let error = SyntaxError::from_grammar(grammar, token, location, vec![]);
+                        return Err(ParseError::Syntax(Box::new(error)));
+                    }
+                    let rule = lookahead.and_then(|t| table.indexed_rules(n, t).first());
+                    if let Some(&rule) = rule {
+                        stack.push(Frame::Reduce {
+                            rule,
+                            base: values.len(),
+                        });
+                        let right_hand = &grammar.rules[rule].right_hand;
+                        stack.extend(right_hand.iter().rev().map(|&symbol| Frame::Symbol(symbol)));
+                        continue;
+                    }
+                }
+                Some(Frame::Reduce { rule, base }) => {
+                    let rule_locations =
+                        Locations::new(&locations[base..], input.location(token.as_ref()));
+                    let location = rule_locations.rule;
+                    let value = actions.reduce(rule, values.drain(base..), rule_locations);
+                    values.push(value);
+                    locations.truncate(base);
+                    locations.push(location);
+                    continue;
+                }
+                None if lookahead == Some(grammar.end()) => return Ok(values.pop().unwrap()),
+                _ => {}
+            }
+            let location = input.location(token.as_ref());
+            let before = stack[..matched].iter().chain(popped.iter().rev());
+            let expected = self.expected(before.copied().collect());
+            let error = SyntaxError::from_grammar(grammar, token, location, expected);
+            return Err(ParseError::Syntax(Box::new(error)));
+        }
+    }
+
+    fn lookahead_index(&self, token: Option<&Token<'_, Terminal>>) -> Option<usize> {
+        let grammar = self.table.grammar();
+        match token {
+            Some(token) => grammar.terminal(&token.symbol),
+            None => Some(grammar.end()),
+        }
+    }
+
+    /// The lookaheads that can be matched from a stack: the FIRST set of its symbols,
+    /// with the end of input if they are nullable.
+    fn expected(&self, stack: Vec<Frame>) -> Vec<usize> {
+        let grammar = self.table.grammar();
+        let symbols: Vec<IndexedSymbol> = stack
+            .into_iter()
+            .rev()
+            .filter_map(|frame| match frame {
+                Frame::Symbol(symbol) => Some(symbol),
+                Frame::Reduce { .. } => None,
+            })
+            .collect();
+        let mut expected = FixedBitSet::with_capacity(grammar.end() + 1);
+        if self.table.first_of_sequence(&symbols, &mut expected) {
+            expected.insert(grammar.end());
+        }
+        expected
+            .ones()
+            .filter(|&t| Some(t) != grammar.error)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{GrammarBuilder, Rule, Symbol};
+    use crate::lexer::LexErrorKind;
+    use crate::ll::LlTableError;
+    use crate::parser::{tokens, Callbacks};
+    use std::vec::Drain;
+
+    /// E -> T E'; E' -> '+' T E' | ε; T -> F T'; T' -> '*' F T' | ε; F -> '(' E ')' | n
+    fn table() -> LlTable<char, &'static str> {
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![n("T"), n("E'")]),
+                Rule::new("E'", vec![t('+'), n("T"), n("E'")]),
+                Rule::new("E'", vec![]),
+                Rule::new("T", vec![n("F"), n("T'")]),
+                Rule::new("T'", vec![t('*'), n("F"), n("T'")]),
+                Rule::new("T'", vec![]),
+                Rule::new("F", vec![t('('), n("E"), t(')')]),
+                Rule::new("F", vec![t('n')]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        LlTable::new(&grammar).unwrap()
+    }
+
+    fn evaluate(input: &str) -> Result<u32, ParseError<'_, char>> {
+        let mut actions = Callbacks {
+            shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+            reduce: |rule, values: Drain<u32>, _: Locations| {
+                let values: Vec<u32> = values.collect();
+                match rule {
+                    0 | 1 => values[values.len() - 2] + values[values.len() - 1],
+                    2 => 0,
+                    3 | 4 => values[values.len() - 2] * values[values.len() - 1],
+                    5 => 1,
+                    6 => values[1],
+                    _ => values[0],
+                }
+            },
+        };
+        LlParser::new(&table()).parse(tokens(input), &mut actions)
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(evaluate("2+3*(4+1)"), Ok(17));
+        assert_eq!(evaluate("((2))*3*2"), Ok(12));
+    }
+
+    #[test]
+    fn locations() {
+        let table = table();
+        let mut spans = vec![];
+        let mut actions = Callbacks {
+            shift: |_: Token<char>| (),
+            reduce: |rule, _: Drain<()>, locations: Locations| {
+                spans.push((rule, locations.rule.span, locations.right_hand.len()));
+            },
+        };
+        LlParser::new(&table)
+            .parse(tokens("1+2"), &mut actions)
+            .unwrap();
+        assert_eq!(
+            spans,
+            vec![
+                (7, (0, 1), 1),
+                (5, (1, 1), 0),
+                (3, (0, 1), 2),
+                (7, (2, 3), 1),
+                (5, (3, 3), 0),
+                (3, (2, 3), 2),
+                (2, (3, 3), 0),
+                (1, (1, 3), 3),
+                (0, (0, 3), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let message = |input| evaluate(input).unwrap_err().to_string();
+        assert_eq!(
+            message("2+"),
+            "unexpected end of input at byte 2; expected one of (, n"
+        );
+        // the lookaheads of the stack before the empty rules were predicted
+        assert_eq!(
+            message("23"),
+            "unexpected n at byte 1; expected one of *, +, end of input"
+        );
+        assert_eq!(
+            message("(2"),
+            "unexpected end of input at byte 2; expected one of ), *, +"
+        );
+        assert_eq!(
+            message("2)"),
+            "unexpected ) at byte 1; expected one of *, +, end of input"
+        );
+        assert_eq!(message("a"), "unexpected a at byte 0; expected one of (, n");
+    }
+
+    #[test]
+    fn lexical_errors() {
+        match evaluate("1+$") {
+            Err(ParseError::Lex(error)) => {
+                assert_eq!(error.kind, LexErrorKind::UnexpectedCharacter('$'))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn left_recursion() {
+        let n = Symbol::Nonterminal;
+        let t = Symbol::Terminal;
+        let conflicting_table = |rules| {
+            let grammar = GrammarBuilder::default()
+                .starting_nonterminal("S")
+                .rules(rules)
+                .symbol_precedence(vec![])
+                .build()
+                .unwrap();
+            match LlTable::new(&grammar) {
+                Err(LlTableError::Conflicts { table, .. }) => table,
+                result => panic!("unexpected result {:?}", result.map(|_| ())),
+            }
+        };
+        let mut actions = Callbacks {
+            shift: |_: Token<char>| (),
+            reduce: |_, _: Drain<()>, _: Locations| (),
+        };
+
+        // S -> S '+' n | n
+        let table = conflicting_table(vec![
+            Rule::new("S", vec![n("S"), t('+'), t('n')]),
+            Rule::new("S", vec![t('n')]),
+        ]);
+        let error = LlParser::new(&table)
+            .parse(tokens("1+2"), &mut actions)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "unexpected n at byte 0");
+
+        // S -> A A 'x'; A -> 'a' | ε: A is predicted twice before 'x', but not by itself
+        let table = conflicting_table(vec![
+            Rule::new("S", vec![n("A"), n("A"), t('x')]),
+            Rule::new("A", vec![t('a')]),
+            Rule::new("A", vec![]),
+        ]);
+        assert_eq!(
+            LlParser::new(&table).parse(tokens("x"), &mut actions),
+            Ok(())
+        );
+    }
+}
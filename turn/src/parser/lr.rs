@@ -0,0 +1,1005 @@
+use super::repair::{find_repair, Edit, MAX_COST, PARSE_DISTANCE};
+use super::{Actions, Input, Locations, ParseError, RecoveredParse, Repair, SyntaxError};
+use crate::grammar::Lookahead;
+use crate::lexer::{LexError, Location, Token};
+use crate::lr::{Action, ParseTable};
+
+/// The tables driving an `LrParser`.
+///
+/// Lookaheads are represented by indices: the terminals of the grammar are numbered
+/// from 0 and the end of input has the index `end()`.
+pub trait LrTable {
+    type Terminal;
+
+    /// The index of the lookahead of a terminal, `None` if the grammar does not use the terminal.
+    fn terminal_index(&self, terminal: &Self::Terminal) -> Option<usize>;
+
+    /// The index of the end of input.
+    fn end(&self) -> usize;
+
+    /// The lookahead with an index.
+    fn lookahead(&self, index: usize) -> Lookahead<Self::Terminal>;
+
+    /// The preferred action for a state and a lookahead, `None` for a syntax error.
+    fn action(&self, state: usize, lookahead: usize) -> Option<Action>;
+
+    /// The number of right-hand symbols of a rule.
+    fn rule_length(&self, rule: usize) -> usize;
+
+    /// The state to go to from a state after reducing by a rule.
+    fn goto(&self, state: usize, rule: usize) -> Option<usize>;
+
+    /// The index of the error terminal of the grammar, see `Grammar::error_terminal`.
+    fn error_terminal(&self) -> Option<usize>;
+
+    /// The display name of a terminal, see `Grammar::terminal_names`.
+    fn terminal_name(&self, index: usize) -> Option<&str>;
+}
+
+impl<Terminal, Nonterminal> LrTable for ParseTable<Terminal, Nonterminal>
+where
+    Terminal: Ord + Clone,
+    Nonterminal: Ord + Clone,
+{
+    type Terminal = Terminal;
+
+    #[inline]
+    fn terminal_index(&self, terminal: &Terminal) -> Option<usize> {
+        self.automaton().grammar.terminal(terminal)
+    }
+
+    #[inline]
+    fn end(&self) -> usize {
+        self.automaton().grammar.end()
+    }
+
+    #[inline]
+    fn lookahead(&self, index: usize) -> Lookahead<Terminal> {
+        self.automaton().grammar.lookahead(index)
+    }
+
+    #[inline]
+    fn action(&self, state: usize, lookahead: usize) -> Option<Action> {
+        self.indexed_actions(state, lookahead).first().copied()
+    }
+
+    #[inline]
+    fn rule_length(&self, rule: usize) -> usize {
+        ParseTable::rule_length(self, rule)
+    }
+
+    #[inline]
+    fn goto(&self, state: usize, rule: usize) -> Option<usize> {
+        self.rule_goto(state, rule)
+    }
+
+    #[inline]
+    fn error_terminal(&self) -> Option<usize> {
+        self.automaton().grammar.error
+    }
+
+    #[inline]
+    fn terminal_name(&self, index: usize) -> Option<&str> {
+        self.automaton().grammar.names.get(index)?.as_deref()
+    }
+}
+
+/// LR tables stored in static arrays, as generated by `turn_grammar_derive`.
+///
+/// The lookahead indices of terminals are computed by a function of type `Index`.
+/// Tables stored in a `static` use a function pointer, while tables built in a function
+/// can use the type of a function item or a closure so that the calls are inlined.
+///
+/// # Examples
+/// ```
+/// use turn::lr::Action;
+/// use turn::parser::{Callbacks, Locations, LrParser, StaticTable};
+/// use turn::{Location, Token};
+///
+/// // S -> 'x'
+/// static TABLE: StaticTable<char> = StaticTable {
+///     terminals: &['x'],
+///     terminal_index: |&c| if c == 'x' { Some(0) } else { None },
+///     actions: &[
+///         &[Some(Action::Shift(2)), None],
+///         &[None, Some(Action::Accept)],
+///         &[None, Some(Action::Reduce(0))],
+///     ],
+///     gotos: &[&[Some(1)], &[None], &[None]],
+///     rules: &[(0, 1)],
+///     error_terminal: None,
+///     terminal_names: &[Some("`x`")],
+/// };
+///
+/// let token = Token { symbol: 'x', attribute: "x", location: Location::new() };
+/// let mut actions = Callbacks {
+///     shift: |token: Token<char>| token.attribute.to_owned(),
+///     reduce: |_rule, mut values: std::vec::Drain<String>, _: Locations| values.next().unwrap(),
+/// };
+/// let value = LrParser::new(&TABLE).parse(vec![Ok(token)], &mut actions);
+/// assert_eq!(value, Ok("x".to_owned()));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct StaticTable<Terminal: 'static, Index = fn(&Terminal) -> Option<usize>> {
+    /// The terminals in the order of their lookahead indices.
+    pub terminals: &'static [Terminal],
+    /// The lookahead index of a terminal, `None` if the grammar does not use the terminal.
+    pub terminal_index: Index,
+    /// The preferred action of each state, indexed by lookahead.
+    pub actions: &'static [&'static [Option<Action>]],
+    /// The gotos of each state, indexed by nonterminal.
+    pub gotos: &'static [&'static [Option<usize>]],
+    /// The left-hand nonterminal and the number of right-hand symbols of each rule.
+    pub rules: &'static [(usize, usize)],
+    /// The index of the error terminal.
+    pub error_terminal: Option<usize>,
+    /// The display names of the terminals, indexed like `terminals`.
+    pub terminal_names: &'static [Option<&'static str>],
+}
+
+impl<Terminal, Index> LrTable for StaticTable<Terminal, Index>
+where
+    Terminal: Clone,
+    Index: Fn(&Terminal) -> Option<usize>,
+{
+    type Terminal = Terminal;
+
+    #[inline]
+    fn terminal_index(&self, terminal: &Terminal) -> Option<usize> {
+        (self.terminal_index)(terminal)
+    }
+
+    #[inline]
+    fn end(&self) -> usize {
+        self.terminals.len()
+    }
+
+    #[inline]
+    fn lookahead(&self, index: usize) -> Lookahead<Terminal> {
+        match self.terminals.get(index) {
+            Some(terminal) => Lookahead::Terminal(terminal.clone()),
+            None => Lookahead::End,
+        }
+    }
+
+    #[inline]
+    fn action(&self, state: usize, lookahead: usize) -> Option<Action> {
+        self.actions[state][lookahead]
+    }
+
+    #[inline]
+    fn rule_length(&self, rule: usize) -> usize {
+        self.rules[rule].1
+    }
+
+    #[inline]
+    fn goto(&self, state: usize, rule: usize) -> Option<usize> {
+        self.gotos[state][self.rules[rule].0]
+    }
+
+    #[inline]
+    fn error_terminal(&self) -> Option<usize> {
+        self.error_terminal
+    }
+
+    #[inline]
+    fn terminal_name(&self, index: usize) -> Option<&str> {
+        self.terminal_names.get(index).copied().flatten()
+    }
+}
+
+/// The number of shifts after an error recovery during which syntax errors are not reported.
+const SUPPRESSED_SHIFTS: usize = 3;
+
+/// How the parser continues after an error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Recovery {
+    /// Stop at the first error.
+    Stop,
+    /// Recover with the error terminal of the grammar.
+    ErrorTerminal,
+    /// Repair the input by the cheapest edits.
+    Repair,
+}
+
+/// The result of feeding a terminal to a simulated parser.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Step {
+    Shifted,
+    Accepted,
+    Failed,
+}
+
+/// Perform the reductions for a terminal and shift it on a stack of states.
+pub(super) fn simulate<Table: LrTable + ?Sized>(
+    table: &Table,
+    states: &mut Vec<usize>,
+    terminal: usize,
+) -> Step {
+    loop {
+        match table.action(*states.last().unwrap(), terminal) {
+            Some(Action::Shift(target)) => {
+                states.push(target);
+                return Step::Shifted;
+            }
+            Some(Action::Reduce(rule)) => {
+                states.truncate(states.len() - table.rule_length(rule));
+                match table.goto(*states.last().unwrap(), rule) {
+                    Some(target) => states.push(target),
+                    None => return Step::Failed,
+                }
+            }
+            Some(Action::Accept) => return Step::Accepted,
+            Some(Action::Error) | None => return Step::Failed,
+        }
+    }
+}
+
+/// A deterministic LR parser driven by an `LrTable`.
+///
+/// Tables with conflicts can be used as well; the parser takes the preferred action
+/// of each conflict (see `ParseTable::action`).
+#[derive(Debug)]
+pub struct LrParser<'t, Table: ?Sized> {
+    table: &'t Table,
+}
+
+impl<Table: ?Sized> Clone for LrParser<'_, Table> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Table: ?Sized> Copy for LrParser<'_, Table> {}
+
+impl<'t, Table: LrTable + ?Sized> LrParser<'t, Table>
+where
+    Table::Terminal: Clone,
+{
+    pub fn new(table: &'t Table) -> Self {
+        LrParser { table }
+    }
+
+    /// Parse the tokens of a lexer and return the value of the starting nonterminal.
+    ///
+    /// Every value is kept together with its location: the location of the token,
+    /// or `Locations::rule` of the reduction. The first lexical or syntax error stops the parser.
+    pub fn parse<'a, Lexer, A>(
+        &self,
+        lexer: Lexer,
+        actions: &mut A,
+    ) -> Result<A::Value, ParseError<'a, Table::Terminal>>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Table::Terminal>, LexError<Table::Terminal>>>,
+        A: Actions<'a, Table::Terminal>,
+    {
+        let mut result = self.run(lexer, actions, Recovery::Stop);
+        match result.value {
+            Some(value) => Ok(value),
+            None => Err(result.errors.pop().unwrap()),
+        }
+    }
+
+    /// Parse the tokens of a lexer, recovering from errors like yacc.
+    ///
+    /// Lexical errors are reported and the erroneous input is skipped. On a syntax error,
+    /// the parser pops states until it can shift the error terminal of the grammar
+    /// (`Grammar::error_terminal`) and shifts it; its value is the value of a token
+    /// of the error terminal with an empty attribute at the location of the offending token.
+    /// Then, tokens are discarded until one of them can be parsed. Syntax errors are
+    /// not reported until three tokens have been shifted after the recovery.
+    ///
+    /// The value is `None` if the parser could not recover: there is no error terminal,
+    /// no state on the stack can shift it, or the input ended while discarding tokens.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+    /// use turn::lr::{Algorithm, ParseTable};
+    /// use turn::parser::{Callbacks, Locations, LrParser, ParseError};
+    /// use turn::{Location, Token};
+    ///
+    /// // S -> S 's' ';' | S '!' ';' | ε
+    /// let t = Symbol::Terminal;
+    /// let s = || Symbol::Nonterminal("S");
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("S")
+    ///     .rules(vec![
+    ///         Rule::new("S", vec![s(), t('s'), t(';')]),
+    ///         Rule::new("S", vec![s(), t('!'), t(';')]),
+    ///         Rule::new("S", vec![]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .error_terminal('!')
+    ///     .build()
+    ///     .unwrap();
+    /// let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+    ///
+    /// let source = "s;ss;s;x;s;";
+    /// let tokens = source.char_indices().map(|(i, c)| {
+    ///     let mut location = Location::new();
+    ///     location.span = (i, i + 1);
+    ///     Ok(Token { symbol: c, attribute: &source[i..i + 1], location })
+    /// });
+    /// // count the statements and the errors
+    /// let mut actions = Callbacks {
+    ///     shift: |token: Token<char>| (0, (token.symbol == '!') as u32),
+    ///     reduce: |rule, mut values: std::vec::Drain<(u32, u32)>, _: Locations| match rule {
+    ///         2 => (0, 0),
+    ///         _ => {
+    ///             let (statements, errors) = values.next().unwrap();
+    ///             (statements + 1, errors + values.next().unwrap().1)
+    ///         }
+    ///     },
+    /// };
+    /// let result = LrParser::new(&table).parse_with_recovery(tokens, &mut actions);
+    /// // the statement before the unknown token is discarded with it
+    /// assert_eq!(result.value, Some((4, 2)));
+    /// let messages: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+    /// assert_eq!(
+    ///     messages,
+    ///     vec![
+    ///         "unexpected s at byte 3; expected ;",
+    ///         "unexpected x at byte 7; expected one of s, end of input",
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_with_recovery<'a, Lexer, A>(
+        &self,
+        lexer: Lexer,
+        actions: &mut A,
+    ) -> RecoveredParse<'a, Table::Terminal, A::Value>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Table::Terminal>, LexError<Table::Terminal>>>,
+        A: Actions<'a, Table::Terminal>,
+    {
+        self.run(lexer, actions, Recovery::ErrorTerminal)
+    }
+
+    /// Parse the tokens of a lexer, repairing syntax errors by the cheapest edits of the input.
+    ///
+    /// Lexical errors are reported and the erroneous input is skipped. On a syntax error,
+    /// the parser searches for the fewest insertions, deletions and replacements of tokens
+    /// (at most three) after which it can parse the next three tokens, reports the error
+    /// with the repairs in `SyntaxError::repairs` and continues with the edited input.
+    /// Inserted and replacing tokens have an empty attribute and the location of the token
+    /// they are placed at. No grammar annotations are needed; the error terminal is never inserted.
+    ///
+    /// The value is `None` if an error could not be repaired.
+    ///
+    /// # Examples
+    /// ```
+    /// use turn::grammar::{GrammarBuilder, Rule, Symbol};
+    /// use turn::lr::{Algorithm, ParseTable};
+    /// use turn::parser::{Callbacks, Locations, LrParser, ParseError};
+    /// use turn::{Location, Token};
+    ///
+    /// // S -> S 'x' '=' 'n' ';' | ε
+    /// let t = Symbol::Terminal;
+    /// let grammar = GrammarBuilder::default()
+    ///     .starting_nonterminal("S")
+    ///     .rules(vec![
+    ///         Rule::new("S", vec![Symbol::Nonterminal("S"), t('x'), t('='), t('n'), t(';')]),
+    ///         Rule::new("S", vec![]),
+    ///     ])
+    ///     .symbol_precedence(vec![])
+    ///     .build()
+    ///     .unwrap();
+    /// let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+    ///
+    /// let source = "x=n;x=nx=n;x==n;";
+    /// let tokens = source.char_indices().map(|(i, c)| {
+    ///     let mut location = Location::new();
+    ///     location.span = (i, i + 1);
+    ///     Ok(Token { symbol: c, attribute: &source[i..i + 1], location })
+    /// });
+    /// // count the statements
+    /// let mut actions = Callbacks {
+    ///     shift: |_: Token<char>| 0,
+    ///     reduce: |rule, mut values: std::vec::Drain<u32>, _: Locations| match rule {
+    ///         0 => values.next().unwrap() + 1,
+    ///         _ => 0,
+    ///     },
+    /// };
+    /// let result = LrParser::new(&table).parse_with_repair(tokens, &mut actions);
+    /// assert_eq!(result.value, Some(4));
+    /// let messages: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+    /// assert_eq!(
+    ///     messages,
+    ///     vec![
+    ///         "unexpected x at byte 7; expected ;; inserted ;",
+    ///         "unexpected = at byte 13; expected n; deleted =",
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_with_repair<'a, Lexer, A>(
+        &self,
+        lexer: Lexer,
+        actions: &mut A,
+    ) -> RecoveredParse<'a, Table::Terminal, A::Value>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Table::Terminal>, LexError<Table::Terminal>>>,
+        A: Actions<'a, Table::Terminal>,
+    {
+        self.run(lexer, actions, Recovery::Repair)
+    }
+
+    fn run<'a, Lexer, A>(
+        &self,
+        lexer: Lexer,
+        actions: &mut A,
+        recovery: Recovery,
+    ) -> RecoveredParse<'a, Table::Terminal, A::Value>
+    where
+        Lexer: IntoIterator<Item = Result<Token<'a, Table::Terminal>, LexError<Table::Terminal>>>,
+        A: Actions<'a, Table::Terminal>,
+    {
+        let table = self.table;
+        let mut input = Input::new(lexer.into_iter());
+        let mut errors = vec![];
+        let stop = |errors| RecoveredParse {
+            value: None,
+            errors,
+        };
+        // read the next token, skipping lexical errors when recovering
+        let read = |input: &mut Input<'a, _, _>, errors: &mut Vec<_>| loop {
+            match input.next_token() {
+                Ok(token) => return Some(token),
+                Err(error) => {
+                    errors.push(ParseError::Lex(error));
+                    if recovery == Recovery::Stop {
+                        return None;
+                    }
+                }
+            }
+        };
+
+        let mut states = vec![0];
+        let mut values = vec![];
+        let mut locations = vec![];
+        // the stack before the reductions for the current lookahead is
+        // `states[..reduced]` followed by the states popped by the reductions
+        let mut reduced = 1;
+        let mut popped = vec![];
+        // the number of shifts until syntax errors are reported again
+        let mut suppressed: usize = 0;
+        let mut token = match read(&mut input, &mut errors) {
+            Some(token) => token,
+            None => return stop(errors),
+        };
+        let mut lookahead = self.lookahead_index(token.as_ref());
+        loop {
+            let state = *states.last().unwrap();
+            match lookahead.and_then(|t| table.action(state, t)) {
+                Some(Action::Shift(target)) => {
+                    let shifted = token.take().unwrap();
+                    locations.push(shifted.location);
+                    values.push(actions.shift(shifted));
+                    states.push(target);
+                    reduced = states.len();
+                    popped.clear();
+                    suppressed = suppressed.saturating_sub(1);
+                    token = match read(&mut input, &mut errors) {
+                        Some(token) => token,
+                        None => return stop(errors),
+                    };
+                    lookahead = self.lookahead_index(token.as_ref());
+                }
+                Some(Action::Reduce(rule)) => {
+                    let start = states.len() - table.rule_length(rule);
+                    if start < reduced {
+                        popped.splice(0..0, states[start..reduced].iter().copied());
+                        reduced = start;
+                    }
+                    states.truncate(start);
+                    let rule_locations =
+                        Locations::new(&locations[start - 1..], input.location(token.as_ref()));
+                    let location = rule_locations.rule;
+                    let value = actions.reduce(rule, values.drain(start - 1..), rule_locations);
+                    values.push(value);
+                    locations.truncate(start - 1);
+                    locations.push(location);
+                    let target = table
+                        .goto(states[start - 1], rule)
+                        .expect("the table has a goto after every reduction");
+                    states.push(target);
+                }
+                Some(Action::Accept) => {
+                    return RecoveredParse {
+                        value: values.pop(),
+                        errors,
+                    }
+                }
+                Some(Action::Error) | None => {
+                    let location = input.location(token.as_ref());
+                    let before: Vec<usize> =
+                        states[..reduced].iter().chain(&popped).copied().collect();
+                    let error_terminal = match (recovery, table.error_terminal()) {
+                        (Recovery::ErrorTerminal, Some(error_terminal)) => error_terminal,
+                        (Recovery::Repair, _) => {
+                            // lexical errors after the offending token are reported after it
+                            let mut lex_errors = vec![];
+                            let peeked = input.peek(MAX_COST + PARSE_DISTANCE - 1, &mut lex_errors);
+                            let lookaheads: Vec<Option<usize>> = std::iter::once(lookahead)
+                                .chain(peeked.iter().map(|t| self.lookahead_index(t.as_ref())))
+                                .collect();
+                            let edits = match find_repair(table, &states, &lookaheads) {
+                                Some(edits) => edits,
+                                None => {
+                                    errors.push(self.syntax_error(
+                                        token,
+                                        location,
+                                        &before,
+                                        vec![],
+                                    ));
+                                    errors.append(&mut lex_errors);
+                                    return stop(errors);
+                                }
+                            };
+                            let (repaired, repairs) =
+                                self.apply_repair(&edits, token.clone(), &mut input);
+                            errors.push(self.syntax_error(token, location, &before, repairs));
+                            errors.append(&mut lex_errors);
+                            input.unread(repaired.into_iter());
+                            token = read(&mut input, &mut errors).unwrap();
+                            lookahead = self.lookahead_index(token.as_ref());
+                            reduced = states.len();
+                            popped.clear();
+                            continue;
+                        }
+                        _ => {
+                            errors.push(self.syntax_error(token, location, &before, vec![]));
+                            return stop(errors);
+                        }
+                    };
+                    if suppressed == 0 {
+                        errors.push(self.syntax_error(token.clone(), location, &before, vec![]));
+                    }
+                    if suppressed == SUPPRESSED_SHIFTS {
+                        // nothing was shifted since the last error: discard the token
+                        if token.is_none() {
+                            return stop(errors);
+                        }
+                        token = match read(&mut input, &mut errors) {
+                            Some(token) => token,
+                            None => return stop(errors),
+                        };
+                        lookahead = self.lookahead_index(token.as_ref());
+                        reduced = states.len();
+                        popped.clear();
+                        continue;
+                    }
+                    suppressed = SUPPRESSED_SHIFTS;
+                    // pop states until the error terminal can be shifted
+                    loop {
+                        let state = *states.last().unwrap();
+                        if let Some(Action::Shift(target)) = table.action(state, error_terminal) {
+                            let symbol = match table.lookahead(error_terminal) {
+                                Lookahead::Terminal(symbol) => symbol,
+                                Lookahead::End => unreachable!(),
+                            };
+                            locations.push(location);
+                            values.push(actions.shift(Token {
+                                symbol,
+                                attribute: "",
+                                location,
+                            }));
+                            states.push(target);
+                            reduced = states.len();
+                            popped.clear();
+                            break;
+                        }
+                        if states.len() == 1 {
+                            return stop(errors);
+                        }
+                        states.pop();
+                        values.pop();
+                        locations.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    /// A syntax error at a token with the lookaheads the parser can shift or accept
+    /// after reductions from a stack of states.
+    fn syntax_error<'a>(
+        &self,
+        token: Option<Token<'a, Table::Terminal>>,
+        location: Location,
+        states: &[usize],
+        repairs: Vec<Repair<'a, Table::Terminal>>,
+    ) -> ParseError<'a, Table::Terminal> {
+        let table = self.table;
+        let error_terminal = table.error_terminal();
+        let expected = (0..=table.end())
+            .filter(|&t| Some(t) != error_terminal)
+            .filter(|&t| simulate(table, &mut states.to_vec(), t) != Step::Failed);
+        let mut error = SyntaxError::from_table(table, token, location, expected);
+        error.repairs = repairs;
+        ParseError::Syntax(Box::new(error))
+    }
+
+    /// Apply the edits of a repair to the offending token and the tokens read ahead.
+    ///
+    /// Returns the tokens to parse instead of the edited tokens, and the repairs.
+    #[allow(clippy::type_complexity)]
+    fn apply_repair<'a, Lexer>(
+        &self,
+        edits: &[Edit],
+        mut token: Option<Token<'a, Table::Terminal>>,
+        input: &mut Input<'a, Table::Terminal, Lexer>,
+    ) -> (
+        Vec<Option<Token<'a, Table::Terminal>>>,
+        Vec<Repair<'a, Table::Terminal>>,
+    )
+    where
+        Lexer: Iterator<Item = Result<Token<'a, Table::Terminal>, LexError<Table::Terminal>>>,
+    {
+        let symbol = |terminal| match self.table.lookahead(terminal) {
+            Lookahead::Terminal(symbol) => symbol,
+            Lookahead::End => unreachable!(),
+        };
+        let mut repaired = vec![];
+        let mut repairs = vec![];
+        for &edit in edits {
+            let location = input.location(token.as_ref());
+            match edit {
+                Edit::Insert(terminal) => {
+                    repaired.push(Some(Token {
+                        symbol: symbol(terminal),
+                        attribute: "",
+                        location: Location {
+                            span: (location.span.0, location.span.0),
+                            ..location
+                        },
+                    }));
+                    repairs.push(Repair::Insert(symbol(terminal)));
+                }
+                Edit::Delete | Edit::Replace(_) => {
+                    let edited = token.expect("repairs do not edit the end of input");
+                    if let Edit::Replace(terminal) = edit {
+                        repaired.push(Some(Token {
+                            symbol: symbol(terminal),
+                            attribute: "",
+                            location,
+                        }));
+                        repairs.push(Repair::Replace(edited, symbol(terminal)));
+                    } else {
+                        repairs.push(Repair::Delete(edited));
+                    }
+                    token = input
+                        .next_token()
+                        .unwrap_or_else(|_| unreachable!("repairs only edit tokens read ahead"));
+                }
+            }
+        }
+        repaired.push(token);
+        (repaired, repairs)
+    }
+
+    /// The index of the lookahead of a token, `None` if the token is not a terminal of the grammar.
+    fn lookahead_index(&self, token: Option<&Token<'_, Table::Terminal>>) -> Option<usize> {
+        match token {
+            Some(token) => self.table.terminal_index(&token.symbol),
+            None => Some(self.table.end()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{Associativity, Grammar, GrammarBuilder, Lookahead, Rule, Symbol};
+    use crate::lexer::{LexErrorKind, Location};
+    use crate::lr::Algorithm;
+    use crate::parser::{tokens, Callbacks, Locations};
+    use std::vec::Drain;
+
+    fn grammar() -> Grammar<char, &'static str> {
+        // E -> E + E | E * E | ( E ) | n
+        let t = Symbol::Terminal;
+        let e = || Symbol::Nonterminal("E");
+        GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(vec![
+                Rule::new("E", vec![e(), t('+'), e()]),
+                Rule::new("E", vec![e(), t('*'), e()]),
+                Rule::new("E", vec![t('('), e(), t(')')]),
+                Rule::new("E", vec![t('n')]),
+            ])
+            .symbol_precedence(vec![
+                (Associativity::Left, vec!['*']),
+                (Associativity::Left, vec!['+']),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    fn evaluate(input: &str) -> Result<u32, ParseError<'_, char>> {
+        let table = ParseTable::new(&grammar(), Algorithm::Lalr1).unwrap();
+        let mut actions = Callbacks {
+            shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+            reduce: |rule, mut values: Drain<u32>, _: Locations| match rule {
+                0 => values.next().unwrap() + values.nth(1).unwrap(),
+                1 => values.next().unwrap() * values.nth(1).unwrap(),
+                2 => values.nth(1).unwrap(),
+                _ => values.next().unwrap(),
+            },
+        };
+        LrParser::new(&table).parse(tokens(input), &mut actions)
+    }
+
+    #[test]
+    fn reductions() {
+        assert_eq!(evaluate("1+2*3"), Ok(7));
+        assert_eq!(evaluate("(1+2)*3"), Ok(9));
+        assert_eq!(evaluate("2*3+4*(5+1)"), Ok(30));
+    }
+
+    #[test]
+    fn locations() {
+        let table = ParseTable::new(&grammar(), Algorithm::Lalr1).unwrap();
+        let mut spans = vec![];
+        let mut actions = Callbacks {
+            shift: |_: Token<char>| (),
+            reduce: |rule, _: Drain<()>, locations: Locations| {
+                let right_hand: Vec<_> = locations.right_hand.iter().map(|l| l.span).collect();
+                spans.push((rule, locations.rule.span, right_hand));
+            },
+        };
+        LrParser::new(&table)
+            .parse(tokens("1+(2*3)"), &mut actions)
+            .unwrap();
+        assert_eq!(
+            spans,
+            vec![
+                (3, (0, 1), vec![(0, 1)]),
+                (3, (3, 4), vec![(3, 4)]),
+                (3, (5, 6), vec![(5, 6)]),
+                (1, (3, 6), vec![(3, 4), (4, 5), (5, 6)]),
+                (2, (2, 7), vec![(2, 3), (3, 6), (6, 7)]),
+                (0, (0, 7), vec![(0, 1), (1, 2), (2, 7)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_rule_locations() {
+        // S -> A 'x'; A -> ε
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![Symbol::Nonterminal("A"), Symbol::Terminal('x')]),
+                Rule::new("A", vec![]),
+            ])
+            .symbol_precedence(vec![])
+            .build()
+            .unwrap();
+        let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+        let mut actions = Callbacks {
+            shift: |token: Token<'_, char>| token.location,
+            reduce: |_, _: Drain<Location>, locations: Locations<'_>| locations.rule,
+        };
+        let mut input = tokens("  x");
+        input.drain(..2);
+        let location = LrParser::new(&table).parse(input, &mut actions).unwrap();
+        assert_eq!(location.span, (2, 3));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = match evaluate("1+*2") {
+            Err(ParseError::Syntax(error)) => error,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(error.token.map(|token| token.symbol), Some('*'));
+        assert_eq!(error.location.span, (2, 3));
+        let expected: Vec<_> = error.expected.iter().map(|e| e.lookahead).collect();
+        assert_eq!(
+            expected,
+            vec![Lookahead::Terminal('('), Lookahead::Terminal('n')]
+        );
+        assert_eq!(
+            error.to_string(),
+            "unexpected * at byte 2; expected one of (, n"
+        );
+
+        // terminals unknown to the grammar
+        let error = match evaluate("-2") {
+            Err(ParseError::Syntax(error)) => error,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(error.token.map(|token| token.symbol), Some('-'));
+        assert_eq!(error.location.span, (0, 1));
+
+        let error = match evaluate("(1+2") {
+            Err(ParseError::Syntax(error)) => error,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(error.token, None);
+        assert_eq!(error.location.span, (4, 4));
+    }
+
+    #[test]
+    fn expected_lookaheads() {
+        let grammar = grammar();
+        let named = GrammarBuilder::default()
+            .starting_nonterminal("E")
+            .rules(grammar.rules().to_vec())
+            .symbol_precedence(grammar.symbol_precedence().to_vec())
+            .terminal_names(vec![
+                ('(', "`(`".to_owned()),
+                (')', "`)`".to_owned()),
+                ('n', "number".to_owned()),
+            ])
+            .build()
+            .unwrap();
+        let table = ParseTable::new(&named, Algorithm::Lalr1).unwrap();
+        let message = |input| {
+            let mut actions = Callbacks {
+                shift: |_: Token<char>| (),
+                reduce: |_, _: Drain<()>, _: Locations| (),
+            };
+            match LrParser::new(&table).parse(tokens(input), &mut actions) {
+                Err(error) => error.to_string(),
+                Ok(()) => panic!("no syntax error in {:?}", input),
+            }
+        };
+
+        // the LALR(1) state of `n` also reduces on `)`, which cannot follow at the top level
+        assert_eq!(
+            message("12"),
+            "unexpected number at byte 1; expected one of *, +, end of input"
+        );
+        assert_eq!(
+            message("(1+2"),
+            "unexpected end of input at byte 4; expected one of `)`, *, +"
+        );
+        assert_eq!(
+            message("1+)"),
+            "unexpected `)` at byte 2; expected one of `(`, number"
+        );
+    }
+
+    #[test]
+    fn error_recovery() {
+        // S -> S E ';' | S '!' ';' | ε; E -> E '+' n | n
+        let t = Symbol::Terminal;
+        let s = || Symbol::Nonterminal("S");
+        let e = || Symbol::Nonterminal("E");
+        let grammar = GrammarBuilder::default()
+            .starting_nonterminal("S")
+            .rules(vec![
+                Rule::new("S", vec![s(), e(), t(';')]),
+                Rule::new("S", vec![s(), t('!'), t(';')]),
+                Rule::new("S", vec![]),
+                Rule::new("E", vec![e(), t('+'), t('n')]),
+                Rule::new("E", vec![t('n')]),
+            ])
+            .symbol_precedence(vec![])
+            .error_terminal('!')
+            .build()
+            .unwrap();
+        let table = ParseTable::new(&grammar, Algorithm::Lalr1).unwrap();
+        let mut actions = Callbacks {
+            shift: |token: Token<char>| match token.symbol {
+                '!' => format!("error@{}", token.location.span.0),
+                _ => token.attribute.to_owned(),
+            },
+            reduce: |_, values: Drain<String>, _: Locations| values.collect::<String>(),
+        };
+        let parser = LrParser::new(&table);
+
+        let result = parser.parse_with_recovery(tokens("1+;2;3+4$;"), &mut actions);
+        assert_eq!(result.value.as_deref(), Some("error@2;2;3+4;"));
+        let errors: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "unexpected ; at byte 2; expected n",
+                "unexpected character '$' at byte 8",
+            ]
+        );
+
+        // errors within three shifts after a recovery are not reported
+        // and the states of an incomplete recovery are popped again
+        let result = parser.parse_with_recovery(tokens("1+;+;3;"), &mut actions);
+        assert_eq!(result.value.as_deref(), Some("error@3;3;"));
+        assert_eq!(result.errors.len(), 1);
+
+        // the input ends while discarding tokens
+        let result = parser.parse_with_recovery(tokens("1;2+"), &mut actions);
+        assert_eq!(result.value, None);
+        assert_eq!(result.errors.len(), 1);
+
+        // the first error stops a parser without recovery
+        assert!(matches!(
+            parser.parse(tokens("1+;2;"), &mut actions),
+            Err(ParseError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn repairs() {
+        let table = ParseTable::new(&grammar(), Algorithm::Lalr1).unwrap();
+        let repair = |input| {
+            let mut actions = Callbacks {
+                shift: |token: Token<char>| token.attribute.parse().unwrap_or(0),
+                reduce: |rule, mut values: Drain<u32>, _: Locations| match rule {
+                    0 => values.next().unwrap() + values.nth(1).unwrap(),
+                    1 => values.next().unwrap() * values.nth(1).unwrap(),
+                    2 => values.nth(1).unwrap(),
+                    _ => values.next().unwrap(),
+                },
+            };
+            let result = LrParser::new(&table).parse_with_repair(tokens(input), &mut actions);
+            let errors: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+            (result.value, errors)
+        };
+
+        assert_eq!(
+            repair("(1+2*3"),
+            (
+                Some(7),
+                vec![
+                    "unexpected end of input at byte 6; expected one of ), *, +; inserted )".into()
+                ]
+            )
+        );
+        // insertions are preferred to deletions
+        assert_eq!(
+            repair("1+*2"),
+            (
+                Some(1),
+                vec!["unexpected * at byte 2; expected one of (, n; inserted n".into()]
+            )
+        );
+        assert_eq!(
+            repair("1+)2"),
+            (
+                Some(3),
+                vec!["unexpected ) at byte 2; expected one of (, n; deleted )".into()]
+            )
+        );
+        assert_eq!(
+            repair("2*2)3"),
+            (
+                Some(12),
+                vec![
+                    "unexpected ) at byte 3; expected one of *, +, end of input; replaced ) with *"
+                        .into()
+                ]
+            )
+        );
+        // several errors and lexical errors read ahead
+        assert_eq!(
+            repair("12$+(3"),
+            (
+                Some(5),
+                vec![
+                    "unexpected n at byte 1; expected one of *, +, end of input; inserted *".into(),
+                    "unexpected character '$' at byte 2".into(),
+                    "unexpected end of input at byte 6; expected one of ), *, +; inserted )".into(),
+                ]
+            )
+        );
+        // more than three edits are needed
+        let (value, errors) = repair("1+((((");
+        assert_eq!(value, None);
+        assert_eq!(
+            errors,
+            vec!["unexpected end of input at byte 6; expected one of (, n"]
+        );
+    }
+
+    #[test]
+    fn lexical_errors() {
+        match evaluate("1+$") {
+            Err(ParseError::Lex(error)) => {
+                assert_eq!(error.kind, LexErrorKind::UnexpectedCharacter('$'))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
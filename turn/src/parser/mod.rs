@@ -0,0 +1,441 @@
+//! Parsers driven by the tables of a grammar.
+//!
+//! Parsers read tokens from a lexer and build their results through `Actions`:
+//! every token is turned into a value when it is shifted, and the values of the
+//! right-hand side of a rule are combined when the rule is reduced.
+
+mod earley;
+mod filter;
+mod forest;
+mod glr;
+mod ll;
+mod lr;
+mod repair;
+
+pub use earley::EarleyParser;
+pub use filter::Filters;
+pub use forest::{Forest, ForestNode, PackedNode, Tree, Trees};
+pub use glr::GlrParser;
+pub use ll::LlParser;
+pub use lr::{LrParser, LrTable, StaticTable};
+pub use repair::Repair;
+
+use crate::diagnostics::Diagnostic;
+use crate::grammar::{IndexedGrammar, Lookahead};
+use crate::lexer::{LexError, Location, Token};
+use std::collections::VecDeque;
+use std::fmt;
+use std::vec::Drain;
+
+/// The locations of the symbols of a reduced rule.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Locations<'l> {
+    /// The location of the whole rule, from the start of its first symbol to the end
+    /// of its last symbol. Empty rules are located at the start of the next token.
+    pub rule: Location,
+    /// The locations of the right-hand symbols.
+    pub right_hand: &'l [Location],
+}
+
+impl<'l> Locations<'l> {
+    /// The locations of a rule with the right-hand symbols at `right_hand`.
+    ///
+    /// `next` is the location of the token after the rule.
+    pub(crate) fn new(right_hand: &'l [Location], next: Location) -> Self {
+        let rule = match (right_hand.first(), right_hand.last()) {
+            (Some(first), Some(last)) => Location {
+                span: (first.span.0, last.span.1),
+                ..*first
+            },
+            _ => Location {
+                span: (next.span.0, next.span.0),
+                ..next
+            },
+        };
+        Locations { rule, right_hand }
+    }
+}
+
+/// The semantic actions of a parser.
+pub trait Actions<'a, Symbol> {
+    /// The value of a terminal or a nonterminal.
+    type Value;
+
+    /// Compute the value of a shifted token.
+    fn shift(&mut self, token: Token<'a, Symbol>) -> Self::Value;
+
+    /// Compute the value of the left-hand nonterminal of a rule
+    /// from the values of its right-hand symbols.
+    ///
+    /// The rule is referenced by its index in `Grammar::rules`.
+    fn reduce(
+        &mut self,
+        rule: usize,
+        values: Drain<'_, Self::Value>,
+        locations: Locations<'_>,
+    ) -> Self::Value;
+}
+
+/// Semantic actions from a pair of closures.
+///
+/// # Examples
+/// ```
+/// use turn::parser::{Actions, Callbacks, Locations};
+/// use turn::{Location, Token};
+///
+/// let mut actions = Callbacks {
+///     shift: |token: Token<char>| token.attribute.len(),
+///     reduce: |_rule, values: std::vec::Drain<usize>, _: Locations| values.sum(),
+/// };
+/// let token = Token { symbol: 'a', attribute: "abc", location: Location::new() };
+/// assert_eq!(actions.shift(token), 3);
+/// let locations = Locations { rule: Location::new(), right_hand: &[Location::new(); 2] };
+/// assert_eq!(actions.reduce(0, vec![1, 2].drain(..), locations), 3);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Callbacks<Shift, Reduce> {
+    pub shift: Shift,
+    pub reduce: Reduce,
+}
+
+impl<'a, Symbol, Value, Shift, Reduce> Actions<'a, Symbol> for Callbacks<Shift, Reduce>
+where
+    Shift: FnMut(Token<'a, Symbol>) -> Value,
+    Reduce: FnMut(usize, Drain<'_, Value>, Locations<'_>) -> Value,
+{
+    type Value = Value;
+
+    #[inline]
+    fn shift(&mut self, token: Token<'a, Symbol>) -> Value {
+        (self.shift)(token)
+    }
+
+    #[inline]
+    fn reduce(&mut self, rule: usize, values: Drain<'_, Value>, locations: Locations<'_>) -> Value {
+        (self.reduce)(rule, values, locations)
+    }
+}
+
+/// A lookahead the parser would have accepted in place of an offending token.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ExpectedLookahead<Symbol> {
+    pub lookahead: Lookahead<Symbol>,
+    /// The display name of the terminal, see `Grammar::terminal_names`.
+    pub name: Option<String>,
+}
+
+/// A token the parser did not expect.
+///
+/// Terminals are displayed by their names in the grammar (`Grammar::terminal_names`)
+/// or by their `Display` implementation.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct SyntaxError<'a, Symbol> {
+    /// The offending token, `None` at the end of input.
+    pub token: Option<Token<'a, Symbol>>,
+    /// The display name of the terminal of the token.
+    pub token_name: Option<String>,
+    /// The location of the token or of the end of input.
+    pub location: Location,
+    /// The terminals that would have been accepted instead, ordered by the grammar.
+    ///
+    /// The set is computed from the stack of the parser before the reductions for the
+    /// offending token, so it contains exactly the lookaheads that can be shifted
+    /// (or accept the input) after further reductions.
+    pub expected: Vec<ExpectedLookahead<Symbol>>,
+    /// The edits of the input the parser continued with, see `LrParser::parse_with_repair`.
+    pub repairs: Vec<Repair<'a, Symbol>>,
+}
+
+/// An error returned from a parser.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ParseError<'a, Symbol> {
+    /// The lexer failed to read a token.
+    Lex(LexError<Symbol>),
+    /// The parser found an unexpected token.
+    Syntax(Box<SyntaxError<'a, Symbol>>),
+}
+
+/// The result of a parse with error recovery.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct RecoveredParse<'a, Symbol, Value> {
+    /// The value of the starting nonterminal, `None` if the parser could not recover from an error.
+    pub value: Option<Value>,
+    /// The lexical and syntax errors in the order of the input.
+    pub errors: Vec<ParseError<'a, Symbol>>,
+}
+
+/// The tokens of a lexer followed by the end of input.
+pub(crate) struct Input<'a, Symbol, Lexer> {
+    lexer: Lexer,
+    /// The location after the last token.
+    end: Location,
+    /// Tokens read ahead of the parser, `None` for the end of input.
+    buffer: VecDeque<Option<Token<'a, Symbol>>>,
+}
+
+impl<'a, Symbol, Lexer> Input<'a, Symbol, Lexer>
+where
+    Lexer: Iterator<Item = Result<Token<'a, Symbol>, LexError<Symbol>>>,
+{
+    pub(crate) fn new(lexer: Lexer) -> Self {
+        Input {
+            lexer,
+            end: Location::new(),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Read the next token from the lexer, `None` at the end of input.
+    fn read(&mut self) -> Result<Option<Token<'a, Symbol>>, LexError<Symbol>> {
+        match self.lexer.next() {
+            Some(Ok(token)) => {
+                self.end = token.location.end();
+                Ok(Some(token))
+            }
+            Some(Err(error)) => Err(error),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the next token, `None` at the end of input.
+    pub(crate) fn next_token(&mut self) -> Result<Option<Token<'a, Symbol>>, LexError<Symbol>> {
+        match self.buffer.pop_front() {
+            Some(token) => Ok(token),
+            None => self.read(),
+        }
+    }
+
+    /// Read ahead up to `count` tokens, or up to the end of input.
+    ///
+    /// Lexical errors are skipped and added to `errors`.
+    pub(crate) fn peek(
+        &mut self,
+        count: usize,
+        errors: &mut Vec<ParseError<'a, Symbol>>,
+    ) -> &VecDeque<Option<Token<'a, Symbol>>> {
+        while self.buffer.len() < count && !matches!(self.buffer.back(), Some(None)) {
+            match self.read() {
+                Ok(token) => self.buffer.push_back(token),
+                Err(error) => errors.push(ParseError::Lex(error)),
+            }
+        }
+        &self.buffer
+    }
+
+    /// Put tokens back in front of the remaining input.
+    pub(crate) fn unread(
+        &mut self,
+        tokens: impl DoubleEndedIterator<Item = Option<Token<'a, Symbol>>>,
+    ) {
+        for token in tokens.rev() {
+            self.buffer.push_front(token);
+        }
+    }
+
+    /// The location of a token, or of the end of input.
+    pub(crate) fn location(&self, token: Option<&Token<'a, Symbol>>) -> Location {
+        token.map_or(self.end, |token| token.location)
+    }
+}
+
+impl<'a, Symbol> SyntaxError<'a, Symbol> {
+    /// A syntax error at a token, with the expected lookaheads given by their indices
+    /// in a table and the names of the terminals from the table.
+    pub(crate) fn from_table<Table>(
+        table: &Table,
+        token: Option<Token<'a, Symbol>>,
+        location: Location,
+        expected: impl IntoIterator<Item = usize>,
+    ) -> Self
+    where
+        Table: LrTable<Terminal = Symbol> + ?Sized,
+    {
+        let expected = expected
+            .into_iter()
+            .map(|t| ExpectedLookahead {
+                lookahead: table.lookahead(t),
+                name: table.terminal_name(t).map(str::to_owned),
+            })
+            .collect();
+        let token_name = token
+            .as_ref()
+            .and_then(|token| table.terminal_index(&token.symbol))
+            .and_then(|t| table.terminal_name(t))
+            .map(str::to_owned);
+        SyntaxError {
+            token,
+            token_name,
+            location,
+            expected,
+            repairs: vec![],
+        }
+    }
+
+    /// A syntax error at a token, with the expected lookaheads given by their indices
+    /// in a grammar and the names of the terminals from the grammar.
+    pub(crate) fn from_grammar<Nonterminal>(
+        grammar: &IndexedGrammar<Symbol, Nonterminal>,
+        token: Option<Token<'a, Symbol>>,
+        location: Location,
+        expected: impl IntoIterator<Item = usize>,
+    ) -> Self
+    where
+        Symbol: Ord + Clone,
+        Nonterminal: Ord + Clone,
+    {
+        let name = |t: usize| grammar.names.get(t).cloned().flatten();
+        let expected = expected
+            .into_iter()
+            .map(|t| ExpectedLookahead {
+                lookahead: grammar.lookahead(t),
+                name: name(t),
+            })
+            .collect();
+        let token_name = token
+            .as_ref()
+            .and_then(|token| grammar.terminal(&token.symbol))
+            .and_then(name);
+        SyntaxError {
+            token,
+            token_name,
+            location,
+            expected,
+            repairs: vec![],
+        }
+    }
+}
+
+impl<Symbol: fmt::Display> SyntaxError<'_, Symbol> {
+    /// Create a diagnostic report of this error.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.to_string(), self.location, "unexpected token");
+        if !self.expected.is_empty() {
+            diagnostic = diagnostic.with_note(format!("expected {}", ExpectedList(&self.expected)));
+        }
+        for repair in &self.repairs {
+            diagnostic = diagnostic.with_note(repair.to_string());
+        }
+        diagnostic
+    }
+}
+
+impl<Symbol: fmt::Display> ParseError<'_, Symbol> {
+    /// Create a diagnostic report of this error.
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            ParseError::Lex(error) => error.diagnostic(),
+            ParseError::Syntax(error) => error.diagnostic(),
+        }
+    }
+}
+
+impl<Symbol> From<LexError<Symbol>> for ParseError<'_, Symbol> {
+    fn from(error: LexError<Symbol>) -> Self {
+        ParseError::Lex(error)
+    }
+}
+
+impl<'a, Symbol> From<SyntaxError<'a, Symbol>> for ParseError<'a, Symbol> {
+    fn from(error: SyntaxError<'a, Symbol>) -> Self {
+        ParseError::Syntax(Box::new(error))
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for ExpectedLookahead<Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, &self.lookahead) {
+            (Some(name), _) => f.write_str(name),
+            (None, Lookahead::Terminal(symbol)) => symbol.fmt(f),
+            (None, Lookahead::End) => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Formats a list of expected lookaheads.
+struct ExpectedList<'a, Symbol>(&'a [ExpectedLookahead<Symbol>]);
+
+impl<Symbol: fmt::Display> fmt::Display for ExpectedList<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.len() > 1 {
+            write!(f, "one of ")?;
+        }
+        for (i, expected) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            expected.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for SyntaxError<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.token_name, &self.token) {
+            (Some(name), _) => write!(f, "unexpected {}", name)?,
+            (None, Some(token)) => write!(f, "unexpected {}", token.symbol)?,
+            (None, None) => write!(f, "unexpected end of input")?,
+        }
+        write!(f, " at byte {}", self.location.span.0)?;
+        if !self.expected.is_empty() {
+            write!(f, "; expected {}", ExpectedList(&self.expected))?;
+        }
+        for (i, repair) in self.repairs.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { "; " } else { ", " }, repair)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for ParseError<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Lex(error) => error.fmt(f),
+            ParseError::Syntax(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<Symbol: fmt::Display + fmt::Debug> std::error::Error for SyntaxError<'_, Symbol> {}
+
+impl<Symbol: fmt::Display + fmt::Debug> std::error::Error for ParseError<'_, Symbol> {}
+
+/// The tokens of a string in tests: one token per character, located at its column,
+/// with digits read as `n` and `$` rejected as a lexical error.
+#[cfg(test)]
+pub(crate) fn tokens(input: &str) -> Vec<Result<Token<'_, char>, LexError<char>>> {
+    input
+        .char_indices()
+        .map(|(i, c)| {
+            let mut location = Location::new();
+            location.span = (i, i + 1);
+            if c == '$' {
+                return Err(LexError {
+                    kind: crate::lexer::LexErrorKind::UnexpectedCharacter(c),
+                    location,
+                    expected: vec![],
+                });
+            }
+            let symbol = if c.is_ascii_digit() { 'n' } else { c };
+            Ok(Token {
+                symbol,
+                attribute: &input[i..i + 1],
+                location,
+            })
+        })
+        .collect()
+}
+
+/// A tree of the tokens of `tokens` in a bracketed notation, like `[[1+2]+3]`.
+///
+/// Rules with a single child are not bracketed.
+#[cfg(test)]
+pub(crate) fn bracketed(tree: &Tree<char>) -> String {
+    match tree {
+        Tree::Token(token) => token.attribute.to_owned(),
+        Tree::Rule { children, .. } if children.len() == 1 => bracketed(&children[0]),
+        Tree::Rule { children, .. } => {
+            format!("[{}]", children.iter().map(bracketed).collect::<String>())
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Automatic repair of syntax errors by the cheapest edits of the input.
+//!
+//! The search works on the tables alone: starting at the offending token, it tries
+//! all sequences of up to `MAX_COST` insertions, deletions and replacements of tokens,
+//! cheapest first, until the parser can shift the next `PARSE_DISTANCE` tokens
+//! after the edits (or accept the input), like Burke and Fisher's repairs.
+
+use super::lr::{simulate, Step};
+use super::LrTable;
+use crate::lexer::Token;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+/// The maximum number of edits of a repair.
+pub(crate) const MAX_COST: usize = 3;
+
+/// The number of tokens that have to be parsed after the edits of a repair.
+pub(crate) const PARSE_DISTANCE: usize = 3;
+
+/// The maximum number of parser configurations examined for a single repair.
+const MAX_CONFIGURATIONS: usize = 10_000;
+
+/// An edit of the input that lets the parser continue after a syntax error.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Repair<'a, Symbol> {
+    /// A token of the terminal was inserted before the next token.
+    Insert(Symbol),
+    /// The token was deleted.
+    Delete(Token<'a, Symbol>),
+    /// The token was replaced by a token of the terminal.
+    Replace(Token<'a, Symbol>, Symbol),
+}
+
+/// A single edit of a repair, with terminals represented by their lookahead indices.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Edit {
+    Insert(usize),
+    Delete,
+    Replace(usize),
+}
+
+/// Returns true if the parser can continue with the lookaheads from the stack of states.
+///
+/// The lookaheads are parsed up to `PARSE_DISTANCE` tokens or the acceptance of the input.
+fn parses_ahead<Table: LrTable + ?Sized>(
+    table: &Table,
+    states: &[usize],
+    lookaheads: &[Option<usize>],
+) -> bool {
+    let mut states = states.to_vec();
+    for lookahead in lookaheads.iter().take(PARSE_DISTANCE) {
+        match lookahead.map(|t| simulate(table, &mut states, t)) {
+            Some(Step::Shifted) => {}
+            Some(Step::Accepted) => return true,
+            Some(Step::Failed) | None => return false,
+        }
+    }
+    true
+}
+
+/// Find a cheapest repair of a syntax error.
+///
+/// `states` is the stack of the parser and `lookaheads` are the lookahead indices of the
+/// offending token and the tokens after it (`None` for tokens unknown to the grammar),
+/// ending with the end of input if it was reached. Insertions are preferred to deletions
+/// and deletions to replacements among repairs of the same cost.
+pub(crate) fn find_repair<Table: LrTable + ?Sized>(
+    table: &Table,
+    states: &[usize],
+    lookaheads: &[Option<usize>],
+) -> Option<Vec<Edit>> {
+    let end = table.end();
+    let error_terminal = table.error_terminal();
+    let insertable: Vec<usize> = (0..end).filter(|&t| Some(t) != error_terminal).collect();
+
+    // breadth-first search: every edit has the same cost
+    let mut queue = VecDeque::new();
+    let mut visited = HashSet::new();
+    queue.push_back((states.to_vec(), 0, vec![]));
+    let mut examined = 0;
+    while let Some((states, position, edits)) = queue.pop_front() {
+        if !edits.is_empty() && parses_ahead(table, &states, &lookaheads[position..]) {
+            return Some(edits);
+        }
+        examined += 1;
+        if edits.len() == MAX_COST || examined == MAX_CONFIGURATIONS {
+            continue;
+        }
+        let mut push = |states: Vec<usize>, position: usize, edit: Edit| {
+            if visited.insert((states.clone(), position)) {
+                let mut edits = edits.clone();
+                edits.push(edit);
+                queue.push_back((states, position, edits));
+            }
+        };
+        for &terminal in &insertable {
+            let mut inserted = states.clone();
+            if simulate(table, &mut inserted, terminal) == Step::Shifted {
+                push(inserted, position, Edit::Insert(terminal));
+            }
+        }
+        let next = match lookaheads.get(position) {
+            Some(&next) if next != Some(end) => next,
+            _ => continue,
+        };
+        push(states.clone(), position + 1, Edit::Delete);
+        for &terminal in &insertable {
+            let mut replaced = states.clone();
+            if Some(terminal) != next && simulate(table, &mut replaced, terminal) == Step::Shifted {
+                push(replaced, position + 1, Edit::Replace(terminal));
+            }
+        }
+    }
+    None
+}
+
+impl<Symbol: fmt::Display> fmt::Display for Repair<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Repair::Insert(symbol) => write!(f, "inserted {}", symbol),
+            Repair::Delete(token) => write!(f, "deleted {}", token.symbol),
+            Repair::Replace(token, symbol) => {
+                write!(f, "replaced {} with {}", token.symbol, symbol)
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::token::Paren;
+use syn::{
+    braced, parenthesized, Attribute, Error, Expr, Ident, Lit, LitStr, Meta, NestedMeta, Path,
+    Token, Type, Visibility,
+};
+use turn::grammar::{Associativity, ExpectedConflicts};
+
+/// The contents of a `grammar!` invocation.
+pub struct GrammarInput {
+    /// Expected conflicts of the grammar from `#![expect(...)]`.
+    pub expected_conflicts: ExpectedConflicts,
+    /// The token enum named by `use ...;`.
+    pub token_type: Path,
+    /// The error terminal named by `error ...;`.
+    pub error_terminal: Option<Ident>,
+    /// The precedence levels, starting with the highest precedence.
+    pub precedence: Vec<(Associativity, Vec<Ident>)>,
+    /// The display names of terminals in syntax errors.
+    pub names: Vec<(Ident, LitStr)>,
+    pub parser: ParserDeclaration,
+    pub nonterminals: Vec<Nonterminal>,
+}
+
+/// The parser function declared by `pub fn name -> nonterminal;`.
+pub struct ParserDeclaration {
+    /// Doc comments and other attributes of the function.
+    pub attrs: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub name: Ident,
+    /// The starting nonterminal.
+    pub start: Ident,
+    /// The error recovery from `#[recover]` or `#[repair]`, with the span of the attribute.
+    pub recovery: Option<(Recovery, Span)>,
+}
+
+/// How the parser function continues after syntax errors.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Recovery {
+    /// Recover by the rules with the error terminal (`LrParser::parse_with_recovery`).
+    ErrorTerminal,
+    /// Repair the input (`LrParser::parse_with_repair`).
+    Repair,
+}
+
+/// A nonterminal with the type of its value and its alternatives.
+pub struct Nonterminal {
+    pub name: Ident,
+    pub ty: Type,
+    pub alternatives: Vec<Alternative>,
+}
+
+/// A single rule: right-hand symbols and the action computing the value.
+pub struct Alternative {
+    pub symbols: Vec<RuleSymbol>,
+    /// The precedence terminal from `#[precedence(...)]`.
+    pub precedence: Option<Ident>,
+    /// Expected conflicts of the rule from `#[expect(...)]`.
+    pub expected_conflicts: ExpectedConflicts,
+    /// The name bound to the location of the rule by `#[location(...)]`.
+    pub location: Option<Ident>,
+    /// The tokens of the right-hand side, used for error spans.
+    pub tokens: TokenStream,
+    pub action: Expr,
+}
+
+/// A right-hand symbol, optionally bound to a name in the action.
+pub struct RuleSymbol {
+    pub binding: Option<Binding>,
+    pub name: Ident,
+}
+
+/// The names bound to the value of a symbol by `value:symbol`,
+/// or to its value and location by `(value, location):symbol`.
+pub struct Binding {
+    pub value: Ident,
+    pub location: Option<Ident>,
+}
+
+impl Parse for GrammarInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut expected_conflicts = ExpectedConflicts::default();
+        for attr in input.call(Attribute::parse_inner)? {
+            expected_conflicts = parse_expect(&attr, expected_conflicts)?;
+        }
+
+        input.parse::<Token![use]>()?;
+        let token_type = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        let mut error_terminal = None;
+        if input.peek(Ident) && input.fork().parse::<Ident>()? == "error" {
+            input.parse::<Ident>()?;
+            error_terminal = Some(input.parse()?);
+            input.parse::<Token![;]>()?;
+        }
+
+        let mut precedence = vec![];
+        if input.peek(Ident) && input.fork().parse::<Ident>()? == "precedence" {
+            input.parse::<Ident>()?;
+            let content;
+            braced!(content in input);
+            while !content.is_empty() {
+                let keyword: Ident = content.parse()?;
+                let associativity = if keyword == "left" {
+                    Associativity::Left
+                } else if keyword == "right" {
+                    Associativity::Right
+                } else if keyword == "nonassoc" {
+                    Associativity::None
+                } else {
+                    return Err(Error::new(
+                        keyword.span(),
+                        "Expected \"left\", \"right\" or \"nonassoc\".",
+                    ));
+                };
+                let mut terminals = vec![];
+                while !content.peek(Token![;]) {
+                    terminals.push(content.parse()?);
+                }
+                if terminals.is_empty() {
+                    return Err(Error::new(
+                        keyword.span(),
+                        "A precedence level must list at least one terminal.",
+                    ));
+                }
+                content.parse::<Token![;]>()?;
+                precedence.push((associativity, terminals));
+            }
+        }
+
+        let mut names = vec![];
+        if input.peek(Ident) && input.fork().parse::<Ident>()? == "names" {
+            input.parse::<Ident>()?;
+            let content;
+            braced!(content in input);
+            while !content.is_empty() {
+                let terminal: Ident = content.parse()?;
+                content.parse::<Token![:]>()?;
+                let name: LitStr = content.parse()?;
+                if names.iter().any(|(named, _)| *named == terminal) {
+                    return Err(Error::new(
+                        terminal.span(),
+                        "Multiple names for the terminal.",
+                    ));
+                }
+                names.push((terminal, name));
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+        }
+
+        let parser = input.parse()?;
+        let mut nonterminals = vec![];
+        while !input.is_empty() {
+            nonterminals.push(input.parse()?);
+        }
+        Ok(GrammarInput {
+            expected_conflicts,
+            token_type,
+            error_terminal,
+            precedence,
+            names,
+            parser,
+            nonterminals,
+        })
+    }
+}
+
+impl Parse for ParserDeclaration {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let mut recovery = None;
+        for attr in &attrs {
+            let kind = if attr.path.is_ident("recover") {
+                Recovery::ErrorTerminal
+            } else if attr.path.is_ident("repair") {
+                Recovery::Repair
+            } else {
+                continue;
+            };
+            if !attr.tokens.is_empty() {
+                return Err(Error::new(
+                    attr.tokens.span(),
+                    "Expected #[recover] or #[repair] without arguments.",
+                ));
+            }
+            if recovery.is_some() {
+                return Err(Error::new(
+                    attr.path.span(),
+                    "Multiple definitions of #[recover] or #[repair].",
+                ));
+            }
+            recovery = Some((kind, attr.path.span()));
+        }
+        attrs.retain(|attr| !attr.path.is_ident("recover") && !attr.path.is_ident("repair"));
+        let visibility = input.parse()?;
+        input.parse::<Token![fn]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![->]>()?;
+        let start = input.parse()?;
+        input.parse::<Token![;]>()?;
+        Ok(ParserDeclaration {
+            attrs,
+            visibility,
+            name,
+            start,
+            recovery,
+        })
+    }
+}
+
+impl Parse for Nonterminal {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        let content;
+        braced!(content in input);
+        let mut alternatives = vec![];
+        while !content.is_empty() {
+            alternatives.push(content.parse()?);
+        }
+        Ok(Nonterminal {
+            name,
+            ty,
+            alternatives,
+        })
+    }
+}
+
+impl Parse for Alternative {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut precedence = None;
+        let mut location = None;
+        let mut expected_conflicts = ExpectedConflicts::default();
+        for attr in input.call(Attribute::parse_outer)? {
+            if attr.path.is_ident("precedence") {
+                if precedence.is_some() {
+                    return Err(Error::new(
+                        attr.path.span(),
+                        "Multiple definitions of #[precedence(...)].",
+                    ));
+                }
+                precedence = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("location") {
+                if location.is_some() {
+                    return Err(Error::new(
+                        attr.path.span(),
+                        "Multiple definitions of #[location(...)].",
+                    ));
+                }
+                location = Some(attr.parse_args()?);
+            } else {
+                expected_conflicts = parse_expect(&attr, expected_conflicts)?;
+            }
+        }
+
+        let mut symbols = vec![];
+        let mut tokens = TokenStream::new();
+        while !input.peek(Token![=>]) {
+            if input.peek(Paren) {
+                let content;
+                parenthesized!(content in input);
+                let value: Ident = content.parse()?;
+                content.parse::<Token![,]>()?;
+                let location = content.parse()?;
+                let colon = input.parse::<Token![:]>()?;
+                let name: Ident = input.parse()?;
+                value.to_tokens(&mut tokens);
+                colon.to_tokens(&mut tokens);
+                name.to_tokens(&mut tokens);
+                symbols.push(RuleSymbol {
+                    binding: Some(Binding {
+                        value,
+                        location: Some(location),
+                    }),
+                    name,
+                });
+                continue;
+            }
+            let name: Ident = input.parse()?;
+            name.to_tokens(&mut tokens);
+            if input.peek(Token![:]) {
+                let colon = input.parse::<Token![:]>()?;
+                colon.to_tokens(&mut tokens);
+                let symbol: Ident = input.parse()?;
+                symbol.to_tokens(&mut tokens);
+                symbols.push(RuleSymbol {
+                    binding: Some(Binding {
+                        value: name,
+                        location: None,
+                    }),
+                    name: symbol,
+                });
+            } else {
+                symbols.push(RuleSymbol {
+                    binding: None,
+                    name,
+                });
+            }
+        }
+        let arrow = input.parse::<Token![=>]>()?;
+        if symbols.is_empty() {
+            arrow.to_tokens(&mut tokens);
+        }
+
+        let action: Expr = input.parse()?;
+        let is_block = matches!(action, Expr::Block(_));
+        if input.peek(Token![,]) || !(is_block || input.is_empty()) {
+            input.parse::<Token![,]>()?;
+        }
+        Ok(Alternative {
+            symbols,
+            precedence,
+            expected_conflicts,
+            location,
+            tokens,
+            action,
+        })
+    }
+}
+
+/// Parse `#[expect(shift_reduce = n, reduce_reduce = m)]` into the expected conflicts.
+fn parse_expect(
+    attr: &Attribute,
+    mut expected: ExpectedConflicts,
+) -> syn::Result<ExpectedConflicts> {
+    if !attr.path.is_ident("expect") {
+        return Err(Error::new(
+            attr.path.span(),
+            "Unknown attribute, expected #[expect(...)], #[location(...)] or #[precedence(...)].",
+        ));
+    }
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        meta => {
+            return Err(Error::new(
+                meta.span(),
+                "Expected #[expect(shift_reduce = n, reduce_reduce = m)].",
+            ))
+        }
+    };
+    for nested in list.nested {
+        let (ident, count) = match &nested {
+            NestedMeta::Meta(Meta::NameValue(value)) => {
+                match (value.path.get_ident(), &value.lit) {
+                    (Some(ident), Lit::Int(count)) => (ident, count.base10_parse()?),
+                    _ => return Err(invalid_expect(nested.span())),
+                }
+            }
+            _ => return Err(invalid_expect(nested.span())),
+        };
+        if ident == "shift_reduce" {
+            expected.shift_reduce = Some(count);
+        } else if ident == "reduce_reduce" {
+            expected.reduce_reduce = Some(count);
+        } else {
+            return Err(invalid_expect(ident.span()));
+        }
+    }
+    Ok(expected)
+}
+
+fn invalid_expect(span: Span) -> Error {
+    Error::new(
+        span,
+        "Expected \"shift_reduce = n\" or \"reduce_reduce = n\".",
+    )
+}
@@ -0,0 +1,77 @@
+//! The `grammar!` macro: LR parsers with typed semantic actions, with tables computed at compile time.
+//!
+//! ```ignore
+//! grammar! {
+//!     use Token;
+//!
+//!     precedence {
+//!         left Times;
+//!         left Plus Minus;
+//!     }
+//!
+//!     names {
+//!         LParen: "`(`",
+//!         RParen: "`)`",
+//!         Number: "number",
+//!     }
+//!
+//!     /// Evaluate an expression.
+//!     pub fn evaluate -> expr;
+//!
+//!     expr: i64 {
+//!         a:expr Plus b:expr => a + b,
+//!         a:expr Minus b:expr => a - b,
+//!         a:expr Times b:expr => a * b,
+//!         LParen e:expr RParen => e,
+//!         n:Number => n.attribute.parse().unwrap(),
+//!     }
+//! }
+//! ```
+//!
+//! `use` names the token enum (usually a `#[derive(Lexer)]` enum, which has to be `Clone`).
+//! Every identifier in a rule that is not a nonterminal is a variant of the enum.
+//! A bound nonterminal (`a:expr`) is passed to the action as a value of the nonterminal's type,
+//! a bound terminal (`n:Number`) as its `turn::Token`.
+//! `(a, location):expr` binds the value and the `turn::Location` of a symbol, and
+//! `#[location(location)]` before an alternative binds the location of the whole rule
+//! (see `turn::parser::Locations`).
+//! The lifetime `'a` of the input can be used in the types.
+//!
+//! The values of all nonterminals share an enum on the value stack of the parser;
+//! the actions are plain functions called by a `match` on the reduced rule.
+//!
+//! The precedence levels start with the highest precedence, like `Grammar::symbol_precedence`;
+//! `#[precedence(Terminal)]` before an alternative sets the precedence of its rule and
+//! `#[expect(shift_reduce = n, reduce_reduce = m)]` declares its expected conflicts.
+//! `names` gives terminals the names displayed in syntax errors (see `Grammar::terminal_names`).
+//! The same attributes as inner attributes (`#![expect(...)]`) apply to the whole grammar.
+//!
+//! The macro builds the LALR(1) table of the grammar and reports invalid grammars and
+//! conflicts as compile errors at the offending rules.
+//! The declared function parses the tokens of a lexer and returns the value of its nonterminal.
+//!
+//! `error Error;` after `use` declares the error terminal (see `Grammar::error_terminal`),
+//! a variant of the token enum that rules use like yacc's `error` token.
+//! With `#[recover]` the declared function recovers from syntax errors by these rules
+//! (`LrParser::parse_with_recovery`), with `#[repair]` it repairs the input
+//! (`LrParser::parse_with_repair`); both return a `turn::parser::RecoveredParse`.
+//! The error terminal and the terminals of `names` have to be used by the rules.
+
+extern crate proc_macro;
+
+mod grammar_parse;
+mod parser_impl;
+
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream {
+    // parse the rules, actions and declarations of the grammar
+    let input = parse_macro_input!(input as grammar_parse::GrammarInput);
+    // compute the tables and create the parser function
+    match parser_impl::create_parser(&input) {
+        Ok(parser) => parser.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
@@ -0,0 +1,494 @@
+use crate::grammar_parse::{Alternative, GrammarInput, Nonterminal, Recovery};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use std::collections::BTreeMap;
+use std::fmt;
+use syn::{Error, Ident};
+use turn::grammar::{Grammar, GrammarBuilder, GrammarError, Lookahead, Rule, Symbol};
+use turn::lr::{Action, Algorithm, Conflict, ParseTable, TableError};
+
+/// The name of a terminal or a nonterminal of the grammar.
+///
+/// Formats without quotes, so that messages show the symbols as written in the grammar.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+struct Name(String);
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&Ident> for Name {
+    fn from(ident: &Ident) -> Self {
+        Name(ident.to_string())
+    }
+}
+
+/// The grammar of the input with the spans of its symbols and rules.
+struct GrammarInfo<'i> {
+    input: &'i GrammarInput,
+    grammar: Grammar<Name, Name>,
+    /// Index of each nonterminal in `input.nonterminals`.
+    nonterminals: BTreeMap<Name, usize>,
+    /// The terminals used by the rules, in the order of their lookahead indices.
+    terminals: Vec<&'i Ident>,
+    /// The nonterminal and the alternative of each rule.
+    rules: Vec<(&'i Nonterminal, &'i Alternative)>,
+}
+
+/// Create the parser function of a grammar.
+pub fn create_parser(input: &GrammarInput) -> Result<TokenStream, Error> {
+    let info = GrammarInfo::new(input)?;
+    let table = info.table()?;
+    Ok(info.parser_function(&table))
+}
+
+impl<'i> GrammarInfo<'i> {
+    fn new(input: &'i GrammarInput) -> Result<Self, Error> {
+        let mut nonterminals = BTreeMap::new();
+        for (i, nonterminal) in input.nonterminals.iter().enumerate() {
+            if nonterminals
+                .insert(Name::from(&nonterminal.name), i)
+                .is_some()
+            {
+                return Err(Error::new(
+                    nonterminal.name.span(),
+                    format!("Multiple definitions of nonterminal {}.", nonterminal.name),
+                ));
+            }
+        }
+        if !nonterminals.contains_key(&Name::from(&input.parser.start)) {
+            return Err(Error::new(
+                input.parser.start.span(),
+                format!("Undefined nonterminal {}.", input.parser.start),
+            ));
+        }
+
+        let mut terminals: Vec<&Ident> = vec![];
+        let mut rules = vec![];
+        let mut grammar_rules = vec![];
+        for nonterminal in &input.nonterminals {
+            for alternative in &nonterminal.alternatives {
+                let right_hand = alternative
+                    .symbols
+                    .iter()
+                    .map(|symbol| {
+                        let name = Name::from(&symbol.name);
+                        if nonterminals.contains_key(&name) {
+                            Symbol::Nonterminal(name)
+                        } else {
+                            if !terminals.contains(&&symbol.name) {
+                                terminals.push(&symbol.name);
+                            }
+                            Symbol::Terminal(name)
+                        }
+                    })
+                    .collect();
+                let left_hand = Name::from(&nonterminal.name);
+                let rule = match &alternative.precedence {
+                    Some(terminal) => {
+                        Rule::with_precedence(left_hand, right_hand, Name::from(terminal))
+                    }
+                    None => Rule::new(left_hand, right_hand),
+                };
+                grammar_rules.push(rule.expect_conflicts(alternative.expected_conflicts));
+                rules.push((nonterminal, alternative));
+            }
+        }
+        terminals.sort_by_key(|terminal| terminal.to_string());
+
+        // terminals declared outside of the rules must be used by them
+        let unused = |terminal: &Ident, message: &str| {
+            if terminals.contains(&terminal) {
+                None
+            } else {
+                Some(Error::new(
+                    terminal.span(),
+                    format!("{} {} is not used by any rule.", message, terminal),
+                ))
+            }
+        };
+        let mut errors = input
+            .error_terminal
+            .iter()
+            .filter_map(|terminal| unused(terminal, "The error terminal"))
+            .chain(
+                input
+                    .names
+                    .iter()
+                    .filter_map(|(terminal, _)| unused(terminal, "The named terminal")),
+            );
+        if let Some(mut error) = errors.next() {
+            for other in errors {
+                error.combine(other);
+            }
+            return Err(error);
+        }
+        if let (Some((Recovery::ErrorTerminal, span)), None) =
+            (input.parser.recovery, &input.error_terminal)
+        {
+            return Err(Error::new(
+                span,
+                "#[recover] needs an error terminal, declared by `error Terminal;`.",
+            ));
+        }
+
+        let mut builder = GrammarBuilder::default();
+        builder
+            .starting_nonterminal(Name::from(&input.parser.start))
+            .rules(grammar_rules)
+            .symbol_precedence(
+                input
+                    .precedence
+                    .iter()
+                    .map(|(associativity, level)| {
+                        (*associativity, level.iter().map(Name::from).collect())
+                    })
+                    .collect(),
+            )
+            .expected_conflicts(input.expected_conflicts);
+        if let Some(terminal) = &input.error_terminal {
+            builder.error_terminal(Name::from(terminal));
+        }
+        let grammar = builder
+            .build()
+            .map_err(|error| Error::new(Span::call_site(), error))?;
+        Ok(GrammarInfo {
+            input,
+            grammar,
+            nonterminals,
+            terminals,
+            rules,
+        })
+    }
+
+    /// An error at the right-hand side of a rule.
+    fn rule_error(&self, rule: usize, message: impl fmt::Display) -> Error {
+        Error::new_spanned(&self.rules[rule].1.tokens, message)
+    }
+
+    /// An error at the definition of a nonterminal.
+    fn nonterminal_error(&self, nonterminal: &Name, message: impl fmt::Display) -> Error {
+        let span = self.input.nonterminals[self.nonterminals[nonterminal]]
+            .name
+            .span();
+        Error::new(span, message)
+    }
+
+    /// An error at the first declaration of a terminal's precedence.
+    fn precedence_error(&self, terminal: &Name, message: impl fmt::Display) -> Error {
+        let span = self
+            .input
+            .precedence
+            .iter()
+            .flat_map(|(_, level)| level)
+            .find(|ident| Name::from(*ident) == *terminal)
+            .map_or_else(Span::call_site, Ident::span);
+        Error::new(span, message)
+    }
+
+    /// Compute the LALR(1) table, reporting invalid grammars and conflicts at their rules.
+    fn table(&self) -> Result<ParseTable<Name, Name>, Error> {
+        let errors = match ParseTable::new(&self.grammar, Algorithm::Lalr1) {
+            Ok(table) => return Ok(table),
+            Err(TableError::InvalidGrammar(errors)) => errors
+                .iter()
+                .map(|error| match error {
+                    GrammarError::UndefinedNonterminal { rules, .. }
+                    | GrammarError::DuplicateRule { rules } => {
+                        self.rule_error(*rules.last().unwrap(), error)
+                    }
+                    GrammarError::UnreachableNonterminal { nonterminal, .. }
+                    | GrammarError::UnproductiveNonterminal { nonterminal, .. } => {
+                        self.nonterminal_error(nonterminal, error)
+                    }
+                    GrammarError::DuplicatePrecedence { terminal, .. } => {
+                        self.precedence_error(terminal, error)
+                    }
+                    GrammarError::UndefinedRule { .. } => Error::new(Span::call_site(), error),
+                })
+                .collect(),
+            Err(TableError::Conflicts { conflicts, table }) => conflicts
+                .iter()
+                .map(|conflict| {
+                    let message = table.counterexample(conflict).to_string();
+                    self.rule_error(conflict_rule(conflict), message)
+                })
+                .collect(),
+            Err(TableError::UnexpectedConflicts {
+                mismatches,
+                conflicts,
+                table,
+            }) => {
+                let mut errors: Vec<Error> = mismatches
+                    .iter()
+                    .map(|mismatch| match mismatch.rule {
+                        Some(rule) => self.rule_error(rule, mismatch),
+                        None => Error::new(Span::call_site(), mismatch),
+                    })
+                    .collect();
+                errors.extend(conflicts.iter().map(|conflict| {
+                    let message = table.counterexample(conflict).to_string();
+                    self.rule_error(conflict_rule(conflict), message)
+                }));
+                errors
+            }
+        };
+        let mut errors = errors.into_iter();
+        let mut error = errors.next().unwrap();
+        for other in errors {
+            error.combine(other);
+        }
+        Err(error)
+    }
+
+    /// Create the parser function with its static tables and semantic actions.
+    fn parser_function(&self, table: &ParseTable<Name, Name>) -> TokenStream {
+        let token_type = &self.input.token_type;
+        let value_variant = |nonterminal: &Ident| {
+            let index = self.nonterminals[&Name::from(nonterminal)];
+            format_ident!("N{}", index)
+        };
+
+        // the value stack holds tokens and the values of all nonterminals
+        let nonterminal_variants = self.input.nonterminals.iter().map(|nonterminal| {
+            let variant = value_variant(&nonterminal.name);
+            let ty = &nonterminal.ty;
+            quote!(#variant(#ty))
+        });
+
+        // each action is a function of the bound right-hand values and locations
+        let mut action_functions = vec![];
+        let mut reductions = vec![];
+        for (rule, (nonterminal, alternative)) in self.rules.iter().enumerate() {
+            let function = format_ident!("__action{}", rule);
+            let mut parameters = vec![];
+            let mut arguments = vec![];
+            let mut pops = vec![];
+            for (i, symbol) in alternative.symbols.iter().enumerate() {
+                let binding = match &symbol.binding {
+                    Some(binding) => binding,
+                    None => {
+                        pops.push(quote!(values.next();));
+                        continue;
+                    }
+                };
+                let value = &binding.value;
+                let argument = format_ident!("__{}", value);
+                let (ty, variant) = match self.nonterminals.get(&Name::from(&symbol.name)) {
+                    Some(&index) => {
+                        let ty = &self.input.nonterminals[index].ty;
+                        (quote!(#ty), value_variant(&symbol.name))
+                    }
+                    None => (
+                        quote!(::turn::Token<'a, #token_type>),
+                        format_ident!("Token"),
+                    ),
+                };
+                parameters.push(quote!(#value: #ty));
+                arguments.push(quote!(#argument));
+                pops.push(quote! {
+                    let #argument = match values.next() {
+                        Some(__Value::#variant(value)) => value,
+                        _ => unreachable!(),
+                    };
+                });
+                if let Some(location) = &binding.location {
+                    parameters.push(quote!(#location: ::turn::Location));
+                    arguments.push(quote!(locations.right_hand[#i]));
+                }
+            }
+            if let Some(location) = &alternative.location {
+                parameters.push(quote!(#location: ::turn::Location));
+                arguments.push(quote!(locations.rule));
+            }
+            let ty = &nonterminal.ty;
+            let action = &alternative.action;
+            action_functions.push(quote! {
+                #[allow(clippy::needless_lifetimes, clippy::extra_unused_lifetimes)]
+                fn #function<'a>(#(#parameters),*) -> #ty {
+                    #action
+                }
+            });
+            let variant = value_variant(&nonterminal.name);
+            reductions.push(quote! {
+                #rule => {
+                    #(#pops)*
+                    __Value::#variant(#function(#(#arguments),*))
+                }
+            });
+        }
+
+        // the tables, indexed by the terminals and nonterminals of the macro
+        let terminal_paths: Vec<TokenStream> = self
+            .terminals
+            .iter()
+            .map(|terminal| quote_spanned!(terminal.span()=> #token_type::#terminal))
+            .collect();
+        let terminal_indices = 0..terminal_paths.len();
+        let terminal_names = self.terminals.iter().map(|&terminal| {
+            match self.input.names.iter().find(|(named, _)| named == terminal) {
+                Some((_, name)) => quote!(Some(#name)),
+                None => quote!(None),
+            }
+        });
+        let lookaheads: Vec<Lookahead<Name>> = self
+            .terminals
+            .iter()
+            .map(|terminal| Lookahead::Terminal(Name::from(*terminal)))
+            .chain(std::iter::once(Lookahead::End))
+            .collect();
+        let actions = (0..table.state_count()).map(|state| {
+            let actions = lookaheads
+                .iter()
+                .map(|lookahead| match table.action(state, lookahead) {
+                    Some(Action::Shift(target)) => {
+                        quote!(Some(::turn::lr::Action::Shift(#target)))
+                    }
+                    Some(Action::Reduce(rule)) => quote!(Some(::turn::lr::Action::Reduce(#rule))),
+                    Some(Action::Accept) => quote!(Some(::turn::lr::Action::Accept)),
+                    Some(Action::Error) | None => quote!(None),
+                });
+            quote!(&[#(#actions),*])
+        });
+        let gotos = (0..table.state_count()).map(|state| {
+            let gotos = self.input.nonterminals.iter().map(|nonterminal| {
+                match table.goto(state, &Name::from(&nonterminal.name)) {
+                    Some(target) => quote!(Some(#target)),
+                    None => quote!(None),
+                }
+            });
+            quote!(&[#(#gotos),*])
+        });
+        let error_terminal = match &self.input.error_terminal {
+            Some(error) => {
+                // used by the rules, checked by `GrammarInfo::new`
+                let index = self.terminals.iter().position(|&t| t == error).unwrap();
+                quote!(Some(#index))
+            }
+            None => quote!(None),
+        };
+        let rules = self.rules.iter().map(|(nonterminal, alternative)| {
+            let left_hand = self.nonterminals[&Name::from(&nonterminal.name)];
+            let length = alternative.symbols.len();
+            quote!((#left_hand, #length))
+        });
+
+        let parser = &self.input.parser;
+        let attrs = &parser.attrs;
+        let visibility = &parser.visibility;
+        let name = &parser.name;
+        let start = &self.input.nonterminals[self.nonterminals[&Name::from(&parser.start)]];
+        let start_ty = &start.ty;
+        let start_variant = value_variant(&start.name);
+        let (result_ty, parse) = match parser.recovery {
+            None => (
+                quote!(::std::result::Result<#start_ty, ::turn::parser::ParseError<'a, #token_type>>),
+                quote! {
+                    match __parser.parse(lexer, &mut __Actions)? {
+                        __Value::#start_variant(value) => Ok(value),
+                        _ => unreachable!(),
+                    }
+                },
+            ),
+            Some((recovery, _)) => {
+                let method = match recovery {
+                    Recovery::ErrorTerminal => quote!(parse_with_recovery),
+                    Recovery::Repair => quote!(parse_with_repair),
+                };
+                (
+                    quote!(::turn::parser::RecoveredParse<'a, #token_type, #start_ty>),
+                    quote! {
+                        let result = __parser.#method(lexer, &mut __Actions);
+                        ::turn::parser::RecoveredParse {
+                            value: result.value.map(|value| match value {
+                                __Value::#start_variant(value) => value,
+                                _ => unreachable!(),
+                            }),
+                            errors: result.errors,
+                        }
+                    },
+                )
+            }
+        };
+        quote! {
+            #(#attrs)*
+            #visibility fn #name<'a, Lexer>(lexer: Lexer) -> #result_ty
+            where
+                Lexer: ::std::iter::IntoIterator<
+                    Item = ::std::result::Result<
+                        ::turn::Token<'a, #token_type>,
+                        ::turn::LexError<#token_type>,
+                    >,
+                >,
+            {
+                enum __Value<'a> {
+                    Token(::turn::Token<'a, #token_type>),
+                    #(#nonterminal_variants,)*
+                }
+
+                #(#action_functions)*
+
+                struct __Actions;
+
+                impl<'a> ::turn::parser::Actions<'a, #token_type> for __Actions {
+                    type Value = __Value<'a>;
+
+                    #[inline]
+                    fn shift(&mut self, token: ::turn::Token<'a, #token_type>) -> Self::Value {
+                        __Value::Token(token)
+                    }
+
+                    #[allow(unused_variables)]
+                    fn reduce(
+                        &mut self,
+                        rule: usize,
+                        mut values: ::std::vec::Drain<'_, Self::Value>,
+                        locations: ::turn::parser::Locations<'_>,
+                    ) -> Self::Value {
+                        match rule {
+                            #(#reductions)*
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+
+                #[allow(unreachable_patterns)]
+                fn __terminal_index(terminal: &#token_type) -> ::std::option::Option<usize> {
+                    match terminal {
+                        #(#terminal_paths => Some(#terminal_indices),)*
+                        _ => None,
+                    }
+                }
+
+                // the type of the function item, unlike a function pointer,
+                // lets the parser inline the lookup of terminals
+                let __table = ::turn::parser::StaticTable {
+                    terminals: &[#(#terminal_paths),*],
+                    terminal_index: __terminal_index,
+                    actions: &[#(#actions),*],
+                    gotos: &[#(#gotos),*],
+                    rules: &[#(#rules),*],
+                    error_terminal: #error_terminal,
+                    terminal_names: &[#(#terminal_names),*],
+                };
+
+                let __parser = ::turn::parser::LrParser::new(&__table);
+                #parse
+            }
+        }
+    }
+}
+
+/// The rule reported for a conflict: the first rule reduced by it.
+fn conflict_rule<Terminal>(conflict: &Conflict<Terminal>) -> usize {
+    conflict
+        .actions
+        .iter()
+        .find_map(|action| match action {
+            Action::Reduce(rule) => Some(*rule),
+            _ => None,
+        })
+        .expect("every conflict involves a reduction")
+}
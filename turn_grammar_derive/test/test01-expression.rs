@@ -0,0 +1,73 @@
+use turn::parser::ParseError;
+use turn::{LexError, Location, Token};
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+    Minus,
+    Times,
+    LParen,
+    RParen,
+}
+
+fn tokens(input: &str) -> Vec<Result<Token<'_, Tok>, LexError<Tok>>> {
+    input
+        .char_indices()
+        .map(|(i, c)| {
+            let symbol = match c {
+                '+' => Tok::Plus,
+                '-' => Tok::Minus,
+                '*' => Tok::Times,
+                '(' => Tok::LParen,
+                ')' => Tok::RParen,
+                _ => Tok::Number,
+            };
+            let mut location = Location::new();
+            location.span = (i, i + 1);
+            Ok(Token {
+                symbol,
+                attribute: &input[i..i + 1],
+                location,
+            })
+        })
+        .collect()
+}
+
+grammar! {
+    use Tok;
+
+    precedence {
+        right Negate;
+        left Times;
+        left Plus Minus;
+    }
+
+    /// Evaluate an expression.
+    pub fn evaluate -> expr;
+
+    expr: i64 {
+        a:expr Plus b:expr => a + b,
+        a:expr Minus b:expr => a - b,
+        a:expr Times b:expr => a * b,
+        #[precedence(Negate)]
+        Minus a:expr => -a,
+        LParen e:expr RParen => e,
+        n:Number => n.attribute.parse().unwrap(),
+    }
+}
+
+fn main() {
+    assert_eq!(evaluate(tokens("1+2*3")), Ok(7));
+    assert_eq!(evaluate(tokens("(1+2)*3")), Ok(9));
+    assert_eq!(evaluate(tokens("-2*3-4")), Ok(-10));
+    assert_eq!(evaluate(tokens("8-2-1")), Ok(5));
+    match evaluate(tokens("1+*2")) {
+        Err(ParseError::Syntax(error)) => {
+            assert_eq!(error.token.map(|token| token.symbol), Some(Tok::Times));
+            assert_eq!(error.location.span, (2, 3));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
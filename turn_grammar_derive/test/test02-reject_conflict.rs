@@ -0,0 +1,20 @@
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+}
+
+grammar! {
+    use Tok;
+
+    pub fn parse_sum -> sum;
+
+    sum: u32 {
+        a:sum Plus b:sum => a + b,
+        Number => 1,
+    }
+}
+
+fn main() {}
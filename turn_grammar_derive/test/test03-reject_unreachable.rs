@@ -0,0 +1,23 @@
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+}
+
+grammar! {
+    use Tok;
+
+    pub fn parse_number -> number;
+
+    number: u32 {
+        Number => 1,
+    }
+
+    sum: u32 {
+        a:number Plus b:number => a + b,
+    }
+}
+
+fn main() {}
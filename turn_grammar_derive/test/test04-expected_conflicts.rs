@@ -0,0 +1,56 @@
+use turn::{Location, Token};
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    If,
+    Then,
+    Else,
+    Print,
+}
+
+#[derive(PartialEq, Debug)]
+enum Statement<'a> {
+    If(Box<Statement<'a>>, Option<Box<Statement<'a>>>),
+    Print(&'a str),
+}
+
+grammar! {
+    use Tok;
+
+    /// Parse a statement.
+    fn parse_statement -> statement;
+
+    statement: Statement<'a> {
+        // the dangling else binds to the innermost if
+        #[expect(shift_reduce = 1)]
+        If Then s:statement => Statement::If(Box::new(s), None),
+        If Then s:statement Else e:statement => Statement::If(Box::new(s), Some(Box::new(e))),
+        p:Print => Statement::Print(p.attribute),
+    }
+}
+
+fn main() {
+    let input = "ititpep";
+    let tokens = input.char_indices().map(|(i, c)| {
+        let symbol = match c {
+            'i' => Tok::If,
+            't' => Tok::Then,
+            'e' => Tok::Else,
+            _ => Tok::Print,
+        };
+        Ok(Token {
+            symbol,
+            attribute: &input[i..i + 1],
+            location: Location::new(),
+        })
+    });
+    let print = || Box::new(Statement::Print("p"));
+    assert_eq!(
+        parse_statement(tokens),
+        Ok(Statement::If(
+            Box::new(Statement::If(print(), Some(print()))),
+            None
+        ))
+    );
+}
@@ -0,0 +1,19 @@
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+}
+
+grammar! {
+    use Tok;
+
+    pub fn parse_number -> number;
+
+    number: u32 {
+        #[expect(shift_reduce = 1, conflicts = 2)]
+        Number => 1,
+    }
+}
+
+fn main() {}
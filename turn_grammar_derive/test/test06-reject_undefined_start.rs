@@ -0,0 +1,18 @@
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+}
+
+grammar! {
+    use Tok;
+
+    pub fn parse_number -> numbr;
+
+    number: u32 {
+        Number => 1,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,66 @@
+use turn::{Location, Token};
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+    LParen,
+    RParen,
+}
+
+#[derive(PartialEq, Debug)]
+enum Expression<'a> {
+    Sum(Box<Expression<'a>>, Box<Expression<'a>>, (usize, usize)),
+    Number(&'a str, (usize, usize)),
+}
+
+grammar! {
+    use Tok;
+
+    /// Parse a sum.
+    fn parse_sum -> sum;
+
+    sum: Expression<'a> {
+        #[location(location)]
+        a:sum Plus b:term => Expression::Sum(Box::new(a), Box::new(b), location.span),
+        t:term => t,
+    }
+
+    term: Expression<'a> {
+        (n, location):Number => Expression::Number(n.attribute, location.span),
+        // parentheses are not part of the expression
+        LParen (s, location):sum RParen => {
+            assert_eq!(location.span.0, 1);
+            s
+        }
+    }
+}
+
+fn main() {
+    let input = "(1+2)+3";
+    let tokens = input.char_indices().map(|(i, c)| {
+        let symbol = match c {
+            '+' => Tok::Plus,
+            '(' => Tok::LParen,
+            ')' => Tok::RParen,
+            _ => Tok::Number,
+        };
+        let mut location = Location::new();
+        location.span = (i, i + 1);
+        Ok(Token {
+            symbol,
+            attribute: &input[i..i + 1],
+            location,
+        })
+    });
+    let number = |text, start| Box::new(Expression::Number(text, (start, start + 1)));
+    assert_eq!(
+        parse_sum(tokens),
+        Ok(Expression::Sum(
+            Box::new(Expression::Sum(number("1", 1), number("2", 3), (1, 4))),
+            number("3", 6),
+            (0, 7)
+        ))
+    );
+}
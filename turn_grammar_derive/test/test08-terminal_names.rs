@@ -0,0 +1,82 @@
+use std::fmt;
+use turn::{LexError, Location, Token};
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+    Times,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+fn tokens(input: &str) -> Vec<Result<Token<'_, Tok>, LexError<Tok>>> {
+    input
+        .char_indices()
+        .map(|(i, c)| {
+            let symbol = match c {
+                '+' => Tok::Plus,
+                '*' => Tok::Times,
+                '(' => Tok::LParen,
+                ')' => Tok::RParen,
+                _ => Tok::Number,
+            };
+            let mut location = Location::new();
+            location.span = (i, i + 1);
+            Ok(Token {
+                symbol,
+                attribute: &input[i..i + 1],
+                location,
+            })
+        })
+        .collect()
+}
+
+grammar! {
+    use Tok;
+
+    precedence {
+        left Times;
+        left Plus;
+    }
+
+    names {
+        Plus: "`+`",
+        LParen: "`(`",
+        RParen: "`)`",
+        Number: "number",
+    }
+
+    pub fn evaluate -> expr;
+
+    expr: i64 {
+        a:expr Plus b:expr => a + b,
+        a:expr Times b:expr => a * b,
+        LParen e:expr RParen => e,
+        n:Number => n.attribute.parse().unwrap(),
+    }
+}
+
+fn main() {
+    assert_eq!(evaluate(tokens("(1+2)*3")), Ok(9));
+    let message = |input| evaluate(tokens(input)).unwrap_err().to_string();
+    assert_eq!(
+        message("(1+2("),
+        "unexpected `(` at byte 4; expected one of `+`, `)`, Times"
+    );
+    assert_eq!(
+        message("1+*2"),
+        "unexpected Times at byte 2; expected one of `(`, number"
+    );
+    assert_eq!(
+        message("12"),
+        "unexpected number at byte 1; expected one of `+`, Times, end of input"
+    );
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use turn::{LexError, Location, Token};
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Statement,
+    Semicolon,
+    Error,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+fn tokens(input: &str) -> Vec<Result<Token<'_, Tok>, LexError<Tok>>> {
+    input
+        .char_indices()
+        .map(|(i, c)| {
+            let symbol = match c {
+                ';' => Tok::Semicolon,
+                _ => Tok::Statement,
+            };
+            let mut location = Location::new();
+            location.span = (i, i + 1);
+            Ok(Token {
+                symbol,
+                attribute: &input[i..i + 1],
+                location,
+            })
+        })
+        .collect()
+}
+
+grammar! {
+    use Tok;
+    error Error;
+
+    names {
+        Semicolon: "`;`",
+    }
+
+    /// Count the statements and the errors.
+    #[recover]
+    pub fn count -> statements;
+
+    statements: (u32, u32) {
+        s:statements Statement Semicolon => (s.0 + 1, s.1),
+        s:statements Error Semicolon => (s.0, s.1 + 1),
+        => (0, 0),
+    }
+}
+
+grammar! {
+    use Tok;
+
+    /// Count the statements.
+    #[repair]
+    pub fn count_repaired -> statements;
+
+    statements: u32 {
+        s:statements Statement Semicolon => s + 1,
+        => 0,
+    }
+}
+
+fn main() {
+    let result = count(tokens("s;ss;s;"));
+    assert_eq!(result.value, Some((2, 1)));
+    let messages: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(messages, vec!["unexpected Statement at byte 3; expected `;`"]);
+
+    let result = count_repaired(tokens("s;ss;s;"));
+    assert_eq!(result.value, Some(4));
+    let messages: Vec<String> = result.errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec!["unexpected Statement at byte 3; expected Semicolon; inserted Semicolon"]
+    );
+}
@@ -0,0 +1,37 @@
+use turn_grammar_derive::grammar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tok {
+    Number,
+    Plus,
+    Error,
+}
+
+grammar! {
+    use Tok;
+    error Error;
+
+    names {
+        Number: "number",
+        Plus: "`+`",
+    }
+
+    pub fn parse_number -> number;
+
+    number: u32 {
+        Number => 1,
+    }
+}
+
+grammar! {
+    use Tok;
+
+    #[recover]
+    pub fn parse_sum -> sum;
+
+    sum: u32 {
+        Number Plus Number => 2,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+#[test]
+fn tests() {
+    let t = trybuild::TestCases::new();
+    t.pass("test/test01-expression.rs");
+    t.compile_fail("test/test02-reject_conflict.rs");
+    t.compile_fail("test/test03-reject_unreachable.rs");
+    t.pass("test/test04-expected_conflicts.rs");
+    t.compile_fail("test/test05-reject_invalid_attribute.rs");
+    t.compile_fail("test/test06-reject_undefined_start.rs");
+    t.pass("test/test07-locations.rs");
+    t.pass("test/test08-terminal_names.rs");
+    t.pass("test/test09-recovery.rs");
+    t.compile_fail("test/test10-reject_unused_terminals.rs");
+}
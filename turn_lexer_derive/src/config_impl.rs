@@ -0,0 +1,32 @@
+use crate::derive_parse::InputTokenRegexes;
+use proc_macro2::TokenStream;
+use quote::quote;
+use turn_utils::position::{ColumnMode, LineBreaks};
+
+/// Create the constants of the lexer options of an enum.
+pub fn create_config(input: &InputTokenRegexes) -> TokenStream {
+    let name = &input.enum_name;
+    let config = input.config.position_config;
+    let columns = match config.columns {
+        ColumnMode::Chars => quote!(Chars),
+        ColumnMode::Bytes => quote!(Bytes),
+        ColumnMode::Utf16 => quote!(Utf16),
+        ColumnMode::Graphemes => quote!(Graphemes),
+        ColumnMode::DisplayWidth { tab_width } => quote!(DisplayWidth { tab_width: #tab_width }),
+    };
+    let line_breaks = match config.line_breaks {
+        LineBreaks::LineFeed => quote!(LineFeed),
+        LineBreaks::Universal => quote!(Universal),
+    };
+    quote! {
+        impl #name {
+            /// The row and column counting of the lexer, set by `#[lexer(columns = ...,
+            /// tab_width = ..., line_breaks = ...)]`. Files registered with it by
+            /// `SourceMap::add_file_with_config` resolve the spans of tokens accordingly.
+            pub const POSITION_CONFIG: ::turn::PositionConfig = ::turn::PositionConfig {
+                columns: ::turn::ColumnMode::#columns,
+                line_breaks: ::turn::LineBreaks::#line_breaks,
+            };
+        }
+    }
+}
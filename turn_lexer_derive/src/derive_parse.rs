@@ -2,9 +2,12 @@ extern crate proc_macro;
 
 use proc_macro2::Span;
 use std::collections::BTreeMap;
+use syn::spanned::Spanned;
 use syn::{
-    Attribute, Data, DataEnum, DeriveInput, Error, Fields, Ident, Lit, Meta, MetaNameValue, Variant,
+    Attribute, Data, DataEnum, DeriveInput, Error, Fields, Ident, Lit, Meta, MetaNameValue,
+    NestedMeta, Variant,
 };
+use turn_utils::position::{ColumnMode, LineBreaks, PositionConfig};
 
 pub struct RegexValue {
     pub span: Span,
@@ -16,12 +19,21 @@ pub enum Regex {
     Regex(RegexValue),
 }
 
+/// Options specified with `#[lexer(...)]` at enum scope.
+pub struct LexerConfig {
+    /// The row and column counting configuration of the lexer.
+    pub position_config: PositionConfig,
+}
+
 pub struct InputTokenRegexes {
     pub enum_name: Ident,
     pub skip_regex: RegexValue,
+    pub config: LexerConfig,
     pub variants: BTreeMap<Ident, Vec<Regex>>,
 }
 
+const DEFAULT_TAB_WIDTH: usize = 8;
+
 pub fn parse(input: DeriveInput) -> Result<InputTokenRegexes, syn::Error> {
     let default_skip = RegexValue {
         span: Span::call_site(),
@@ -33,9 +45,12 @@ pub fn parse(input: DeriveInput) -> Result<InputTokenRegexes, syn::Error> {
     let skip_regex = get_skip_regex(&input.attrs)?.unwrap_or(default_skip);
     // get regex and tokens for all enum items
     let variants = get_variants(data)?;
+    // get the lexer options
+    let config = get_lexer_config(&input.attrs)?;
     Ok(InputTokenRegexes {
         enum_name: input.ident,
         skip_regex,
+        config,
         variants,
     })
 }
@@ -124,6 +139,159 @@ fn get_skip_regex(attrs: &[Attribute]) -> Result<Option<RegexValue>, Error> {
     Ok(skip_regex)
 }
 
+/// Get the options of `#[lexer(...)]` attributes at enum scope.
+///
+/// Options are either flags (`#[lexer(option)]`) or values (`#[lexer(option = ...)]`).
+fn get_lexer_options(attrs: &[Attribute]) -> Result<Vec<Meta>, Error> {
+    let mut options: Vec<Meta> = vec![];
+    for attr in attrs {
+        if !attr.path.is_ident("lexer") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            _ => {
+                return Err(Error::new(
+                    attr.path.get_ident().unwrap().span(),
+                    "Lexer options must be in the format #[lexer(option = ...)].",
+                ))
+            }
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(option @ Meta::NameValue(_))
+                | NestedMeta::Meta(option @ Meta::Path(_))
+                    if option.path().get_ident().is_some() =>
+                {
+                    let ident = option.path().get_ident().unwrap();
+                    if options.iter().any(|x| x.path().is_ident(ident)) {
+                        return Err(Error::new(
+                            ident.span(),
+                            format!("Multiple definitions of #[lexer({})].", ident),
+                        ));
+                    }
+                    options.push(option);
+                }
+                NestedMeta::Meta(meta) => {
+                    return Err(Error::new(
+                        meta.path().span(),
+                        "Lexer options must be in the format #[lexer(option = ...)].",
+                    ))
+                }
+                NestedMeta::Lit(lit) => {
+                    return Err(Error::new(
+                        lit.span(),
+                        "Lexer options must be in the format #[lexer(option = ...)].",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn get_lexer_config(attrs: &[Attribute]) -> Result<LexerConfig, Error> {
+    let mut columns = None;
+    let mut tab_width = None;
+    let mut line_breaks = None;
+    for option in get_lexer_options(attrs)? {
+        let ident = option.path().get_ident().unwrap();
+        match option {
+            Meta::NameValue(ref value) if ident == "columns" => {
+                columns = Some(retreive_str(ident, value)?);
+            }
+            Meta::NameValue(ref value) if ident == "tab_width" => {
+                tab_width = Some((ident.span(), retreive_usize(ident, value)?));
+            }
+            Meta::NameValue(ref value) if ident == "line_breaks" => {
+                line_breaks = Some(retreive_str(ident, value)?);
+            }
+            // no lexer is generated yet that could continue after an error
+            _ if ident == "collect_errors" || ident == "recovery" => {
+                let option = match option {
+                    Meta::NameValue(_) => format!("{} = ...", ident),
+                    _ => ident.to_string(),
+                };
+                return Err(Error::new(
+                    ident.span(),
+                    format!(
+                        "#[lexer({})] is not supported: #[derive(Lexer)] does not generate \
+                         a lexer yet.",
+                        option
+                    ),
+                ));
+            }
+            Meta::Path(_)
+                if ident == "columns" || ident == "tab_width" || ident == "line_breaks" =>
+            {
+                return Err(Error::new(
+                    ident.span(),
+                    format!(
+                        "The {} option must be defined as #[lexer({} = ...)].",
+                        ident, ident
+                    ),
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    ident.span(),
+                    format!("Unknown lexer option {}.", ident),
+                ))
+            }
+        }
+    }
+    let columns = match columns {
+        None => ColumnMode::Chars,
+        Some(value) => match value.regex.as_str() {
+            "chars" => ColumnMode::Chars,
+            "bytes" => ColumnMode::Bytes,
+            "utf16" => ColumnMode::Utf16,
+            "graphemes" => ColumnMode::Graphemes,
+            "display_width" => ColumnMode::DisplayWidth {
+                tab_width: DEFAULT_TAB_WIDTH,
+            },
+            _ => {
+                return Err(Error::new(
+                    value.span,
+                    "Column mode must be one of \"chars\", \"bytes\", \"utf16\", \
+                     \"graphemes\" or \"display_width\".",
+                ))
+            }
+        },
+    };
+    let columns = match (columns, tab_width) {
+        (ColumnMode::DisplayWidth { .. }, Some((_, tab_width))) => {
+            ColumnMode::DisplayWidth { tab_width }
+        }
+        (_, Some((span, _))) => {
+            return Err(Error::new(
+                span,
+                "#[lexer(tab_width = ...)] requires #[lexer(columns = \"display_width\")].",
+            ))
+        }
+        (columns, None) => columns,
+    };
+    let line_breaks = match line_breaks {
+        None => LineBreaks::LineFeed,
+        Some(value) => match value.regex.as_str() {
+            "line_feed" => LineBreaks::LineFeed,
+            "universal" => LineBreaks::Universal,
+            _ => {
+                return Err(Error::new(
+                    value.span,
+                    "Line breaks must be one of \"line_feed\" or \"universal\".",
+                ))
+            }
+        },
+    };
+    Ok(LexerConfig {
+        position_config: PositionConfig {
+            columns,
+            line_breaks,
+        },
+    })
+}
+
 fn get_variants(data: &DataEnum) -> Result<BTreeMap<Ident, Vec<Regex>>, Error> {
     let mut result = BTreeMap::new();
     for variant in &data.variants {
@@ -166,6 +334,19 @@ fn get_variant(variant: &Variant) -> Result<(Ident, Vec<Regex>), Error> {
                     attr.path.get_ident().unwrap().span(),
                     "#[skip = ...] must be specified at enum level.",
                 ));
+            } else if ident == "lexer" {
+                // no lexer is generated yet that could emit error tokens
+                if is_error_attribute(attr)? {
+                    return Err(Error::new(
+                        variant.ident.span(),
+                        "#[lexer(error)] is not supported: #[derive(Lexer)] does not generate \
+                         a lexer yet.",
+                    ));
+                }
+                return Err(Error::new(
+                    attr.path.get_ident().unwrap().span(),
+                    "#[lexer(...)] options must be specified at enum level.",
+                ));
             }
         } else {
             continue;
@@ -174,6 +355,18 @@ fn get_variant(variant: &Variant) -> Result<(Ident, Vec<Regex>), Error> {
     Ok((variant.ident.clone(), regex))
 }
 
+/// Checks whether a variant attribute is exactly `#[lexer(error)]`.
+fn is_error_attribute(attr: &Attribute) -> Result<bool, Error> {
+    match attr.parse_meta()? {
+        Meta::List(list) => Ok(list.nested.len() == 1
+            && match list.nested.first() {
+                Some(NestedMeta::Meta(Meta::Path(path))) => path.is_ident("error"),
+                _ => false,
+            }),
+        _ => Ok(false),
+    }
+}
+
 fn retreive_str(attr: &Ident, value: &MetaNameValue) -> Result<RegexValue, Error> {
     match value.lit {
         Lit::Str(ref lit) => Ok(RegexValue {
@@ -190,3 +383,13 @@ fn retreive_str(attr: &Ident, value: &MetaNameValue) -> Result<RegexValue, Error
         )),
     }
 }
+
+fn retreive_usize(attr: &Ident, value: &MetaNameValue) -> Result<usize, Error> {
+    match value.lit {
+        Lit::Int(ref lit) => lit.base10_parse(),
+        _ => Err(Error::new(
+            value.path.get_ident().unwrap().span(),
+            format!("Attribute {} must be an integer literal.", attr),
+        )),
+    }
+}
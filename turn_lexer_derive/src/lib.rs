@@ -3,6 +3,7 @@
 extern crate proc_macro;
 
 mod automata;
+mod config_impl;
 mod derive_parse;
 mod matchers;
 mod set_ordering;
@@ -15,7 +16,7 @@ use syn::{parse_macro_input, DeriveInput};
 //use syn::{parse_macro_input, DeriveInput, Ident, Data, Fields, Type,PathArguments, GenericArgument,
 //    Attribute, Meta, Lit};
 
-#[proc_macro_derive(Lexer, attributes(skip, token, regex))]
+#[proc_macro_derive(Lexer, attributes(lexer, skip, token, regex))]
 pub fn derive(input: TokenStream) -> TokenStream {
     // parse the derive input and process all attributes
     let input = match derive_parse::parse(parse_macro_input!(input as DeriveInput)) {
@@ -29,5 +30,5 @@ pub fn derive(input: TokenStream) -> TokenStream {
     };
     // create turn::Lexer implementation for this enum
     //lexer_impl::create_implementation(automaton)
-    TokenStream::new()
+    config_impl::create_config(&input).into()
 }
@@ -0,0 +1,60 @@
+use turn::{ColumnMode, LineBreaks, PositionConfig};
+use turn_lexer_derive::Lexer;
+use turn_utils::position::Position;
+use turn_utils::source_map::{SourceMap, Span};
+
+#[derive(Lexer)]
+#[lexer(columns = "utf16", line_breaks = "universal")]
+enum Foo {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(columns = "display_width")]
+#[lexer(tab_width = 4)]
+enum Bar {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+enum Baz {
+    #[token = "one"]
+    One,
+}
+
+fn main() {
+    assert_eq!(
+        Foo::POSITION_CONFIG,
+        PositionConfig {
+            columns: ColumnMode::Utf16,
+            line_breaks: LineBreaks::Universal,
+        }
+    );
+    assert_eq!(
+        Bar::POSITION_CONFIG,
+        PositionConfig {
+            columns: ColumnMode::DisplayWidth { tab_width: 4 },
+            line_breaks: LineBreaks::LineFeed,
+        }
+    );
+    assert_eq!(Baz::POSITION_CONFIG, PositionConfig::default());
+
+    let mut map = SourceMap::new();
+    let foo = map.add_file_with_config("foo", "💣\rone", Foo::POSITION_CONFIG);
+    let bar = map.add_file_with_config("bar", "\tone", Bar::POSITION_CONFIG);
+    let baz = map.add_file_with_config("baz", "💣\rone", Baz::POSITION_CONFIG);
+    assert_eq!(
+        map.span_positions(Span::new(foo, 5, 8)),
+        Some(Position { row: 2, col: 1, index: 5 }..Position { row: 2, col: 4, index: 8 })
+    );
+    assert_eq!(
+        map.position(bar, 1),
+        Some(Position { row: 1, col: 5, index: 1 })
+    );
+    assert_eq!(
+        map.position(baz, 5),
+        Some(Position { row: 1, col: 3, index: 5 })
+    );
+}
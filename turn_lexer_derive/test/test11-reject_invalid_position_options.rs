@@ -0,0 +1,38 @@
+use turn_lexer_derive::Lexer;
+
+#[derive(Lexer)]
+#[lexer(columns = "words")]
+enum Foo {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(columns = "utf16", tab_width = 4)]
+enum Bar {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(line_breaks = "universal", line_breaks = "line_feed")]
+enum Baz {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(tabs = 4)]
+enum Qux {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+enum Quux {
+    #[lexer(columns = "bytes")]
+    #[token = "one"]
+    One,
+}
+
+fn main() {}
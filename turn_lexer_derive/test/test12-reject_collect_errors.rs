@@ -0,0 +1,10 @@
+use turn_lexer_derive::Lexer;
+
+#[derive(Lexer)]
+#[lexer(collect_errors, columns = "utf16")]
+enum Foo {
+    #[token = "one"]
+    One,
+}
+
+fn main() {}
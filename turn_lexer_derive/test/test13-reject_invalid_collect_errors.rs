@@ -0,0 +1,25 @@
+use turn_lexer_derive::Lexer;
+
+#[derive(Lexer)]
+#[lexer(collect_errors = true)]
+enum Foo {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(collect_errors)]
+#[lexer(collect_errors)]
+enum Bar {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(columns)]
+enum Baz {
+    #[token = "one"]
+    One,
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use turn_lexer_derive::Lexer;
+
+#[derive(Lexer)]
+#[lexer(recovery = "skip_to_token")]
+enum Foo {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(columns = "utf16", recovery = "skip_char")]
+enum Bar {
+    #[token = "one"]
+    One,
+}
+
+#[derive(Lexer)]
+#[lexer(recovery)]
+enum Baz {
+    #[token = "one"]
+    One,
+}
+
+fn main() {}
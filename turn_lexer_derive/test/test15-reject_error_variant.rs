@@ -0,0 +1,27 @@
+use turn_lexer_derive::Lexer;
+
+#[derive(Lexer)]
+enum Foo {
+    #[token = "one"]
+    One,
+    #[lexer(error)]
+    Error,
+}
+
+#[derive(Lexer)]
+#[lexer(recovery = "error_token")]
+enum Bar {
+    #[token = "one"]
+    One,
+    #[lexer(error)]
+    Error,
+}
+
+#[derive(Lexer)]
+enum Baz {
+    #[lexer(skip)]
+    #[token = "one"]
+    One,
+}
+
+fn main() {}
@@ -10,4 +10,10 @@ fn tests() {
     t.compile_fail("test/test07-reject_invalid_skip.rs");
     t.compile_fail("test/test08-reject_multiple_skip.rs");
     t.compile_fail("test/test09-reject_invalid_regex.rs");
+    t.pass("test/test10-position_options.rs");
+    t.compile_fail("test/test11-reject_invalid_position_options.rs");
+    t.compile_fail("test/test12-reject_collect_errors.rs");
+    t.compile_fail("test/test13-reject_invalid_collect_errors.rs");
+    t.compile_fail("test/test14-reject_recovery.rs");
+    t.compile_fail("test/test15-reject_error_variant.rs");
 }
@@ -2,5 +2,6 @@ pub mod matchers;
 pub mod position;
 pub mod regex;
 pub mod set_ordering;
+pub mod source_map;
 pub mod text_reader;
 pub mod token;
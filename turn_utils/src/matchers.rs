@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Matcher {
     /// Matches a literal character.
@@ -97,3 +99,65 @@ impl CharacterCategory {
         }
     }
 }
+
+impl fmt::Display for Matcher {
+    /// Formats the matcher for use in human-readable messages.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::matchers::{CharacterCategory, Matcher, SingleMatcher};
+    /// let matcher = Matcher::NegatedSet(vec![
+    ///     SingleMatcher::Character('"'),
+    ///     SingleMatcher::Category(CharacterCategory::ASCIIDigit),
+    /// ]);
+    /// assert_eq!(matcher.to_string(), "any character except '\"', <0-9>");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::SingleMatcher(matcher) => matcher.fmt(f),
+            Matcher::NegatedSet(set) => {
+                write!(f, "any character")?;
+                for (i, matcher) in set.iter().enumerate() {
+                    let separator = if i == 0 { " except " } else { ", " };
+                    write!(f, "{}{}", separator, matcher)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for SingleMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SingleMatcher::Character(c) => write!(f, "{:?}", c),
+            SingleMatcher::Category(category) => category.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for CharacterCategory {
+    /// Formats the category as its built-in regex category name.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CharacterCategory::*;
+
+        let name = match self {
+            ASCIILowercase => "<a-z>",
+            ASCIIUppercase => "<A-Z>",
+            ASCIIAlpha => "<a-Z>",
+            ASCIIBinaryDigit => "<0b>",
+            ASCIIDigit => "<0-9>",
+            ASCIIHexDigit => "<0x>",
+            ASCIIAlphanumeric => "<0-Z>",
+            ASCIIWhitespace => "< >",
+            Utf8Lowercase => "<lower>",
+            Utf8Uppercase => "<upper>",
+            Utf8Alpha => "<alpha>",
+            Utf8Numeric => "<digit>",
+            Utf8Alphanumeric => "<alnum>",
+            Utf8Whitespace => "<whitespace>",
+            Any => "any character",
+        };
+        write!(f, "{}", name)
+    }
+}
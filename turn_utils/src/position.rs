@@ -1,3 +1,6 @@
+use unicode_segmentation::GraphemeCursor;
+use unicode_width::UnicodeWidthChar;
+
 /// A position in an input string.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct Position {
@@ -45,6 +48,135 @@ impl Position {
         }
         self.index += character.len_utf8();
     }
+
+    /// Advance the position after reading a specific character,
+    /// counting rows and columns according to a configuration.
+    ///
+    /// `next` is the character following the read character; it is used to recognize `\r\n`
+    /// as a single line break. `continues_grapheme` must be set when the read character
+    /// extends the grapheme cluster of the previous character,
+    /// and is only used by `ColumnMode::Graphemes`.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::position::{ColumnMode, LineBreaks, Position, PositionConfig};
+    /// let config = PositionConfig {
+    ///     columns: ColumnMode::Utf16,
+    ///     line_breaks: LineBreaks::Universal,
+    /// };
+    /// let mut position = Position::new();
+    /// position.advance_with('💣', Some('\r'), false, config);
+    /// assert_eq!(position, Position { row: 1, col: 3, index: 4 });
+    /// position.advance_with('\r', Some('\n'), false, config);
+    /// assert_eq!(position, Position { row: 1, col: 3, index: 5 });
+    /// position.advance_with('\n', None, false, config);
+    /// assert_eq!(position, Position { row: 2, col: 1, index: 6 });
+    /// ```
+    pub fn advance_with(
+        &mut self,
+        character: char,
+        next: Option<char>,
+        continues_grapheme: bool,
+        config: PositionConfig,
+    ) {
+        let universal = config.line_breaks == LineBreaks::Universal;
+        if character == '\n' || (universal && character == '\r' && next != Some('\n')) {
+            self.row += 1;
+            self.col = 1;
+        } else if !(universal && character == '\r') {
+            // the carriage return of "\r\n" does not occupy a column
+            self.col = config
+                .columns
+                .advance(self.col, character, continues_grapheme);
+        }
+        self.index += character.len_utf8();
+    }
+}
+
+/// The unit in which columns are counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColumnMode {
+    /// Every unicode scalar value occupies a single column.
+    #[default]
+    Chars,
+    /// Columns are counted in utf-8 bytes.
+    Bytes,
+    /// Columns are counted in utf-16 code units, as required by the Language Server Protocol.
+    Utf16,
+    /// Every extended grapheme cluster occupies a single column.
+    Graphemes,
+    /// Columns are counted as displayed by a terminal or an editor.
+    ///
+    /// Wide characters occupy two columns, combining characters and other zero-width characters
+    /// occupy none, and tabs advance to the next tab stop.
+    DisplayWidth {
+        /// The distance between two tab stops.
+        tab_width: usize,
+    },
+}
+
+impl ColumnMode {
+    /// Compute the column after a character read at a specific column.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::position::ColumnMode;
+    /// let display = ColumnMode::DisplayWidth { tab_width: 4 };
+    /// assert_eq!(display.advance(1, '界', false), 3);
+    /// assert_eq!(display.advance(3, '\t', false), 5);
+    /// assert_eq!(display.advance(5, '\t', false), 9);
+    /// assert_eq!(ColumnMode::Bytes.advance(1, '界', false), 4);
+    /// assert_eq!(ColumnMode::Graphemes.advance(2, '\u{301}', true), 2);
+    /// ```
+    pub fn advance(self, col: usize, character: char, continues_grapheme: bool) -> usize {
+        match self {
+            ColumnMode::Chars => col + 1,
+            ColumnMode::Bytes => col + character.len_utf8(),
+            ColumnMode::Utf16 => col + character.len_utf16(),
+            ColumnMode::Graphemes => {
+                if continues_grapheme {
+                    col
+                } else {
+                    col + 1
+                }
+            }
+            ColumnMode::DisplayWidth { tab_width } => {
+                if character == '\t' && tab_width > 0 {
+                    (col - 1) / tab_width * tab_width + tab_width + 1
+                } else {
+                    col + character.width().unwrap_or(0)
+                }
+            }
+        }
+    }
+}
+
+/// The character sequences recognized as line breaks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineBreaks {
+    /// Only `\n` breaks lines.
+    #[default]
+    LineFeed,
+    /// `\n`, `\r\n` and a lone `\r` break lines.
+    Universal,
+}
+
+/// The configuration of row and column counting.
+///
+/// The default configuration counts a column per character and only breaks lines on `\n`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PositionConfig {
+    /// The unit in which columns are counted.
+    pub columns: ColumnMode,
+    /// The character sequences recognized as line breaks.
+    pub line_breaks: LineBreaks,
+}
+
+/// Determine whether a grapheme cluster starts at an index of an input.
+pub(crate) fn is_grapheme_boundary(input: &str, index: usize) -> bool {
+    GraphemeCursor::new(index, input.len(), true)
+        .is_boundary(input, 0)
+        .unwrap_or(true)
 }
 
 impl Default for Position {
@@ -87,4 +219,63 @@ mod tests {
             }
         );
     }
+
+    fn advance_all(input: &str, config: PositionConfig) -> Position {
+        let mut position = Position::new();
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            position.advance_with(c, chars.peek().copied(), false, config);
+        }
+        position
+    }
+
+    #[test]
+    fn advance_columns() {
+        let input = "a界💣\t";
+        let columns = |columns| {
+            advance_all(
+                input,
+                PositionConfig {
+                    columns,
+                    ..Default::default()
+                },
+            )
+            .col
+        };
+        assert_eq!(columns(ColumnMode::Chars), 5);
+        assert_eq!(columns(ColumnMode::Bytes), 10);
+        assert_eq!(columns(ColumnMode::Utf16), 6);
+        assert_eq!(columns(ColumnMode::Graphemes), 5);
+        assert_eq!(columns(ColumnMode::DisplayWidth { tab_width: 8 }), 9);
+        assert_eq!(columns(ColumnMode::DisplayWidth { tab_width: 0 }), 6);
+    }
+
+    #[test]
+    fn advance_line_breaks() {
+        let input = "a\r\nb\rc\n\rd";
+        let line_feed = advance_all(input, Default::default());
+        assert_eq!(
+            line_feed,
+            Position {
+                row: 3,
+                col: 3,
+                index: 9
+            }
+        );
+        let universal = advance_all(
+            input,
+            PositionConfig {
+                line_breaks: LineBreaks::Universal,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            universal,
+            Position {
+                row: 5,
+                col: 2,
+                index: 9
+            }
+        );
+    }
 }
@@ -0,0 +1,517 @@
+use crate::position::{is_grapheme_boundary, ColumnMode, LineBreaks, Position, PositionConfig};
+use std::ops::Range;
+
+/// An identifier of a file registered in a `SourceMap`.
+///
+/// The default identifier is that of the first registered file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(usize);
+
+impl FileId {
+    /// The index of the file in its source map.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A byte range inside a single file of a `SourceMap`.
+///
+/// Spans only store byte offsets; rows and columns are computed on demand by the source map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    /// The file the span belongs to.
+    pub file: FileId,
+    /// The byte offset of the first character of the span.
+    pub start: usize,
+    /// The byte offset after the last character of the span.
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new span inside a file.
+    #[inline]
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Span { file, start, end }
+    }
+
+    /// The byte range of the span.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Create the smallest span covering both spans.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::source_map::SourceMap;
+    /// # use turn_utils::source_map::Span;
+    /// let mut map = SourceMap::new();
+    /// let file = map.add_file("a", "a + b");
+    /// let span = Span::new(file, 0, 1).to(Span::new(file, 4, 5));
+    /// assert_eq!(span, Span::new(file, 0, 5));
+    /// ```
+    pub fn to(self, other: Span) -> Span {
+        debug_assert_eq!(self.file, other.file);
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// A single source file with a precomputed index of line starts.
+///
+/// Rows and columns are counted according to the position configuration of the file,
+/// as a `TextReader` with the same configuration counts them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    config: PositionConfig,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    /// Create a new source file with the default position configuration and index its lines.
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        SourceFile::with_config(name, source, Default::default())
+    }
+
+    /// Create a new source file counting rows and columns according to a configuration
+    /// and index its lines.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::source_map::SourceFile;
+    /// # use turn_utils::position::{ColumnMode, LineBreaks, Position, PositionConfig};
+    /// let config = PositionConfig {
+    ///     columns: ColumnMode::Utf16,
+    ///     line_breaks: LineBreaks::Universal,
+    /// };
+    /// let file = SourceFile::with_config("file", "💣x\ry", config);
+    /// assert_eq!(file.line(1), Some("💣x"));
+    /// assert_eq!(file.position(4), Some(Position { row: 1, col: 3, index: 4 }));
+    /// assert_eq!(file.position(6), Some(Position { row: 2, col: 1, index: 6 }));
+    /// assert_eq!(file.offset(1, 4), Some(5));
+    /// assert_eq!(file.offset(1, 2), None);
+    /// ```
+    pub fn with_config(
+        name: impl Into<String>,
+        source: impl Into<String>,
+        config: PositionConfig,
+    ) -> Self {
+        let source = source.into();
+        let universal = config.line_breaks == LineBreaks::Universal;
+        let line_starts = std::iter::once(0)
+            .chain(
+                source
+                    .char_indices()
+                    .filter(|&(i, c)| {
+                        c == '\n' || (universal && c == '\r' && !source[i + 1..].starts_with('\n'))
+                    })
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        SourceFile {
+            name: name.into(),
+            source,
+            config,
+            line_starts,
+        }
+    }
+
+    /// The name of the file.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The contents of the file.
+    #[inline]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The row and column counting configuration of the file.
+    #[inline]
+    pub fn config(&self) -> PositionConfig {
+        self.config
+    }
+
+    /// The number of lines in the file.
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Get the contents of a line, without its line terminator.
+    ///
+    /// Lines are numbered from 1.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::source_map::SourceFile;
+    /// let file = SourceFile::new("file", "first\r\nsecond\n");
+    /// assert_eq!(file.line(1), Some("first"));
+    /// assert_eq!(file.line(2), Some("second"));
+    /// assert_eq!(file.line(3), Some(""));
+    /// assert_eq!(file.line(4), None);
+    /// ```
+    pub fn line(&self, row: usize) -> Option<&str> {
+        let range = self.line_range(row)?;
+        Some(self.strip_line_terminator(&self.source[range]))
+    }
+
+    /// Get the byte range of a line, including its line terminator.
+    fn line_range(&self, row: usize) -> Option<Range<usize>> {
+        let index = row.checked_sub(1)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        Some(start..end)
+    }
+
+    /// Compute the position of a byte offset in the file.
+    ///
+    /// Returns `None` when the offset lies past the end of the file.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::source_map::SourceFile;
+    /// # use turn_utils::position::Position;
+    /// let file = SourceFile::new("file", "a€\nbc");
+    /// assert_eq!(file.position(4), Some(Position { row: 1, col: 3, index: 4 }));
+    /// assert_eq!(file.position(6), Some(Position { row: 2, col: 2, index: 6 }));
+    /// assert_eq!(file.position(8), None);
+    /// ```
+    pub fn position(&self, offset: usize) -> Option<Position> {
+        if offset > self.source.len() {
+            return None;
+        }
+        // the last line starting at or before the offset
+        let index = match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+        let start = Position {
+            row: index + 1,
+            col: 1,
+            index: self.line_starts[index],
+        };
+        // an offset inside of a character is counted after the character
+        let end = (offset..)
+            .find(|&i| self.source.is_char_boundary(i))
+            .expect("the end of the file is a character boundary");
+        let position = self.positions(start, end).last().unwrap_or(start);
+        Some(Position {
+            index: offset,
+            ..position
+        })
+    }
+
+    /// Compute the byte offset of a row and column in the file.
+    ///
+    /// The column may point one character past the end of the line.
+    /// Returns `None` when the position lies outside of the file.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::source_map::SourceFile;
+    /// let file = SourceFile::new("file", "a€\nbc");
+    /// assert_eq!(file.offset(1, 3), Some(4));
+    /// assert_eq!(file.offset(2, 3), Some(7));
+    /// assert_eq!(file.offset(2, 4), None);
+    /// assert_eq!(file.offset(3, 1), None);
+    /// ```
+    pub fn offset(&self, row: usize, col: usize) -> Option<usize> {
+        let range = self.line_range(row)?;
+        let line = self.strip_line_terminator(&self.source[range.clone()]);
+        let start = Position {
+            row,
+            col: 1,
+            index: range.start,
+        };
+        self.positions(start, range.start + line.len())
+            .find(|position| position.col >= col)
+            .filter(|position| position.col == col)
+            .map(|position| position.index)
+    }
+
+    /// The positions at `start` and after every character from `start` to the byte offset `end`.
+    fn positions(&self, start: Position, end: usize) -> impl Iterator<Item = Position> + '_ {
+        let source = &self.source;
+        let config = self.config;
+        let graphemes = config.columns == ColumnMode::Graphemes;
+        let mut position = start;
+        std::iter::once(start).chain(source[start.index..end].chars().map(move |c| {
+            let next = source[position.index + c.len_utf8()..].chars().next();
+            let continues_grapheme = graphemes && !is_grapheme_boundary(source, position.index);
+            position.advance_with(c, next, continues_grapheme, config);
+            position
+        }))
+    }
+
+    /// Strip the line break terminating a line.
+    ///
+    /// A lone `\r` only terminates lines with universal line breaks.
+    fn strip_line_terminator<'s>(&self, line: &'s str) -> &'s str {
+        match line.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None if self.config.line_breaks == LineBreaks::Universal => {
+                line.strip_suffix('\r').unwrap_or(line)
+            }
+            None => line,
+        }
+    }
+}
+
+/// A collection of source files.
+///
+/// Files are identified by `FileId`s and positions inside them are stored as byte offsets.
+/// Rows and columns are only computed when requested.
+///
+/// # Example
+/// ```
+/// # use turn_utils::source_map::{SourceMap, Span};
+/// # use turn_utils::position::Position;
+/// let mut map = SourceMap::new();
+/// let main = map.add_file("main.json", "{\n  \"a\": 1\n}");
+/// let other = map.add_file("other.json", "[]");
+///
+/// assert_eq!(map.name(main), "main.json");
+/// assert_eq!(map.source(other), "[]");
+///
+/// let span = Span::new(main, 4, 7);
+/// assert_eq!(map.slice(span), "\"a\"");
+/// assert_eq!(
+///     map.span_positions(span),
+///     Some(Position { row: 2, col: 3, index: 4 }..Position { row: 2, col: 6, index: 7 })
+/// );
+/// assert_eq!(map.offset(main, 2, 3), Some(4));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Create an empty source map.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register a new file and return its identifier.
+    pub fn add_file(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        self.add_file_with_config(name, source, Default::default())
+    }
+
+    /// Register a new file counting rows and columns according to a configuration
+    /// and return its identifier.
+    pub fn add_file_with_config(
+        &mut self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+        config: PositionConfig,
+    ) -> FileId {
+        self.files
+            .push(SourceFile::with_config(name, source, config));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Get a registered file.
+    ///
+    /// # Panics
+    /// Panics if the identifier was not created by this source map.
+    #[inline]
+    pub fn file(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    /// Iterate over all registered files.
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i), file))
+    }
+
+    /// The name of a registered file.
+    #[inline]
+    pub fn name(&self, file: FileId) -> &str {
+        self.file(file).name()
+    }
+
+    /// The contents of a registered file.
+    #[inline]
+    pub fn source(&self, file: FileId) -> &str {
+        self.file(file).source()
+    }
+
+    /// The slice of the input a span covers.
+    #[inline]
+    pub fn slice(&self, span: Span) -> &str {
+        &self.source(span.file)[span.range()]
+    }
+
+    /// Compute the position of a byte offset in a file.
+    #[inline]
+    pub fn position(&self, file: FileId, offset: usize) -> Option<Position> {
+        self.file(file).position(offset)
+    }
+
+    /// Compute the starting and ending positions of a span.
+    pub fn span_positions(&self, span: Span) -> Option<Range<Position>> {
+        let file = self.file(span.file);
+        Some(file.position(span.start)?..file.position(span.end)?)
+    }
+
+    /// Compute the byte offset of a row and column in a file.
+    #[inline]
+    pub fn offset(&self, file: FileId, row: usize, col: usize) -> Option<usize> {
+        self.file(file).offset(row, col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_lookup() {
+        let file = SourceFile::new("test", "ℝb\n\n💣x\r\n\r");
+        assert_eq!(file.line_count(), 4);
+        assert_eq!(file.line(0), None);
+        assert_eq!(file.line(1), Some("ℝb"));
+        assert_eq!(file.line(2), Some(""));
+        assert_eq!(file.line(3), Some("💣x"));
+        assert_eq!(file.line(4), Some("\r"));
+        assert_eq!(file.offset(3, 3), Some(11));
+        assert_eq!(file.offset(3, 4), None);
+    }
+
+    #[test]
+    fn positions_match_text_reader() {
+        use crate::text_reader::TextReader;
+
+        let inputs = [
+            "ℝb\n\n💣x\nlast",
+            "a\r\n\rb\t界\r\re\u{301}x\n",
+            "\t\u{1F1E8}\u{1F1FF}y",
+        ];
+        let columns = [
+            ColumnMode::Chars,
+            ColumnMode::Bytes,
+            ColumnMode::Utf16,
+            ColumnMode::Graphemes,
+            ColumnMode::DisplayWidth { tab_width: 4 },
+        ];
+        for &input in &inputs {
+            for &columns in &columns {
+                for &line_breaks in &[LineBreaks::LineFeed, LineBreaks::Universal] {
+                    let config = PositionConfig {
+                        columns,
+                        line_breaks,
+                    };
+                    let file = SourceFile::with_config("test", input, config);
+                    let mut reader = TextReader::with_config(input, config);
+                    let mut previous = None;
+                    loop {
+                        let position = reader.current_position();
+                        assert_eq!(file.position(position.index), Some(position));
+                        let line_start = file.offset(position.row, 1).unwrap();
+                        let line = file.line(position.row).unwrap();
+                        // several characters may start at the same column,
+                        // and line terminators have no columns
+                        if previous != Some((position.row, position.col))
+                            && position.index <= line_start + line.len()
+                        {
+                            assert_eq!(
+                                file.offset(position.row, position.col),
+                                Some(position.index),
+                                "{:?} {:?}",
+                                input,
+                                config
+                            );
+                        }
+                        previous = Some((position.row, position.col));
+                        if reader.next().is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn universal_line_breaks() {
+        let config = PositionConfig {
+            columns: ColumnMode::Chars,
+            line_breaks: LineBreaks::Universal,
+        };
+        let file = SourceFile::with_config("test", "a\rb\r\nc\n\r", config);
+        assert_eq!(file.line_count(), 5);
+        assert_eq!(file.line(1), Some("a"));
+        assert_eq!(file.line(2), Some("b"));
+        assert_eq!(file.line(3), Some("c"));
+        assert_eq!(file.line(4), Some(""));
+        assert_eq!(file.line(5), Some(""));
+        assert_eq!(file.offset(2, 2), Some(3));
+        assert_eq!(file.offset(3, 1), Some(5));
+        assert_eq!(
+            file.position(4),
+            Some(Position {
+                row: 2,
+                col: 2,
+                index: 4
+            })
+        );
+    }
+
+    #[test]
+    fn multiple_files() {
+        let mut map = SourceMap::new();
+        let first = map.add_file("first", "abc");
+        let second = map.add_file("second", "\nxyz");
+        assert_ne!(first, second);
+        assert_eq!(
+            map.files()
+                .map(|(id, file)| (id, file.name()))
+                .collect::<Vec<_>>(),
+            vec![(first, "first"), (second, "second")]
+        );
+        assert_eq!(
+            map.position(first, 2),
+            Some(Position {
+                row: 1,
+                col: 3,
+                index: 2
+            })
+        );
+        assert_eq!(
+            map.position(second, 2),
+            Some(Position {
+                row: 2,
+                col: 2,
+                index: 2
+            })
+        );
+        assert_eq!(map.slice(Span::new(second, 1, 4)), "xyz");
+    }
+
+    #[test]
+    fn offsets_outside_of_file() {
+        let file = SourceFile::new("test", "ab\ncd");
+        assert_eq!(file.position(6), None);
+        assert_eq!(file.offset(1, 0), None);
+        assert_eq!(file.offset(1, 3), Some(2));
+        assert_eq!(file.offset(1, 4), None);
+        assert_eq!(file.offset(0, 1), None);
+    }
+}
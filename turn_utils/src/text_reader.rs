@@ -1,4 +1,4 @@
-use crate::position::Position;
+use crate::position::{is_grapheme_boundary, ColumnMode, Position, PositionConfig};
 use std::ops::Range;
 use std::str::Chars;
 
@@ -29,12 +29,36 @@ pub struct TextReader<'a> {
     peek: Option<char>,
     iter: Chars<'a>,
     position: Position,
+    config: PositionConfig,
 }
 
 impl<'a> TextReader<'a> {
     /// Create a new TextReader from an input slice.
     #[inline]
     pub fn new(input: &str) -> TextReader {
+        TextReader::with_config(input, Default::default())
+    }
+
+    /// Create a new TextReader from an input slice,
+    /// counting rows and columns according to a configuration.
+    ///
+    /// # Example
+    /// ```
+    /// # use turn_utils::text_reader::TextReader;
+    /// # use turn_utils::position::{ColumnMode, LineBreaks, Position, PositionConfig};
+    /// let config = PositionConfig {
+    ///     columns: ColumnMode::Utf16,
+    ///     line_breaks: LineBreaks::Universal,
+    /// };
+    /// let mut reader = TextReader::with_config("💣\r\nx", config);
+    /// reader.next();
+    /// assert_eq!(reader.current_position(), Position { row: 1, col: 3, index: 4 });
+    /// reader.next();
+    /// reader.next();
+    /// assert_eq!(reader.current_position(), Position { row: 2, col: 1, index: 6 });
+    /// ```
+    #[inline]
+    pub fn with_config(input: &'a str, config: PositionConfig) -> TextReader<'a> {
         let mut iter = input.chars();
         let peek = iter.next();
         TextReader {
@@ -42,9 +66,16 @@ impl<'a> TextReader<'a> {
             peek,
             iter,
             position: Default::default(),
+            config,
         }
     }
 
+    /// Get the row and column counting configuration of this reader.
+    #[inline]
+    pub fn config(&self) -> PositionConfig {
+        self.config
+    }
+
     /// Peek the next character from the input.
     ///
     /// # Example
@@ -64,10 +95,13 @@ impl<'a> TextReader<'a> {
     /// Read the next character from the input.
     fn read_next(&mut self) -> Option<char> {
         let next = self.peek;
-        if let Some(c) = self.peek {
-            self.position.advance(c);
-        }
         self.peek = self.iter.next();
+        if let Some(c) = next {
+            let continues_grapheme = self.config.columns == ColumnMode::Graphemes
+                && !is_grapheme_boundary(self.input, self.position.index);
+            self.position
+                .advance_with(c, self.peek, continues_grapheme, self.config);
+        }
         next
     }
 
@@ -221,6 +255,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reader_grapheme_positions() {
+        let config = PositionConfig {
+            columns: ColumnMode::Graphemes,
+            ..Default::default()
+        };
+        // "e" followed by a combining acute accent, and a flag made of two regional indicators
+        let mut reader = TextReader::with_config("e\u{301}🇨🇿x", config);
+        let columns: Vec<_> = std::iter::from_fn(|| {
+            reader.next()?;
+            Some(reader.current_position().col)
+        })
+        .collect();
+        assert_eq!(columns, vec![2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn reader_display_positions() {
+        let config = PositionConfig {
+            columns: ColumnMode::DisplayWidth { tab_width: 4 },
+            ..Default::default()
+        };
+        let mut reader = TextReader::with_config("\t界\tx", config);
+        let columns: Vec<_> = std::iter::from_fn(|| {
+            reader.next()?;
+            Some(reader.current_position().col)
+        })
+        .collect();
+        assert_eq!(columns, vec![5, 7, 9, 10]);
+    }
+
     #[test]
     fn read_input_slices() {
         let mut reader = TextReader::new("abcℝb💣def");